@@ -24,6 +24,7 @@ const WHITELISTED_FUNCS: &[&str] = &[
     "battery_get_current",
     "battery_get_temperature",
     "battery_get_voltage",
+    "competition_get_status",
     "controller_clear",
     "controller_clear_line",
     "controller_get_analog",
@@ -51,10 +52,35 @@ const WHITELISTED_FUNCS: &[&str] = &[
     "ext_adi_gyro_get",
     "ext_adi_gyro_reset",
     "ext_adi_gyro_shutdown",
+    "ext_adi_motor_get",
+    "ext_adi_motor_set",
+    "ext_adi_motor_stop",
     "ext_adi_port_set_config",
     "ext_adi_ultrasonic_get",
     "ext_adi_ultrasonic_init",
     "ext_adi_ultrasonic_shutdown",
+    "fclose",
+    "ferror",
+    "fflush",
+    "fopen",
+    "fread",
+    "fseek",
+    "ftell",
+    "fwrite",
+    "gps_get_accel",
+    "gps_get_error",
+    "gps_get_gyro_rate",
+    "gps_get_heading",
+    "gps_get_heading_raw",
+    "gps_get_offset",
+    "gps_get_rotation",
+    "gps_get_status",
+    "gps_initialize_full",
+    "gps_set_data_rate",
+    "gps_set_offset",
+    "gps_set_position",
+    "gps_set_rotation",
+    "gps_tare_rotation",
     "imu_reset",
     "imu_get_rotation",
     "imu_get_heading",
@@ -79,6 +105,18 @@ const WHITELISTED_FUNCS: &[&str] = &[
     "imu_set_pitch",
     "imu_set_roll",
     "imu_set_yaw",
+    "lcd_initialize",
+    "lcd_read_buttons",
+    "lcd_set_text",
+    "link_clear_receive_buf",
+    "link_connected",
+    "link_init",
+    "link_raw_receivable_size",
+    "link_raw_transmittable_size",
+    "link_receive",
+    "link_receive_raw",
+    "link_transmit",
+    "link_transmit_raw",
     "micros",
     "motor_get_actual_velocity",
     "motor_get_brake_mode",
@@ -118,6 +156,18 @@ const WHITELISTED_FUNCS: &[&str] = &[
     "mutex_recursive_create",
     "mutex_recursive_give",
     "mutex_recursive_take",
+    "optical_disable_gesture",
+    "optical_enable_gesture",
+    "optical_get_brightness",
+    "optical_get_gesture",
+    "optical_get_gesture_raw",
+    "optical_get_hue",
+    "optical_get_led_pwm",
+    "optical_get_proximity",
+    "optical_get_raw",
+    "optical_get_rgb",
+    "optical_get_saturation",
+    "optical_set_led_pwm",
     "registry_get_plugged_type",
     "rotation_get_angle",
     "rotation_get_position",
@@ -128,6 +178,20 @@ const WHITELISTED_FUNCS: &[&str] = &[
     "rotation_reverse",
     "rotation_set_position",
     "rotation_set_reversed",
+    "screen_draw_circle",
+    "screen_draw_line",
+    "screen_draw_pixel",
+    "screen_draw_rect",
+    "screen_erase",
+    "screen_fill_circle",
+    "screen_fill_rect",
+    "screen_get_eraser",
+    "screen_get_pen",
+    "screen_print",
+    "screen_print_at",
+    "screen_set_eraser",
+    "screen_set_pen",
+    "screen_touch_status",
     "sem_create",
     "sem_delete",
     "sem_get_count",
@@ -153,16 +217,44 @@ const WHITELISTED_FUNCS: &[&str] = &[
     "task_get_state",
     "task_notify",
     "task_notify_take",
+    "usd_is_installed",
+    "vision_clear_led",
+    "vision_create_color_code",
+    "vision_get_exposure",
+    "vision_get_object_count",
+    "vision_get_signature",
+    "vision_get_white_balance",
+    "vision_read_by_code",
+    "vision_read_by_sig",
+    "vision_read_by_size",
+    "vision_set_auto_white_balance",
+    "vision_set_exposure",
+    "vision_set_led",
+    "vision_set_signature",
+    "vision_set_white_balance",
+    "vision_set_wifi_mode",
+    "vision_set_zero_point",
 ];
 
 // Variables to be included by bindgen
 const WHITELISTED_VARS: &[&str] = &[
+    "COMPETITION_AUTONOMOUS",
+    "COMPETITION_CONNECTED",
+    "COMPETITION_DISABLED",
+    "COMPETITION_SYSTEM",
     "INTERNAL_ADI_PORT",
+    "LCD_BTN_CENTER",
+    "LCD_BTN_LEFT",
+    "LCD_BTN_RIGHT",
     "PROS_ERR_",
     "PROS_ERR_F_",
+    "SEEK_CUR",
+    "SEEK_END",
+    "SEEK_SET",
     "TASK_PRIORITY_DEFAULT",
     "TASK_PRIORITY_MAX",
     "TASK_STACK_DEPTH_DEFAULT",
+    "VISION_OBJECT_ERR_SIG",
 ];
 
 fn main() -> Result<(), io::Error> {
@@ -171,6 +263,11 @@ fn main() -> Result<(), io::Error> {
     println!("cargo:rerun-if-changed={}", PROS_ZIP_STR);
     println!("cargo:rerun-if-changed={}", PROS_WRAPPER_STR);
 
+    // the simulator provides its own bindings
+    if env::var_os("CARGO_FEATURE_SIM").is_some() {
+        return Ok(());
+    }
+
     // define input paths
     let pros_zip_path = PathBuf::from(PROS_ZIP_STR);
     let wrapper_h_path = PathBuf::from(PROS_WRAPPER_STR);
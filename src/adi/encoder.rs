@@ -1,6 +1,7 @@
 use crate::{
     bindings,
     error::{get_errno, Error},
+    hal::PositionSensor,
     rtos::DataSource,
 };
 
@@ -59,6 +60,19 @@ impl DataSource for AdiEncoder {
     }
 }
 
+impl PositionSensor for AdiEncoder {
+    type Error = AdiEncoderError;
+
+    fn position(&self) -> Result<f64, Self::Error> {
+        // There is one tick per degree.
+        Ok(self.get()? as f64)
+    }
+
+    fn reset_position(&mut self) -> Result<(), Self::Error> {
+        self.reset()
+    }
+}
+
 impl Drop for AdiEncoder {
     fn drop(&mut self) {
         if let bindings::PROS_ERR_ = unsafe { bindings::ext_adi_encoder_shutdown(self.port) } {
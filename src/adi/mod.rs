@@ -6,6 +6,7 @@ mod digital_out;
 mod encoder;
 mod expander;
 mod gyro;
+mod motor;
 mod port;
 mod ultrasonic;
 
@@ -15,5 +16,6 @@ pub use digital_out::*;
 pub use encoder::*;
 pub use expander::*;
 pub use gyro::*;
+pub use motor::*;
 pub use port::*;
 pub use ultrasonic::*;
@@ -0,0 +1,159 @@
+use crate::{
+    bindings,
+    error::{get_errno, Error},
+    hal::VoltageActuator,
+    rtos::{time_since_start, Instant},
+};
+
+/// A struct which represents a V5 ADI port configured as a legacy 3-wire motor
+/// (such as a 393 motor on a Motor Controller 29).
+///
+/// An optional slew limit caps how quickly the commanded speed may change. The
+/// output only moves towards the target when [`AdiMotor::set()`] is called, so
+/// a slew-limited motor should be set regularly, such as from a control loop.
+pub struct AdiMotor {
+    port: u8,
+    expander_port: u8,
+    slew_limit: Option<f64>,
+    output: f64,
+    updated: Instant,
+}
+
+impl AdiMotor {
+    /// Initializes a legacy 3-wire motor on an ADI port.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it allows the user to create multiple
+    /// mutable references to the same ADI motor. You likely want to implement
+    /// [`Robot::new()`](crate::robot::Robot::new()) instead.
+    pub unsafe fn new(port: u8, expander_port: u8) -> Result<Self, AdiMotorError> {
+        match bindings::ext_adi_port_set_config(
+            expander_port,
+            port,
+            bindings::adi_port_config_e_E_ADI_LEGACY_PWM,
+        ) {
+            bindings::PROS_ERR_ => Err(AdiMotorError::from_errno()),
+            _ => Ok(Self {
+                port,
+                expander_port,
+                slew_limit: None,
+                output: 0.0,
+                updated: time_since_start(),
+            }),
+        }
+    }
+
+    /// Sets the speed of the motor, from -127 to 127.
+    ///
+    /// With a slew limit, the speed moves towards the given value by at most
+    /// the amount allowed since the previous call.
+    pub fn set(&mut self, speed: i8) -> Result<(), AdiMotorError> {
+        let now = time_since_start();
+        let target = speed.max(-127) as f64;
+        let output = match self.slew_limit {
+            Some(limit) => {
+                let dt = now
+                    .checked_sub_instant(self.updated)
+                    .map_or(0.0, |d| d.as_secs_f64());
+                let step = limit * dt;
+                self.output + (target - self.output).clamp(-step, step)
+            }
+            None => target,
+        };
+        self.write(output)?;
+        self.updated = now;
+        Ok(())
+    }
+
+    /// Stops the motor immediately, regardless of any slew limit.
+    pub fn stop(&mut self) -> Result<(), AdiMotorError> {
+        match unsafe { bindings::ext_adi_motor_stop(self.expander_port, self.port) } {
+            bindings::PROS_ERR_ => Err(AdiMotorError::from_errno()),
+            _ => {
+                self.output = 0.0;
+                self.updated = time_since_start();
+                Ok(())
+            }
+        }
+    }
+
+    /// Gets the speed last sent to the motor, from -127 to 127.
+    pub fn get(&self) -> Result<i8, AdiMotorError> {
+        match unsafe { bindings::ext_adi_motor_get(self.expander_port, self.port) } {
+            bindings::PROS_ERR_ => Err(AdiMotorError::from_errno()),
+            x => Ok(x as i8),
+        }
+    }
+
+    /// Sets the maximum rate at which the speed may change, in units of speed
+    /// per second, or `None` to apply speeds immediately. For example, a limit
+    /// of 254 takes one second to go from full reverse to full forward. Panics
+    /// if the limit is not positive.
+    pub fn set_slew_limit(&mut self, limit: Option<f64>) {
+        if let Some(limit) = limit {
+            assert!(limit > 0.0, "slew limit must be positive");
+        }
+        self.slew_limit = limit;
+    }
+
+    /// Gets the slew limit of the motor; see [`AdiMotor::set_slew_limit()`].
+    pub fn get_slew_limit(&self) -> Option<f64> {
+        self.slew_limit
+    }
+
+    fn write(&mut self, output: f64) -> Result<(), AdiMotorError> {
+        // Round to the nearest speed.
+        let speed = if output < 0.0 {
+            output - 0.5
+        } else {
+            output + 0.5
+        } as i8;
+        match unsafe { bindings::ext_adi_motor_set(self.expander_port, self.port, speed) } {
+            bindings::PROS_ERR_ => Err(AdiMotorError::from_errno()),
+            _ => {
+                self.output = output;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl VoltageActuator for AdiMotor {
+    type Error = AdiMotorError;
+
+    fn set_voltage(&mut self, voltage: i32) -> Result<(), Self::Error> {
+        self.set((voltage.clamp(-12000, 12000) * 127 / 12000) as i8)
+    }
+}
+
+/// Represents possible errors for ADI motor operations.
+#[derive(Debug)]
+pub enum AdiMotorError {
+    /// Port is out of range (1-8).
+    PortOutOfRange,
+    /// Port cannot be configured as an ADI motor.
+    PortNotAdiMotor,
+    /// Unknown error.
+    Unknown(i32),
+}
+
+impl AdiMotorError {
+    fn from_errno() -> Self {
+        match get_errno() {
+            libc::ENXIO => Self::PortOutOfRange,
+            libc::EADDRINUSE => Self::PortNotAdiMotor,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl From<AdiMotorError> for Error {
+    fn from(err: AdiMotorError) -> Self {
+        match err {
+            AdiMotorError::PortOutOfRange => Error::Custom("port is out of range".into()),
+            AdiMotorError::PortNotAdiMotor => Error::Custom("port is not an adi motor".into()),
+            AdiMotorError::Unknown(n) => Error::System(n),
+        }
+    }
+}
@@ -2,8 +2,8 @@ use core::cmp::Ordering;
 
 use super::{
     AdiAnalog, AdiAnalogError, AdiDigitalInput, AdiDigitalInputError, AdiDigitalOutput,
-    AdiDigitalOutputError, AdiEncoder, AdiEncoderError, AdiGyro, AdiGyroError, AdiUltrasonic,
-    AdiUltrasonicError,
+    AdiDigitalOutputError, AdiEncoder, AdiEncoderError, AdiGyro, AdiGyroError, AdiMotor,
+    AdiMotorError, AdiUltrasonic, AdiUltrasonicError,
 };
 
 use crate::bindings;
@@ -68,6 +68,12 @@ impl AdiPort {
         (self, multiplier).try_into()
     }
 
+    /// Turns this port into a legacy 3-wire motor.
+    #[inline]
+    pub fn into_adi_motor(self) -> Result<AdiMotor, AdiMotorError> {
+        self.try_into()
+    }
+
     /// Turns this and another port into an ADI ultrasonic sensor.
     #[inline]
     pub fn into_adi_ultrasonic(self, bottom: Self) -> Result<AdiUltrasonic, AdiUltrasonicError> {
@@ -154,6 +160,15 @@ impl TryFrom<(AdiPort, f64)> for AdiGyro {
     }
 }
 
+impl TryFrom<AdiPort> for AdiMotor {
+    type Error = AdiMotorError;
+
+    /// Converts a `AdiPort` into a [`AdiMotor`].
+    fn try_from(port: AdiPort) -> Result<Self, Self::Error> {
+        unsafe { AdiMotor::new(port.port, port.expander_port) }
+    }
+}
+
 impl TryFrom<(AdiPort, AdiPort)> for AdiUltrasonic {
     type Error = AdiUltrasonicError;
 
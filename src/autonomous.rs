@@ -0,0 +1,206 @@
+//! Selection of autonomous routines.
+//!
+//! An [`AutonomousSelector`] holds a robot's autonomous routines by name. The
+//! driver picks one on the controller screen before the match, and the default
+//! implementation of [`Robot::autonomous()`](crate::robot::Robot::autonomous())
+//! runs it.
+
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{fmt::Write, time::Duration};
+
+use crate::{
+    controller::{Controller, Screen, ScreenPage},
+    io::eprintln,
+    rtos::{Context, Loop},
+    sd::File,
+    select,
+};
+
+/// How often the selection menu checks the controller buttons.
+const MENU_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// An autonomous routine for a robot of type `R`.
+pub type AutonomousRoutine<R> = Arc<dyn Fn(&mut R, Context) + Send + Sync>;
+
+/// A registry of autonomous routines, one of which is selected.
+///
+/// ```
+/// # use vex_rt::prelude::*;
+/// struct Bot {
+///     controller: Controller,
+///     selector: AutonomousSelector<Self>,
+/// }
+///
+/// impl Robot for Bot {
+///     fn new(p: Peripherals) -> Self {
+///         Bot {
+///             controller: p.master_controller,
+///             selector: AutonomousSelector::new()
+///                 .persist("auton.txt")
+///                 .routine("left", |_bot, _ctx| { /* ... */ })
+///                 .routine("right", |_bot, _ctx| { /* ... */ }),
+///         }
+///     }
+///
+///     fn autonomous_selector(&self) -> Option<&AutonomousSelector<Self>> {
+///         Some(&self.selector)
+///     }
+///
+///     fn disabled(&mut self, ctx: Context) {
+///         self.selector.run_menu(&mut self.controller, ctx);
+///     }
+/// }
+/// ```
+pub struct AutonomousSelector<R: ?Sized> {
+    routines: Vec<(String, AutonomousRoutine<R>)>,
+    selected: usize,
+    path: Option<String>,
+    saved: Option<String>,
+}
+
+impl<R: ?Sized> AutonomousSelector<R> {
+    /// Creates an empty selector.
+    pub fn new() -> Self {
+        Self {
+            routines: Vec::new(),
+            selected: 0,
+            path: None,
+            saved: None,
+        }
+    }
+
+    /// Adds a routine with the given name. The first routine added is selected
+    /// unless another was restored by [`AutonomousSelector::persist()`].
+    pub fn routine(
+        mut self,
+        name: &str,
+        f: impl Fn(&mut R, Context) + Send + Sync + 'static,
+    ) -> Self {
+        if self.saved.as_deref() == Some(name) {
+            self.selected = self.routines.len();
+        }
+        self.routines.push((name.to_string(), Arc::new(f)));
+        self
+    }
+
+    /// Saves the selection to a file on the SD card whenever it changes, so
+    /// that it survives the program restarting, and restores the selection
+    /// previously saved to the file, if any.
+    pub fn persist(mut self, path: &str) -> Self {
+        let mut contents = String::new();
+        if let Ok(mut file) = File::open(path) {
+            if file.read_to_string(&mut contents).is_ok() {
+                let name = contents.trim();
+                if let Some(index) = self.routines.iter().position(|(n, _)| n == name) {
+                    self.selected = index;
+                }
+                self.saved = Some(name.to_string());
+            }
+        }
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Gets the names of the routines, in the order they were added.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.routines.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Gets the name of the selected routine, or `None` if there are no
+    /// routines.
+    pub fn selected(&self) -> Option<&str> {
+        self.routines
+            .get(self.selected)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Gets the selected routine, or `None` if there are no routines.
+    pub fn selected_routine(&self) -> Option<AutonomousRoutine<R>> {
+        self.routines.get(self.selected).map(|(_, f)| f.clone())
+    }
+
+    /// Selects the routine with the given name, returning `false` if there is
+    /// no such routine.
+    pub fn select(&mut self, name: &str) -> bool {
+        match self.routines.iter().position(|(n, _)| n == name) {
+            Some(index) => {
+                self.set_selected(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Shows the selected routine on the controller screen, and lets the
+    /// driver change it with the left and right buttons, until the context is
+    /// cancelled. Intended to be called from
+    /// [`Robot::initialize()`](crate::robot::Robot::initialize()) or
+    /// [`Robot::disabled()`](crate::robot::Robot::disabled()).
+    pub fn run_menu(&mut self, controller: &mut Controller, ctx: Context) {
+        let mut l = Loop::new(MENU_POLL_INTERVAL);
+        // Buttons held when the menu opens are ignored until released.
+        let mut was_pressed = (true, true);
+        self.show(&mut controller.screen);
+        loop {
+            let pressed = (
+                controller.left.is_pressed().unwrap_or(false),
+                controller.right.is_pressed().unwrap_or(false),
+            );
+            let len = self.routines.len();
+            if len > 0 {
+                if pressed.0 && !was_pressed.0 {
+                    self.set_selected((self.selected + len - 1) % len);
+                    self.show(&mut controller.screen);
+                } else if pressed.1 && !was_pressed.1 {
+                    self.set_selected((self.selected + 1) % len);
+                    self.show(&mut controller.screen);
+                }
+            }
+            was_pressed = pressed;
+
+            select! {
+                _ = ctx.done() => break,
+                _ = l.select() => continue,
+            }
+        }
+    }
+
+    fn set_selected(&mut self, index: usize) {
+        self.selected = index;
+        if let (Some(path), Some(name)) = (&self.path, self.selected()) {
+            if let Err(err) =
+                File::create(path).and_then(|mut file| file.write_all(name.as_bytes()))
+            {
+                eprintln!("failed to save autonomous selection: {:?}", err);
+            }
+        }
+    }
+
+    fn show(&self, screen: &mut Screen) {
+        let mut page = ScreenPage::new();
+        match self.selected() {
+            Some(name) => {
+                let _ = write!(
+                    page.line(0),
+                    "Auton {}/{}",
+                    self.selected + 1,
+                    self.routines.len()
+                );
+                page.set_line(1, name);
+                page.set_line(2, "<-               ->");
+            }
+            None => page.set_line(0, "No autons"),
+        }
+        screen.show(&page);
+    }
+}
+
+impl<R: ?Sized> Default for AutonomousSelector<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,115 @@
+//! API for querying the competition status of the robot.
+//!
+//! The status is reported by a field controller or competition switch, if one
+//! is connected. Without one, the robot is always enabled in driver control.
+
+use core::time::Duration;
+
+use crate::{
+    bindings,
+    once::Once,
+    rtos::{Broadcast, BroadcastListener, Task},
+};
+
+/// How often the status is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Represents the competition status of the robot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompetitionStatus {
+    /// Whether the robot is disabled.
+    pub disabled: bool,
+    /// Whether the robot is in the autonomous period.
+    pub autonomous: bool,
+    /// The system the robot is connected to, if any.
+    pub system: Option<CompetitionSystem>,
+}
+
+impl CompetitionStatus {
+    /// Gets the current competition status.
+    pub fn get() -> Self {
+        let status = unsafe { bindings::competition_get_status() } as u32;
+        Self {
+            disabled: status & bindings::COMPETITION_DISABLED != 0,
+            autonomous: status & bindings::COMPETITION_AUTONOMOUS != 0,
+            system: if status & bindings::COMPETITION_CONNECTED == 0 {
+                None
+            } else if status & bindings::COMPETITION_SYSTEM != 0 {
+                Some(CompetitionSystem::FieldControl)
+            } else {
+                Some(CompetitionSystem::CompetitionSwitch)
+            },
+        }
+    }
+
+    #[inline]
+    /// Checks whether the robot is connected to a field controller or
+    /// competition switch.
+    pub fn is_connected(&self) -> bool {
+        self.system.is_some()
+    }
+}
+
+/// Represents the kind of system controlling the competition status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompetitionSystem {
+    /// A VEXnet field controller.
+    FieldControl,
+    /// A competition switch.
+    CompetitionSwitch,
+}
+
+#[inline]
+/// Gets the current competition status; see [`CompetitionStatus::get()`].
+pub fn status() -> CompetitionStatus {
+    CompetitionStatus::get()
+}
+
+#[inline]
+/// Checks whether the robot is disabled.
+pub fn is_disabled() -> bool {
+    status().disabled
+}
+
+#[inline]
+/// Checks whether the robot is in the autonomous period.
+pub fn is_autonomous() -> bool {
+    status().autonomous
+}
+
+#[inline]
+/// Checks whether the robot is connected to a field controller or competition
+/// switch.
+pub fn is_connected() -> bool {
+    status().is_connected()
+}
+
+/// Creates a [`BroadcastListener`] which receives the competition status
+/// whenever it changes.
+///
+/// The first call starts a background task which checks the status every 10
+/// milliseconds for the rest of the program.
+pub fn listen() -> BroadcastListener<CompetitionStatus> {
+    static BROADCAST: Once<Broadcast<CompetitionStatus>> = Once::new();
+    BROADCAST
+        .call_once(|| {
+            Task::spawn_ext(
+                "competition-status",
+                bindings::TASK_PRIORITY_MAX,
+                bindings::TASK_STACK_DEPTH_DEFAULT as u16,
+                || loop {
+                    Task::delay(POLL_INTERVAL);
+                    if let Some(broadcast) = BROADCAST.get() {
+                        let current = status();
+                        if current != broadcast.value() {
+                            broadcast.publish(current);
+                        }
+                    }
+                },
+            )
+            .unwrap()
+            .mark_service();
+            Broadcast::new(status())
+        })
+        .listen()
+}
@@ -0,0 +1,384 @@
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use super::SimpleMotorFeedforward;
+use crate::{
+    error::Error,
+    hal::{VelocitySensor, VoltageActuator},
+    math::{abs, signum},
+    motor::MotorData,
+    rtos::{time_since_start, Context, Loop},
+    select,
+};
+
+/// How long the mechanism is left to coast to a stop after each part of a
+/// [`Characterization`].
+const COAST_TIME: Duration = Duration::from_secs(2);
+
+/// The fraction of the fastest logged speed below which a mechanism is
+/// considered stationary when fitting a feedforward.
+const STATIONARY_FRACTION: f64 = 0.01;
+
+/// A sample of the voltage applied to a mechanism and its resulting velocity,
+/// for fitting a feedforward with [`fit_feedforward()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeedforwardSample {
+    /// The time at which the sample was taken, from any fixed starting point.
+    pub time: Duration,
+    /// The voltage applied, in millivolts.
+    pub voltage: f64,
+    /// The velocity of the mechanism.
+    pub velocity: f64,
+}
+
+impl FeedforwardSample {
+    /// Creates a sample from motor data read at the given time, with the
+    /// velocity in degrees per second.
+    pub fn from_motor_data(time: Duration, data: &MotorData) -> Self {
+        Self {
+            time,
+            voltage: data.voltage as f64,
+            // RPM to degrees per second.
+            velocity: data.actual_velocity * 6.0,
+        }
+    }
+}
+
+/// Fits a [`SimpleMotorFeedforward`] to a log of samples by least squares.
+///
+/// The samples must be in order, and evenly spaced in time, since the
+/// acceleration is estimated from the velocities of neighbouring samples.
+/// Samples with no voltage applied, or during which the mechanism was
+/// stationary, are left out of the fit. If the samples do not vary enough in
+/// acceleration to determine `ka`, it is left as 0.
+///
+/// Returns `None` if there are too few usable samples to determine `ks` and
+/// `kv`.
+pub fn fit_feedforward(samples: &[FeedforwardSample]) -> Option<SimpleMotorFeedforward> {
+    let max_speed = samples.iter().map(|s| abs(s.velocity)).fold(0.0, f64::max);
+    let threshold = max_speed * STATIONARY_FRACTION;
+
+    // The normal equations of the regression of voltage on sgn(v), v and a.
+    let mut m = [[0.0; 3]; 3];
+    let mut b = [0.0; 3];
+    for window in samples.windows(3) {
+        let (prev, sample, next) = (&window[0], &window[1], &window[2]);
+        if sample.voltage == 0.0 || abs(sample.velocity) <= threshold {
+            continue;
+        }
+        let dt = match next.time.checked_sub(prev.time) {
+            Some(dt) if !dt.is_zero() => dt.as_secs_f64(),
+            _ => continue,
+        };
+        let x = [
+            signum(sample.velocity),
+            sample.velocity,
+            (next.velocity - prev.velocity) / dt,
+        ];
+        for i in 0..3 {
+            for j in 0..3 {
+                m[i][j] += x[i] * x[j];
+            }
+            b[i] += x[i] * sample.voltage;
+        }
+    }
+
+    if let Some([ks, kv, ka]) = solve(m, b) {
+        return Some(SimpleMotorFeedforward::new(ks, kv, ka));
+    }
+    let [ks, kv] = solve([[m[0][0], m[0][1]], [m[1][0], m[1][1]]], [b[0], b[1]])?;
+    Some(SimpleMotorFeedforward::new(ks, kv, 0.0))
+}
+
+/// Solves a system of linear equations by Gaussian elimination, returning
+/// `None` if it has no unique solution.
+fn solve<const N: usize>(mut m: [[f64; N]; N], mut b: [f64; N]) -> Option<[f64; N]> {
+    let scale: [f64; N] = core::array::from_fn(|i| abs(m[i][i]));
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&i, &j| abs(m[i][col]).total_cmp(&abs(m[j][col])))?;
+        if abs(m[pivot][col]) <= scale[col] * 1e-9 {
+            return None;
+        }
+        m.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..N {
+            let factor = m[row][col] / m[col][col];
+            for k in col..N {
+                m[row][k] -= factor * m[col][k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = [0.0; N];
+    for row in (0..N).rev() {
+        let sum: f64 = (row + 1..N).map(|k| m[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / m[row][row];
+    }
+    Some(x)
+}
+
+/// A test which drives a mechanism with known voltages and logs its velocity,
+/// to find its feedforward gains.
+///
+/// The test has two parts. First the voltage ramps up slowly, so that the
+/// mechanism barely accelerates, which determines `ks` and `kv`. Then a fixed
+/// voltage is applied suddenly, so that the mechanism accelerates hard, which
+/// determines `ka`. After each part, the mechanism coasts to a stop for 2
+/// seconds.
+///
+/// The mechanism is only driven forwards, and must be free to move for the
+/// whole test; for example, a drivetrain should be lifted off the ground.
+#[derive(Clone, Copy, Debug)]
+pub struct Characterization {
+    ramp_rate: f64,
+    step_voltage: f64,
+    test_time: Duration,
+    interval: Duration,
+}
+
+impl Characterization {
+    /// Creates a test with a ramp rate of 1 volt per second, a step of 6
+    /// volts, 4 seconds for each part and a sample every 10 milliseconds.
+    pub fn new() -> Self {
+        Self {
+            ramp_rate: 1000.0,
+            step_voltage: 6000.0,
+            test_time: Duration::from_secs(4),
+            interval: Duration::from_millis(10),
+        }
+    }
+
+    /// Sets the rate at which the voltage ramps up, in millivolts per second.
+    pub fn ramp_rate(mut self, ramp_rate: f64) -> Self {
+        self.ramp_rate = ramp_rate;
+        self
+    }
+
+    /// Sets the voltage of the step, in millivolts.
+    pub fn step_voltage(mut self, step_voltage: f64) -> Self {
+        self.step_voltage = step_voltage;
+        self
+    }
+
+    /// Sets how long each part of the test lasts.
+    pub fn test_time(mut self, test_time: Duration) -> Self {
+        self.test_time = test_time;
+        self
+    }
+
+    /// Sets the interval between samples.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Runs the test on a motor, motor group or other mechanism, returning the
+    /// logged samples, with velocities in degrees per second.
+    ///
+    /// If the context is cancelled, the test stops early and returns the
+    /// samples logged so far. The mechanism is stopped at the end of the test,
+    /// including if an error occurs.
+    pub fn run<M: VoltageActuator + VelocitySensor>(
+        &self,
+        mechanism: &mut M,
+        ctx: Context,
+    ) -> Result<Vec<FeedforwardSample>, Error> {
+        let mut samples = Vec::new();
+        let result = self.log(mechanism, ctx, &mut samples);
+        let stopped = mechanism.set_voltage(0).map_err(Into::into);
+        result.and(stopped)?;
+        Ok(samples)
+    }
+
+    /// Runs the test, as in [`Characterization::run()`], and fits a
+    /// feedforward to the samples.
+    pub fn run_and_fit<M: VoltageActuator + VelocitySensor>(
+        &self,
+        mechanism: &mut M,
+        ctx: Context,
+    ) -> Result<SimpleMotorFeedforward, Error> {
+        fit_feedforward(&self.run(mechanism, ctx)?)
+            .ok_or_else(|| Error::Custom("too few samples to fit feedforward".into()))
+    }
+
+    fn log<M: VoltageActuator + VelocitySensor>(
+        &self,
+        mechanism: &mut M,
+        ctx: Context,
+        samples: &mut Vec<FeedforwardSample>,
+    ) -> Result<(), Error> {
+        let start = time_since_start();
+        let mut l = Loop::new(self.interval);
+        let mut applied = 0.0;
+        let parts = [
+            (Part::Ramp, self.test_time),
+            (Part::Coast, COAST_TIME),
+            (Part::Step, self.test_time),
+            (Part::Coast, COAST_TIME),
+        ];
+        for (part, duration) in parts {
+            let part_start = time_since_start();
+            loop {
+                let now = time_since_start();
+                let elapsed = now.checked_sub_instant(part_start).unwrap_or_default();
+                if elapsed >= duration {
+                    break;
+                }
+
+                // The velocity results from the voltage applied since the
+                // previous sample.
+                samples.push(FeedforwardSample {
+                    time: now.checked_sub_instant(start).unwrap_or_default(),
+                    voltage: applied,
+                    velocity: mechanism.velocity().map_err(Into::into)?,
+                });
+
+                applied = match part {
+                    Part::Ramp => (self.ramp_rate * elapsed.as_secs_f64()).min(12000.0),
+                    Part::Step => self.step_voltage,
+                    Part::Coast => 0.0,
+                };
+                mechanism.set_voltage(applied as i32).map_err(Into::into)?;
+
+                let cancelled = select! {
+                    _ = ctx.done() => true,
+                    _ = l.select() => false,
+                };
+                if cancelled {
+                    return Ok(());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Characterization {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Part {
+    Ramp,
+    Coast,
+    Step,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KS: f64 = 800.0;
+    const KV: f64 = 20.0;
+    const KA: f64 = 5.0;
+
+    /// Generates samples every 10 milliseconds for 4 seconds, following the
+    /// given velocity and acceleration, with voltages from the given gains.
+    fn generate(
+        velocity: impl Fn(f64) -> f64,
+        acceleration: impl Fn(f64) -> f64,
+        gains: SimpleMotorFeedforward,
+    ) -> Vec<FeedforwardSample> {
+        (0..400)
+            .map(|i| {
+                let time = Duration::from_millis(10 * i);
+                let t = time.as_secs_f64();
+                let (v, a) = (velocity(t), acceleration(t));
+                FeedforwardSample {
+                    time,
+                    voltage: gains.ks * signum(v) + gains.kv * v + gains.ka * a,
+                    velocity: v,
+                }
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            abs(actual - expected) <= 1e-6 * abs(expected).max(1.0),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn fit_recovers_gains() {
+        // The velocity is quadratic, so the acceleration varies and its
+        // estimate from neighbouring samples is exact. The mechanism also
+        // reverses, so that `ks` is distinguishable from a constant offset.
+        let samples = generate(
+            |t| -100.0 + 80.0 * t + 10.0 * t * t,
+            |t| 80.0 + 20.0 * t,
+            SimpleMotorFeedforward::new(KS, KV, KA),
+        );
+        let fit = fit_feedforward(&samples).unwrap();
+        assert_close(fit.ks, KS);
+        assert_close(fit.kv, KV);
+        assert_close(fit.ka, KA);
+    }
+
+    #[test]
+    fn fit_ignores_stationary_and_unpowered_samples() {
+        let mut samples = generate(
+            |t| -100.0 + 80.0 * t + 10.0 * t * t,
+            |t| 80.0 + 20.0 * t,
+            SimpleMotorFeedforward::new(KS, KV, KA),
+        );
+        // Give samples which should be left out voltages which do not follow
+        // the model. The top speed is about 378, so samples under 1% of it are
+        // stationary.
+        let mut stationary = 0;
+        for sample in &mut samples {
+            if abs(sample.velocity) <= 3.0 {
+                sample.voltage = 5000.0;
+                stationary += 1;
+            }
+        }
+        assert!(stationary > 0);
+        for sample in &mut samples[200..250] {
+            sample.voltage = 0.0;
+        }
+        let fit = fit_feedforward(&samples).unwrap();
+        assert_close(fit.ks, KS);
+        assert_close(fit.kv, KV);
+        assert_close(fit.ka, KA);
+    }
+
+    #[test]
+    fn fit_without_varying_acceleration() {
+        // With a constant acceleration, `ka` cannot be told apart from `ks`.
+        let samples = generate(
+            |t| 50.0 + 30.0 * t,
+            |_| 30.0,
+            SimpleMotorFeedforward::new(KS, KV, 0.0),
+        );
+        let fit = fit_feedforward(&samples).unwrap();
+        assert_close(fit.ks, KS);
+        assert_close(fit.kv, KV);
+        assert_eq!(fit.ka, 0.0);
+
+        // At a constant velocity, not even `ks` and `kv` can be told apart.
+        let samples = generate(|_| 100.0, |_| 0.0, SimpleMotorFeedforward::new(KS, KV, 0.0));
+        assert_eq!(fit_feedforward(&samples), None);
+    }
+
+    #[test]
+    fn fit_too_few_samples() {
+        assert_eq!(fit_feedforward(&[]), None);
+        let samples = generate(|_| 100.0, |_| 0.0, SimpleMotorFeedforward::new(KS, KV, KA));
+        assert_eq!(fit_feedforward(&samples[..2]), None);
+    }
+
+    #[test]
+    fn solve_pivots() {
+        let m = [[0.0, 2.0, 1.0], [1.0, 1.0, 0.0], [2.0, 0.0, 3.0]];
+        let x = solve(m, [7.0, 3.0, 11.0]).unwrap();
+        for (actual, expected) in x.into_iter().zip([1.0, 2.0, 3.0]) {
+            assert_close(actual, expected);
+        }
+        assert_eq!(solve([[1.0, 2.0], [2.0, 4.0]], [1.0, 2.0]), None);
+    }
+}
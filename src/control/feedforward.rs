@@ -0,0 +1,98 @@
+use core::f64::consts::PI;
+
+use crate::math::{cos, signum};
+
+/// Feedforward for a mechanism with no gravity load, such as a drivetrain or
+/// flywheel.
+///
+/// The voltage, in millivolts, is `ks * sgn(v) + kv * v + ka * a` for a
+/// velocity `v` and acceleration `a`, in whichever units the gains were
+/// characterized with; see [`Characterization`](super::Characterization).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SimpleMotorFeedforward {
+    /// The voltage needed to overcome static friction, in millivolts.
+    pub ks: f64,
+    /// The voltage per unit of velocity, in millivolts.
+    pub kv: f64,
+    /// The voltage per unit of acceleration, in millivolts.
+    pub ka: f64,
+}
+
+impl SimpleMotorFeedforward {
+    #[inline]
+    /// Creates a feedforward with the given gains.
+    pub fn new(ks: f64, kv: f64, ka: f64) -> Self {
+        Self { ks, kv, ka }
+    }
+
+    /// Computes the voltage, in millivolts, for the given velocity and
+    /// acceleration.
+    pub fn calculate(&self, velocity: f64, acceleration: f64) -> f64 {
+        self.ks * signum(velocity) + self.kv * velocity + self.ka * acceleration
+    }
+}
+
+/// Feedforward for a mechanism which lifts a constant gravity load, such as an
+/// elevator or lift.
+///
+/// The voltage, in millivolts, is `kg + ks * sgn(v) + kv * v + ka * a`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ElevatorFeedforward {
+    /// The voltage needed to overcome static friction, in millivolts.
+    pub ks: f64,
+    /// The voltage needed to hold the load against gravity, in millivolts.
+    pub kg: f64,
+    /// The voltage per unit of velocity, in millivolts.
+    pub kv: f64,
+    /// The voltage per unit of acceleration, in millivolts.
+    pub ka: f64,
+}
+
+impl ElevatorFeedforward {
+    #[inline]
+    /// Creates a feedforward with the given gains.
+    pub fn new(ks: f64, kg: f64, kv: f64, ka: f64) -> Self {
+        Self { ks, kg, kv, ka }
+    }
+
+    /// Computes the voltage, in millivolts, for the given velocity and
+    /// acceleration.
+    pub fn calculate(&self, velocity: f64, acceleration: f64) -> f64 {
+        self.kg + self.ks * signum(velocity) + self.kv * velocity + self.ka * acceleration
+    }
+}
+
+/// Feedforward for a rotating arm, whose gravity load varies with the cosine
+/// of its angle.
+///
+/// The voltage, in millivolts, is `kg * cos(θ) + ks * sgn(v) + kv * v + ka *
+/// a` for an angle `θ` in degrees from horizontal.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ArmFeedforward {
+    /// The voltage needed to overcome static friction, in millivolts.
+    pub ks: f64,
+    /// The voltage needed to hold the arm horizontal against gravity, in
+    /// millivolts.
+    pub kg: f64,
+    /// The voltage per unit of velocity, in millivolts.
+    pub kv: f64,
+    /// The voltage per unit of acceleration, in millivolts.
+    pub ka: f64,
+}
+
+impl ArmFeedforward {
+    #[inline]
+    /// Creates a feedforward with the given gains.
+    pub fn new(ks: f64, kg: f64, kv: f64, ka: f64) -> Self {
+        Self { ks, kg, kv, ka }
+    }
+
+    /// Computes the voltage, in millivolts, for the given angle in degrees
+    /// from horizontal, velocity and acceleration.
+    pub fn calculate(&self, angle: f64, velocity: f64, acceleration: f64) -> f64 {
+        self.kg * cos(angle * PI / 180.0)
+            + self.ks * signum(velocity)
+            + self.kv * velocity
+            + self.ka * acceleration
+    }
+}
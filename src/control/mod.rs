@@ -0,0 +1,11 @@
+//! Building blocks for closed-loop control.
+
+mod characterization;
+mod feedforward;
+mod pid;
+mod profile;
+
+pub use characterization::*;
+pub use feedforward::*;
+pub use pid::*;
+pub use profile::*;
@@ -0,0 +1,358 @@
+use core::time::Duration;
+
+use crate::{
+    math::abs,
+    rtos::{time_since_start, GenericSleep, Instant, Selectable},
+};
+
+/// A PID controller.
+///
+/// The derivative term acts on the measurement rather than the error, so that
+/// changing the target does not cause a spike in the output, and can be
+/// low-pass filtered to reduce sensor noise. The integral is not accumulated
+/// while the output is saturated in the direction of the error, and can be
+/// clamped, to limit windup.
+///
+/// ```
+/// # use core::time::Duration;
+/// # use vex_rt::prelude::*;
+/// # fn f(motor: &mut Motor, ctx: Context) {
+/// let mut pid = Pid::new(0.5, 0.01, 0.02)
+///     .output_limits(-12000.0, 12000.0)
+///     .settle_criteria(2.0, 5.0, Duration::from_millis(250));
+/// pid.set_target(900.0);
+/// let mut l = Loop::new(Duration::from_millis(10));
+/// loop {
+///     let output = pid.update(motor.get_position().unwrap());
+///     motor.move_voltage(output as i32).unwrap();
+///     select! {
+///         _ = pid.select_settled() => break,
+///         _ = ctx.done() => break,
+///         _ = l.select() => continue,
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Pid {
+    kp: f64,
+    ki: f64,
+    kd: f64,
+    integral_limit: Option<f64>,
+    derivative_filter: Duration,
+    output_min: f64,
+    output_max: f64,
+    settle: Option<SettleCriteria>,
+    target: f64,
+    integral: f64,
+    /// The filtered rate of change of the measurement, per second.
+    velocity: f64,
+    last: Option<(Instant, f64)>,
+    settled_since: Option<Instant>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SettleCriteria {
+    error: f64,
+    velocity: f64,
+    duration: Duration,
+}
+
+impl Pid {
+    /// Creates a PID controller with the given proportional, integral and
+    /// derivative gains, and a target of 0.
+    ///
+    /// Initially the integral is unclamped, the derivative is unfiltered, the
+    /// output is unlimited and the controller never settles.
+    pub fn new(kp: f64, ki: f64, kd: f64) -> Self {
+        Self {
+            kp,
+            ki,
+            kd,
+            integral_limit: None,
+            derivative_filter: Duration::ZERO,
+            output_min: f64::NEG_INFINITY,
+            output_max: f64::INFINITY,
+            settle: None,
+            target: 0.0,
+            integral: 0.0,
+            velocity: 0.0,
+            last: None,
+            settled_since: None,
+        }
+    }
+
+    /// Limits the magnitude of the integral term of the output. Panics if the
+    /// limit is negative.
+    pub fn integral_limit(mut self, limit: f64) -> Self {
+        assert!(limit >= 0.0, "integral limit must not be negative");
+        self.integral_limit = Some(limit);
+        self
+    }
+
+    /// Sets the time constant of the low-pass filter applied to the derivative
+    /// term. Longer times give a smoother but slower derivative; zero disables
+    /// the filter.
+    pub fn derivative_filter(mut self, time_constant: Duration) -> Self {
+        self.derivative_filter = time_constant;
+        self
+    }
+
+    /// Limits the output to the given range. Panics if `min` is greater than
+    /// `max`.
+    pub fn output_limits(mut self, min: f64, max: f64) -> Self {
+        assert!(min <= max, "output minimum must not exceed maximum");
+        self.output_min = min;
+        self.output_max = max;
+        self
+    }
+
+    /// Sets when the controller is considered settled: once the magnitudes of
+    /// the error and of the rate of change of the measurement (per second) have
+    /// stayed within the given tolerances for the given duration.
+    pub fn settle_criteria(mut self, error: f64, velocity: f64, duration: Duration) -> Self {
+        self.settle = Some(SettleCriteria {
+            error,
+            velocity,
+            duration,
+        });
+        self
+    }
+
+    /// Sets the gains of the controller.
+    pub fn set_gains(&mut self, kp: f64, ki: f64, kd: f64) {
+        self.kp = kp;
+        self.ki = ki;
+        self.kd = kd;
+    }
+
+    #[inline]
+    /// Gets the target of the controller.
+    pub fn target(&self) -> f64 {
+        self.target
+    }
+
+    /// Sets the target of the controller. The controller is no longer settled
+    /// until it meets the settle criteria again.
+    pub fn set_target(&mut self, target: f64) {
+        self.target = target;
+        self.settled_since = None;
+    }
+
+    /// Gets the error at the last update, or `None` if the controller has not
+    /// been updated since it was created or reset.
+    pub fn error(&self) -> Option<f64> {
+        self.last.map(|(_, measurement)| self.target - measurement)
+    }
+
+    /// Clears the integral, derivative and settle state, as if the controller
+    /// had just been created. The gains, limits and target are kept.
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.velocity = 0.0;
+        self.last = None;
+        self.settled_since = None;
+    }
+
+    /// Computes the output of the controller for a new measurement, taken now.
+    pub fn update(&mut self, measurement: f64) -> f64 {
+        self.update_at(measurement, time_since_start())
+    }
+
+    /// Computes the output of the controller for a new measurement taken at
+    /// the given time.
+    pub fn update_at(&mut self, measurement: f64, now: Instant) -> f64 {
+        let error = self.target - measurement;
+        let dt = self.last.map_or(0.0, |(time, _)| {
+            now.checked_sub_instant(time)
+                .map_or(0.0, |d| d.as_secs_f64())
+        });
+
+        if let (Some((_, last_measurement)), true) = (self.last, dt > 0.0) {
+            let velocity = (measurement - last_measurement) / dt;
+            let time_constant = self.derivative_filter.as_secs_f64();
+            self.velocity += (velocity - self.velocity) * dt / (time_constant + dt);
+        }
+
+        let mut integral = self.integral + error * dt;
+        if let Some(limit) = self.integral_limit {
+            if self.ki != 0.0 {
+                let limit = abs(limit / self.ki);
+                integral = integral.clamp(-limit, limit);
+            }
+        }
+
+        let unlimited = self.kp * error + self.ki * integral - self.kd * self.velocity;
+        let output = unlimited.clamp(self.output_min, self.output_max);
+        // Only accumulate the integral while it is not pushing the output
+        // further past its limits.
+        let winding_up = (output < unlimited && error > 0.0) || (output > unlimited && error < 0.0);
+        if !winding_up {
+            self.integral = integral;
+        }
+
+        self.last = Some((now, measurement));
+        match self.settle {
+            Some(settle) if abs(error) <= settle.error && abs(self.velocity) <= settle.velocity => {
+                self.settled_since.get_or_insert(now);
+            }
+            _ => self.settled_since = None,
+        }
+
+        output
+    }
+
+    /// Checks whether the controller has settled, according to its settle
+    /// criteria.
+    pub fn is_settled(&self) -> bool {
+        self.settled_at()
+            .map_or(false, |time| time_since_start() >= time)
+    }
+
+    /// A [`Selectable`] event which occurs when the controller has settled,
+    /// according to its settle criteria.
+    ///
+    /// The criteria are only checked when the controller is updated, so this
+    /// event is intended to be selected alongside the loop which updates the
+    /// controller.
+    pub fn select_settled(&'_ self) -> impl Selectable + '_ {
+        struct SettledSelect<'a>(&'a Pid);
+
+        impl<'a> Selectable for SettledSelect<'a> {
+            fn poll(self) -> Result<(), Self> {
+                if self.0.is_settled() {
+                    Ok(())
+                } else {
+                    Err(self)
+                }
+            }
+
+            fn sleep(&self) -> GenericSleep {
+                match self.0.settled_at() {
+                    Some(time) => GenericSleep::Timestamp(time),
+                    None => GenericSleep::NotifyTake(None),
+                }
+            }
+        }
+
+        SettledSelect(self)
+    }
+
+    /// The time at which the controller will have settled, if it continues to
+    /// meet the settle criteria.
+    fn settled_at(&self) -> Option<Instant> {
+        Some(self.settled_since? + self.settle?.duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtos::Task;
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Updates the controller with each measurement in turn, one interval
+    /// apart, returning the outputs.
+    fn run(pid: &mut Pid, start: Instant, measurements: &[f64]) -> Vec<f64> {
+        measurements
+            .iter()
+            .enumerate()
+            .map(|(i, &m)| pid.update_at(m, start + INTERVAL * i as u32))
+            .collect()
+    }
+
+    #[test]
+    fn integral_stops_while_saturated() {
+        let mut pid = Pid::new(1.0, 1.0, 0.0).output_limits(-1.0, 1.0);
+        pid.set_target(10.0);
+        let outputs = run(&mut pid, Instant::from_secs(1), &[0.0; 100]);
+        assert!(outputs.iter().all(|&output| output == 1.0));
+        assert_eq!(pid.integral, 0.0);
+
+        // With no wound-up integral, the output reverses as soon as the target
+        // is passed.
+        let output = pid.update_at(10.5, Instant::from_secs(2));
+        assert!(output < 0.0);
+
+        // Once the integral term alone saturates the output, the integral stops
+        // growing, so the output comes off its limit as soon as the error
+        // changes sign.
+        let mut pid = Pid::new(0.0, 1.0, 0.0).output_limits(-1.0, 1.0);
+        pid.set_target(1.0);
+        run(&mut pid, Instant::from_secs(1), &[0.0; 300]);
+        assert!(pid.integral <= 1.0);
+        pid.set_target(-1.0);
+        let outputs = run(&mut pid, Instant::from_secs(5), &[0.0; 2]);
+        assert!(outputs[1] < 1.0);
+    }
+
+    #[test]
+    fn set_target_does_not_kick() {
+        let mut pid = Pid::new(2.0, 0.0, 1.0);
+        pid.set_target(5.0);
+        let start = Instant::from_secs(1);
+        let outputs = run(&mut pid, start, &[5.0; 10]);
+        assert!(outputs.iter().all(|&output| output == 0.0));
+
+        pid.set_target(100.0);
+        let output = pid.update_at(5.0, start + INTERVAL * 10);
+        assert_eq!(output, 2.0 * 95.0);
+
+        // The derivative still acts on the measurement.
+        let output = pid.update_at(6.0, start + INTERVAL * 11);
+        assert!(abs(output - (2.0 * 94.0 - 100.0)) < 1e-9);
+    }
+
+    #[test]
+    fn integral_limit_clamps_integral_term() {
+        let mut pid = Pid::new(0.0, 2.0, 0.0).integral_limit(1.0);
+        pid.set_target(1.0);
+        let outputs = run(&mut pid, Instant::from_secs(1), &[0.0; 200]);
+        assert!(outputs.iter().all(|&output| (0.0..=1.0).contains(&output)));
+        assert_eq!(outputs[199], 1.0);
+
+        pid.set_target(-1.0);
+        let outputs = run(&mut pid, Instant::from_secs(3), &[0.0; 200]);
+        assert!(outputs.iter().all(|&output| (-1.0..=1.0).contains(&output)));
+        assert_eq!(outputs[199], -1.0);
+    }
+
+    #[test]
+    fn settles_after_window() {
+        let window = Duration::from_millis(100);
+        let mut pid = Pid::new(1.0, 0.0, 0.0).settle_criteria(1.0, 10.0, window);
+        let start = time_since_start();
+
+        // Within the error tolerance, but still moving too fast.
+        run(&mut pid, start, &[5.0, 0.5]);
+        assert_eq!(pid.settled_at(), None);
+
+        let settled = start + INTERVAL * 2;
+        pid.update_at(0.5, settled);
+        assert_eq!(pid.settled_at(), Some(settled + window));
+        for i in 3..10 {
+            pid.update_at(0.5, start + INTERVAL * i);
+            assert_eq!(pid.settled_at(), Some(settled + window));
+        }
+
+        // Leaving the tolerance restarts the window.
+        pid.update_at(3.0, start + INTERVAL * 10);
+        assert_eq!(pid.settled_at(), None);
+        pid.update_at(3.0, start + INTERVAL * 11);
+        assert_eq!(pid.settled_at(), None);
+
+        // So does changing the target.
+        pid.set_target(3.0);
+        assert_eq!(pid.settled_at(), None);
+        let settled = start + INTERVAL * 12;
+        pid.update_at(3.0, settled);
+        assert_eq!(pid.settled_at(), Some(settled + window));
+
+        assert!(!pid.is_settled());
+        Task::delay(INTERVAL * 12 + window - INTERVAL);
+        assert!(!pid.is_settled());
+        Task::delay(INTERVAL);
+        assert!(pid.is_settled());
+    }
+}
@@ -0,0 +1,713 @@
+use alloc::sync::Arc;
+use core::time::Duration;
+
+use crate::{
+    error::Error,
+    hal::{PositionActuator, VelocityActuator},
+    math::{abs, cbrt, signum, sqrt},
+    rtos::{time_since_start, Context, Loop, Mutex, Promise},
+    select,
+};
+
+/// The state of a mechanism at a point in a motion profile.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MotionState {
+    /// The position of the mechanism.
+    pub position: f64,
+    /// The velocity of the mechanism, in units of position per second.
+    pub velocity: f64,
+    /// The acceleration of the mechanism, in units of position per second
+    /// squared.
+    pub acceleration: f64,
+}
+
+/// Describes a one-dimensional motion profile, which plans how a mechanism
+/// moves over time.
+pub trait MotionProfile {
+    /// Gets how long the profile takes to complete.
+    fn duration(&self) -> Duration;
+
+    /// Gets the planned state of the mechanism at the given time since the
+    /// start of the profile. Times past the end of the profile give the final
+    /// state.
+    fn sample(&self, time: Duration) -> MotionState;
+}
+
+/// A motion profile with limited velocity and acceleration, which moves
+/// between two positions at rest.
+///
+/// The mechanism accelerates at the maximum rate, cruises at the maximum
+/// velocity, then decelerates at the maximum rate, so that its velocity over
+/// time is a trapezoid. If the distance is too short to reach the maximum
+/// velocity, there is no cruise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrapezoidalProfile {
+    start: f64,
+    direction: f64,
+    distance: f64,
+    acceleration: f64,
+    peak_velocity: f64,
+    accel_time: f64,
+    cruise_time: f64,
+}
+
+impl TrapezoidalProfile {
+    /// Creates a profile from `start` to `end` with the given maximum
+    /// velocity and acceleration. Panics if either limit is not positive.
+    pub fn new(start: f64, end: f64, max_velocity: f64, max_acceleration: f64) -> Self {
+        assert!(max_velocity > 0.0, "maximum velocity must be positive");
+        assert!(
+            max_acceleration > 0.0,
+            "maximum acceleration must be positive"
+        );
+
+        let distance = abs(end - start);
+        let (peak_velocity, accel_time, cruise_time) =
+            if max_velocity * max_velocity / max_acceleration <= distance {
+                let accel_time = max_velocity / max_acceleration;
+                (
+                    max_velocity,
+                    accel_time,
+                    distance / max_velocity - accel_time,
+                )
+            } else {
+                let accel_time = sqrt(distance / max_acceleration);
+                (max_acceleration * accel_time, accel_time, 0.0)
+            };
+
+        Self {
+            start,
+            direction: signum(end - start),
+            distance,
+            acceleration: max_acceleration,
+            peak_velocity,
+            accel_time,
+            cruise_time,
+        }
+    }
+
+    /// The state after accelerating from rest for the given time, with the
+    /// motion taken to be in the positive direction.
+    fn accelerate(&self, t: f64) -> (f64, f64, f64) {
+        (
+            self.acceleration * t * t / 2.0,
+            self.acceleration * t,
+            self.acceleration,
+        )
+    }
+}
+
+impl MotionProfile for TrapezoidalProfile {
+    fn duration(&self) -> Duration {
+        Duration::from_secs_f64(2.0 * self.accel_time + self.cruise_time)
+    }
+
+    fn sample(&self, time: Duration) -> MotionState {
+        let t = time.as_secs_f64();
+        let total = 2.0 * self.accel_time + self.cruise_time;
+        // The duration is rounded to the nanosecond, so check against both to
+        // give the final state from the reported end of the profile.
+        let (position, velocity, acceleration) = if t >= total || time >= self.duration() {
+            (self.distance, 0.0, 0.0)
+        } else if t < self.accel_time {
+            self.accelerate(t)
+        } else if t < self.accel_time + self.cruise_time {
+            let (position, _, _) = self.accelerate(self.accel_time);
+            (
+                position + self.peak_velocity * (t - self.accel_time),
+                self.peak_velocity,
+                0.0,
+            )
+        } else {
+            // Deceleration mirrors acceleration, counting back from the end.
+            let (position, velocity, acceleration) = self.accelerate(total - t);
+            (self.distance - position, velocity, -acceleration)
+        };
+        resolve(self.start, self.direction, position, velocity, acceleration)
+    }
+}
+
+/// A motion profile with limited velocity, acceleration and jerk, which moves
+/// between two positions at rest.
+///
+/// This is like a [`TrapezoidalProfile`], but the acceleration ramps up and
+/// down at the maximum jerk rather than changing instantly, so that the
+/// velocity over time follows an S-shaped curve. This gives gentler motion at
+/// the cost of a slightly longer profile.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SCurveProfile {
+    start: f64,
+    direction: f64,
+    distance: f64,
+    jerk: f64,
+    peak_acceleration: f64,
+    peak_velocity: f64,
+    /// The time spent changing acceleration at each end of the acceleration
+    /// phase.
+    jerk_time: f64,
+    /// The time spent at the peak acceleration.
+    accel_time: f64,
+    cruise_time: f64,
+}
+
+impl SCurveProfile {
+    /// Creates a profile from `start` to `end` with the given maximum
+    /// velocity, acceleration and jerk. Panics if any limit is not positive.
+    pub fn new(
+        start: f64,
+        end: f64,
+        max_velocity: f64,
+        max_acceleration: f64,
+        max_jerk: f64,
+    ) -> Self {
+        assert!(max_velocity > 0.0, "maximum velocity must be positive");
+        assert!(
+            max_acceleration > 0.0,
+            "maximum acceleration must be positive"
+        );
+        assert!(max_jerk > 0.0, "maximum jerk must be positive");
+
+        let distance = abs(end - start);
+        let mut profile = Self {
+            start,
+            direction: signum(end - start),
+            distance,
+            jerk: max_jerk,
+            peak_acceleration: 0.0,
+            peak_velocity: 0.0,
+            jerk_time: 0.0,
+            accel_time: 0.0,
+            cruise_time: 0.0,
+        };
+
+        profile.set_peak_velocity(max_velocity, max_acceleration);
+        let accel_distance = profile.accel_distance();
+        if 2.0 * accel_distance <= distance {
+            profile.cruise_time = (distance - 2.0 * accel_distance) / max_velocity;
+            return profile;
+        }
+
+        // The distance is too short to reach the maximum velocity, so find the
+        // peak velocity at which the acceleration and deceleration meet.
+        let jerk_time = max_acceleration / max_jerk;
+        let velocity = max_acceleration
+            * (sqrt(jerk_time * jerk_time + 4.0 * distance / max_acceleration) - jerk_time)
+            / 2.0;
+        if velocity * max_jerk >= max_acceleration * max_acceleration {
+            profile.set_peak_velocity(velocity, max_acceleration);
+        } else {
+            // The maximum acceleration is not reached either.
+            let jerk_time = cbrt(distance / (2.0 * max_jerk));
+            profile.set_peak_velocity(max_jerk * jerk_time * jerk_time, max_acceleration);
+        }
+        profile
+    }
+
+    /// Plans the acceleration phase to reach the given velocity from rest.
+    fn set_peak_velocity(&mut self, velocity: f64, max_acceleration: f64) {
+        self.peak_velocity = velocity;
+        if velocity * self.jerk >= max_acceleration * max_acceleration {
+            self.peak_acceleration = max_acceleration;
+            self.jerk_time = max_acceleration / self.jerk;
+            self.accel_time = velocity / max_acceleration - self.jerk_time;
+        } else {
+            self.jerk_time = sqrt(velocity / self.jerk);
+            self.peak_acceleration = self.jerk * self.jerk_time;
+            self.accel_time = 0.0;
+        }
+    }
+
+    /// The duration of the acceleration phase.
+    fn accel_phase_time(&self) -> f64 {
+        2.0 * self.jerk_time + self.accel_time
+    }
+
+    /// The distance covered during the acceleration phase.
+    fn accel_distance(&self) -> f64 {
+        // The velocity curve is symmetric, so the mean velocity is half the
+        // peak.
+        self.peak_velocity * self.accel_phase_time() / 2.0
+    }
+
+    /// The state after the given time into the acceleration phase, with the
+    /// motion taken to be in the positive direction.
+    fn accelerate(&self, t: f64) -> (f64, f64, f64) {
+        let jerk = self.jerk;
+        if t < self.jerk_time {
+            (jerk * t * t * t / 6.0, jerk * t * t / 2.0, jerk * t)
+        } else if t < self.jerk_time + self.accel_time {
+            let ramp = self.jerk_time;
+            let (position, velocity) = (jerk * ramp * ramp * ramp / 6.0, jerk * ramp * ramp / 2.0);
+            let t = t - ramp;
+            (
+                position + velocity * t + self.peak_acceleration * t * t / 2.0,
+                velocity + self.peak_acceleration * t,
+                self.peak_acceleration,
+            )
+        } else {
+            // The end of the phase mirrors the start, counting back from the
+            // peak velocity.
+            let t = self.accel_phase_time() - t;
+            (
+                self.accel_distance() - (self.peak_velocity * t - jerk * t * t * t / 6.0),
+                self.peak_velocity - jerk * t * t / 2.0,
+                jerk * t,
+            )
+        }
+    }
+}
+
+impl MotionProfile for SCurveProfile {
+    fn duration(&self) -> Duration {
+        Duration::from_secs_f64(2.0 * self.accel_phase_time() + self.cruise_time)
+    }
+
+    fn sample(&self, time: Duration) -> MotionState {
+        let t = time.as_secs_f64();
+        let accel_phase_time = self.accel_phase_time();
+        let total = 2.0 * accel_phase_time + self.cruise_time;
+        // The duration is rounded to the nanosecond, so check against both to
+        // give the final state from the reported end of the profile.
+        let (position, velocity, acceleration) = if t >= total || time >= self.duration() {
+            (self.distance, 0.0, 0.0)
+        } else if t < accel_phase_time {
+            self.accelerate(t)
+        } else if t < accel_phase_time + self.cruise_time {
+            (
+                self.accel_distance() + self.peak_velocity * (t - accel_phase_time),
+                self.peak_velocity,
+                0.0,
+            )
+        } else {
+            // Deceleration mirrors acceleration, counting back from the end.
+            let (position, velocity, acceleration) = self.accelerate(total - t);
+            (self.distance - position, velocity, -acceleration)
+        };
+        resolve(self.start, self.direction, position, velocity, acceleration)
+    }
+}
+
+/// Turns a state measured along the direction of travel into a
+/// [`MotionState`].
+fn resolve(
+    start: f64,
+    direction: f64,
+    position: f64,
+    velocity: f64,
+    acceleration: f64,
+) -> MotionState {
+    MotionState {
+        position: start + direction * position,
+        velocity: direction * velocity,
+        acceleration: direction * acceleration,
+    }
+}
+
+/// How following a motion profile or path ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FollowOutcome {
+    /// The profile or path ran to completion.
+    Finished,
+    /// The context was cancelled before the profile or path completed.
+    Cancelled,
+}
+
+/// Follows a motion profile in a background task, sending the planned state to
+/// a mechanism on every cycle of a [`Loop`].
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use vex_rt::prelude::*;
+/// # fn f(lift: Arc<Mutex<Motor>>, ctx: Context) {
+/// let profile = SCurveProfile::new(0.0, 720.0, 360.0, 720.0, 3600.0);
+/// let promise = ProfileFollower::new(profile).follow_position(lift, ctx);
+/// select! {
+///     result = promise.done() => println!("{:?}", result),
+/// }
+/// # }
+/// ```
+pub struct ProfileFollower<P> {
+    profile: P,
+    interval: Duration,
+}
+
+impl<P: MotionProfile + Send + 'static> ProfileFollower<P> {
+    /// Creates a follower for a profile, which updates every 10 milliseconds.
+    pub fn new(profile: P) -> Self {
+        Self {
+            profile,
+            interval: Duration::from_millis(10),
+        }
+    }
+
+    /// Sets the interval between updates.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Starts following the profile, calling `f` with the planned state on
+    /// each update.
+    ///
+    /// The returned promise resolves once `f` has been given the final state,
+    /// when the context is cancelled, or with the first error returned by `f`.
+    pub fn follow(
+        self,
+        ctx: Context,
+        f: impl FnMut(&MotionState) -> Result<(), Error> + Send + 'static,
+    ) -> Promise<Result<FollowOutcome, Error>> {
+        self.follow_ext(ctx, f, || Ok(()))
+    }
+
+    /// Starts following the profile by setting the target position of an
+    /// actuator on each update; see [`ProfileFollower::follow()`].
+    ///
+    /// The actuator is left holding its last target position if the context is
+    /// cancelled.
+    pub fn follow_position<A: PositionActuator + Send + 'static>(
+        self,
+        actuator: Arc<Mutex<A>>,
+        ctx: Context,
+    ) -> Promise<Result<FollowOutcome, Error>> {
+        self.follow(ctx, move |state| {
+            actuator
+                .lock()
+                .set_position(state.position)
+                .map_err(Into::into)
+        })
+    }
+
+    /// Starts following the profile by setting the target velocity of an
+    /// actuator on each update; see [`ProfileFollower::follow()`].
+    ///
+    /// The actuator is stopped if the context is cancelled.
+    pub fn follow_velocity<A: VelocityActuator + Send + 'static>(
+        self,
+        actuator: Arc<Mutex<A>>,
+        ctx: Context,
+    ) -> Promise<Result<FollowOutcome, Error>> {
+        let stop = actuator.clone();
+        self.follow_ext(
+            ctx,
+            move |state| {
+                actuator
+                    .lock()
+                    .set_velocity(state.velocity)
+                    .map_err(Into::into)
+            },
+            move || stop.lock().set_velocity(0.0).map_err(Into::into),
+        )
+    }
+
+    /// Like [`ProfileFollower::follow()`], but calls `cancel` if the context
+    /// is cancelled.
+    fn follow_ext(
+        self,
+        ctx: Context,
+        mut f: impl FnMut(&MotionState) -> Result<(), Error> + Send + 'static,
+        cancel: impl FnOnce() -> Result<(), Error> + Send + 'static,
+    ) -> Promise<Result<FollowOutcome, Error>> {
+        Promise::spawn(move || {
+            let start = time_since_start();
+            let duration = self.profile.duration();
+            let mut l = Loop::new(self.interval);
+            loop {
+                let elapsed = time_since_start()
+                    .checked_sub_instant(start)
+                    .unwrap_or_default();
+                f(&self.profile.sample(elapsed))?;
+                if elapsed >= duration {
+                    break Ok(FollowOutcome::Finished);
+                }
+                select! {
+                    _ = ctx.done() => {
+                        cancel()?;
+                        break Ok(FollowOutcome::Cancelled);
+                    },
+                    _ = l.select() => continue,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hal::fake::FakeActuator, rtos::Task};
+
+    const EPSILON: f64 = 1e-9;
+    const STEPS: u32 = 10000;
+
+    struct Limits {
+        velocity: f64,
+        acceleration: f64,
+        jerk: Option<f64>,
+    }
+
+    /// Checks that a profile ends at `end` and keeps within its limits, and
+    /// that its position and velocity are continuous at each of `boundaries`.
+    fn check(
+        profile: &impl MotionProfile,
+        start: f64,
+        end: f64,
+        limits: Limits,
+        boundaries: &[f64],
+    ) {
+        let duration = profile.duration();
+        let first = profile.sample(Duration::ZERO);
+        assert!(abs(first.position - start) < EPSILON);
+        assert!(abs(first.velocity) < EPSILON);
+        let last = profile.sample(duration);
+        assert_eq!(last.position, end);
+        assert_eq!(last.velocity, 0.0);
+        assert_eq!(profile.sample(duration * 2), last);
+
+        let (mut previous_time, mut previous) = (Duration::ZERO, first);
+        for i in 1..=STEPS {
+            let time = duration * i / STEPS;
+            // The duration is rounded to the nanosecond, so allow for the last
+            // step ending up to a nanosecond early.
+            let dt = (time - previous_time).as_secs_f64() + 1e-9;
+            let state = profile.sample(time);
+            assert!(abs(state.velocity) <= limits.velocity + EPSILON);
+            assert!(abs(state.acceleration) <= limits.acceleration + EPSILON);
+            assert!(state.velocity * (end - start) >= 0.0);
+            if let Some(jerk) = limits.jerk {
+                let change = abs(state.acceleration - previous.acceleration);
+                assert!(change <= jerk * dt + EPSILON);
+            }
+            assert!(abs(state.position - previous.position) <= limits.velocity * dt + EPSILON);
+            (previous_time, previous) = (time, state);
+        }
+
+        for &t in boundaries {
+            let before = profile.sample(Duration::from_secs_f64(t - 1e-7));
+            let after = profile.sample(Duration::from_secs_f64(t + 1e-7));
+            assert!(
+                abs(after.position - before.position) < 1e-6,
+                "jump at {}",
+                t
+            );
+            assert!(
+                abs(after.velocity - before.velocity) < 1e-5,
+                "jump at {}",
+                t
+            );
+        }
+    }
+
+    fn trapezoidal_boundaries(profile: &TrapezoidalProfile) -> [f64; 2] {
+        let cruise_end = profile.accel_time + profile.cruise_time;
+        [profile.accel_time, cruise_end]
+    }
+
+    fn s_curve_boundaries(profile: &SCurveProfile) -> [f64; 6] {
+        let phase = profile.accel_phase_time();
+        let total = 2.0 * phase + profile.cruise_time;
+        let ramp_end = profile.jerk_time + profile.accel_time;
+        [
+            profile.jerk_time,
+            ramp_end,
+            phase,
+            total - phase,
+            total - ramp_end,
+            total - profile.jerk_time,
+        ]
+    }
+
+    #[test]
+    fn trapezoidal_cruise() {
+        let profile = TrapezoidalProfile::new(0.0, 10.0, 2.0, 4.0);
+        assert_eq!(profile.peak_velocity, 2.0);
+        assert!(profile.cruise_time > 0.0);
+        let limits = Limits {
+            velocity: 2.0,
+            acceleration: 4.0,
+            jerk: None,
+        };
+        check(
+            &profile,
+            0.0,
+            10.0,
+            limits,
+            &trapezoidal_boundaries(&profile),
+        );
+    }
+
+    #[test]
+    fn trapezoidal_no_cruise() {
+        let profile = TrapezoidalProfile::new(0.0, 0.5, 2.0, 4.0);
+        assert!(profile.peak_velocity < 2.0);
+        assert_eq!(profile.cruise_time, 0.0);
+        let limits = Limits {
+            velocity: 2.0,
+            acceleration: 4.0,
+            jerk: None,
+        };
+        check(
+            &profile,
+            0.0,
+            0.5,
+            limits,
+            &trapezoidal_boundaries(&profile),
+        );
+    }
+
+    #[test]
+    fn trapezoidal_reversed() {
+        let profile = TrapezoidalProfile::new(3.0, -7.0, 2.0, 4.0);
+        let limits = Limits {
+            velocity: 2.0,
+            acceleration: 4.0,
+            jerk: None,
+        };
+        check(
+            &profile,
+            3.0,
+            -7.0,
+            limits,
+            &trapezoidal_boundaries(&profile),
+        );
+        assert!(profile.sample(profile.duration() / 2).velocity < 0.0);
+    }
+
+    #[test]
+    fn trapezoidal_zero_distance() {
+        let profile = TrapezoidalProfile::new(5.0, 5.0, 2.0, 4.0);
+        assert_eq!(profile.duration(), Duration::ZERO);
+        let state = profile.sample(Duration::ZERO);
+        assert_eq!(state.position, 5.0);
+        assert_eq!(state.velocity, 0.0);
+        assert_eq!(state.acceleration, 0.0);
+    }
+
+    #[test]
+    fn s_curve_cruise() {
+        let profile = SCurveProfile::new(0.0, 10.0, 2.0, 4.0, 20.0);
+        assert_eq!(profile.peak_velocity, 2.0);
+        assert_eq!(profile.peak_acceleration, 4.0);
+        assert!(profile.cruise_time > 0.0);
+        let limits = Limits {
+            velocity: 2.0,
+            acceleration: 4.0,
+            jerk: Some(20.0),
+        };
+        check(&profile, 0.0, 10.0, limits, &s_curve_boundaries(&profile));
+    }
+
+    #[test]
+    fn s_curve_no_cruise() {
+        let profile = SCurveProfile::new(0.0, 1.0, 2.0, 4.0, 20.0);
+        assert!(profile.peak_velocity < 2.0);
+        assert_eq!(profile.peak_acceleration, 4.0);
+        assert!(profile.accel_time > 0.0);
+        assert_eq!(profile.cruise_time, 0.0);
+        let limits = Limits {
+            velocity: 2.0,
+            acceleration: 4.0,
+            jerk: Some(20.0),
+        };
+        check(&profile, 0.0, 1.0, limits, &s_curve_boundaries(&profile));
+    }
+
+    #[test]
+    fn s_curve_no_peak_acceleration() {
+        let profile = SCurveProfile::new(0.0, 0.1, 2.0, 4.0, 20.0);
+        assert!(profile.peak_acceleration < 4.0);
+        assert_eq!(profile.accel_time, 0.0);
+        assert_eq!(profile.cruise_time, 0.0);
+        let limits = Limits {
+            velocity: 2.0,
+            acceleration: 4.0,
+            jerk: Some(20.0),
+        };
+        check(&profile, 0.0, 0.1, limits, &s_curve_boundaries(&profile));
+    }
+
+    #[test]
+    fn s_curve_reversed() {
+        let profile = SCurveProfile::new(3.0, -7.0, 2.0, 4.0, 20.0);
+        let limits = Limits {
+            velocity: 2.0,
+            acceleration: 4.0,
+            jerk: Some(20.0),
+        };
+        check(&profile, 3.0, -7.0, limits, &s_curve_boundaries(&profile));
+        assert!(profile.sample(profile.duration() / 2).velocity < 0.0);
+    }
+
+    #[test]
+    fn s_curve_zero_distance() {
+        let profile = SCurveProfile::new(5.0, 5.0, 2.0, 4.0, 20.0);
+        assert_eq!(profile.duration(), Duration::ZERO);
+        let state = profile.sample(Duration::ZERO);
+        assert_eq!(state.position, 5.0);
+        assert_eq!(state.velocity, 0.0);
+        assert_eq!(state.acceleration, 0.0);
+    }
+
+    fn outcome(promise: &Promise<Result<FollowOutcome, Error>>) -> FollowOutcome {
+        select! {
+            result = promise.done() => *result.as_ref().unwrap(),
+        }
+    }
+
+    #[test]
+    fn follower_finishes() {
+        let profile = TrapezoidalProfile::new(0.0, 1.0, 2.0, 4.0);
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let start = time_since_start();
+        let promise = ProfileFollower::new(profile).follow(Context::new_global(), {
+            let states = states.clone();
+            move |state| {
+                states.lock().push(*state);
+                Ok(())
+            }
+        });
+
+        assert_eq!(outcome(&promise), FollowOutcome::Finished);
+        assert_eq!(time_since_start(), start + profile.duration());
+        let states = states.lock();
+        // One update every 10 milliseconds, including at both ends.
+        assert_eq!(states.len(), 101);
+        assert_eq!(states[0], profile.sample(Duration::ZERO));
+        assert_eq!(states[50], profile.sample(Duration::from_millis(500)));
+        assert_eq!(states[100].position, 1.0);
+        assert_eq!(states[100].velocity, 0.0);
+    }
+
+    #[test]
+    fn follower_cancelled() {
+        let profile = TrapezoidalProfile::new(0.0, 10.0, 2.0, 4.0);
+        let actuator = Arc::new(Mutex::new(FakeActuator::default()));
+        let ctx = Context::new_global();
+        let promise = ProfileFollower::new(profile).follow_velocity(actuator.clone(), ctx.clone());
+        Task::delay(Duration::from_millis(500));
+        ctx.cancel();
+
+        assert_eq!(outcome(&promise), FollowOutcome::Cancelled);
+        let velocities = &actuator.lock().velocities;
+        assert!(velocities.len() > 2);
+        assert!(velocities[velocities.len() - 2] > 0.0);
+        assert_eq!(velocities.last(), Some(&0.0));
+    }
+
+    #[test]
+    fn follower_stops_at_error() {
+        let profile = TrapezoidalProfile::new(0.0, 1.0, 2.0, 4.0);
+        let mut updates = 0;
+        let promise = ProfileFollower::new(profile).follow(Context::new_global(), move |_| {
+            updates += 1;
+            if updates == 3 {
+                Err(Error::Custom("failed".into()))
+            } else {
+                Ok(())
+            }
+        });
+        let failed = select! {
+            result = promise.done() => result.is_err(),
+        };
+        assert!(failed);
+    }
+}
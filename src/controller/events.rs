@@ -0,0 +1,507 @@
+//! Sampling of controller inputs, and detection of button events.
+
+use alloc::sync::Arc;
+use core::time::Duration;
+
+use super::{Button, Controller, ControllerData, ControllerId};
+use crate::{
+    bindings,
+    rtos::{
+        time_since_start, Broadcast, BroadcastListener, Context, DataSource, GenericSleep, Instant,
+        Loop, Mutex, Selectable, Task,
+    },
+    select,
+};
+
+/// How long a button must be held for a [`ButtonEvent::Held`] event when using
+/// [`Controller::sampler()`].
+pub const DEFAULT_HOLD_TIME: Duration = Duration::from_millis(500);
+
+/// The longest time between the starts of two presses of a button for a
+/// [`ButtonEvent::DoubleTapped`] event when using [`Controller::sampler()`].
+pub const DEFAULT_DOUBLE_TAP_TIME: Duration = Duration::from_millis(300);
+
+/// The buttons of a controller, in the order their events are stored.
+const BUTTONS: [bindings::controller_digital_e_t; 12] = [
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_L1,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_L2,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_R1,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_R2,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_UP,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_DOWN,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_LEFT,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_RIGHT,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_X,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_B,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_Y,
+    bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_A,
+];
+
+impl Controller {
+    #[inline]
+    /// Starts a task which samples the controller at a fixed interval; see
+    /// [`ControllerSampler`]. Uses the default hold and double tap times.
+    pub fn sampler(&self, interval: Duration) -> ControllerSampler {
+        self.sampler_ext(interval, DEFAULT_HOLD_TIME, DEFAULT_DOUBLE_TAP_TIME)
+    }
+
+    /// Starts a task which samples the controller at a fixed interval, with
+    /// the given hold and double tap times; see [`ControllerSampler`].
+    pub fn sampler_ext(
+        &self,
+        interval: Duration,
+        hold_time: Duration,
+        double_tap_time: Duration,
+    ) -> ControllerSampler {
+        let (id, name) = match self.id {
+            bindings::controller_id_e_t_E_CONTROLLER_MASTER => {
+                (ControllerId::Master, "controller-sampler-master")
+            }
+            _ => (ControllerId::Partner, "controller-sampler-partner"),
+        };
+        let controller = unsafe { Controller::new(id) };
+        let data = Arc::new(Broadcast::new(controller.read().unwrap_or_default()));
+        let counts = Arc::new(Mutex::new([EventCounts::default(); BUTTONS.len()]));
+        let ctx = Context::new_global();
+
+        let sampler = ControllerSampler {
+            id: self.id,
+            interval,
+            data: data.clone(),
+            counts: counts.clone(),
+            ctx: ctx.clone(),
+        };
+
+        Task::spawn_ext(
+            name,
+            bindings::TASK_PRIORITY_MAX,
+            bindings::TASK_STACK_DEPTH_DEFAULT as u16,
+            move || {
+                let mut l = Loop::new(interval);
+                let mut trackers = [ButtonTracker::default(); BUTTONS.len()];
+                loop {
+                    if let Ok(sample) = controller.read() {
+                        let now = time_since_start();
+                        let mut all_counts = counts.lock();
+                        for ((tracker, counts), button) in
+                            trackers.iter_mut().zip(all_counts.iter_mut()).zip(BUTTONS)
+                        {
+                            tracker.update(
+                                sample.digital(button),
+                                now,
+                                hold_time,
+                                double_tap_time,
+                                counts,
+                            );
+                        }
+                        drop(all_counts);
+                        data.publish(sample);
+                    }
+
+                    select! {
+                        _ = ctx.done() => break,
+                        _ = l.select() => continue,
+                    }
+                }
+            },
+        )
+        .unwrap();
+
+        sampler
+    }
+}
+
+/// Samples the inputs of a controller at a fixed interval from a background
+/// task, publishing them to a [`Broadcast`] and detecting events on each
+/// button.
+///
+/// The task stops when the sampler is dropped.
+pub struct ControllerSampler {
+    id: bindings::controller_id_e_t,
+    interval: Duration,
+    data: Arc<Broadcast<ControllerData>>,
+    counts: Arc<Mutex<[EventCounts; BUTTONS.len()]>>,
+    ctx: Context,
+}
+
+impl ControllerSampler {
+    #[inline]
+    /// Gets the most recent sample of the controller inputs.
+    pub fn value(&self) -> ControllerData {
+        self.data.value()
+    }
+
+    #[inline]
+    /// Creates a [`BroadcastListener`] which receives every new sample of the
+    /// controller inputs.
+    pub fn listen(&self) -> BroadcastListener<ControllerData> {
+        self.data.listen()
+    }
+
+    /// Creates a listener for the events of a button on the sampled
+    /// controller. Only events which occur after this call are reported.
+    /// Panics if the button belongs to the other controller.
+    pub fn events(&self, button: &Button) -> ButtonEvents {
+        assert_eq!(
+            button.id, self.id,
+            "button does not belong to the sampled controller"
+        );
+        let index = BUTTONS
+            .iter()
+            .position(|&b| b == button.button)
+            .unwrap_or_else(|| panic!("unknown button: {}", button.button));
+        ButtonEvents {
+            index,
+            interval: self.interval,
+            seen: self.counts.lock()[index],
+            counts: self.counts.clone(),
+        }
+    }
+}
+
+impl Drop for ControllerSampler {
+    fn drop(&mut self) {
+        self.ctx.cancel();
+    }
+}
+
+/// Represents an event on a controller button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonEvent {
+    /// The button was pressed.
+    Pressed,
+    /// The button was pressed within the double tap time of the start of its
+    /// previous press.
+    DoubleTapped,
+    /// The button has been held down for the hold time.
+    Held,
+    /// The button was released.
+    Released,
+}
+
+/// Listens for the events of a single button, as detected by a
+/// [`ControllerSampler`].
+///
+/// Events are counted rather than queued, so none are missed however rarely
+/// the listener is checked. Each kind of event is tracked separately; taking
+/// an event of one kind does not affect the others.
+pub struct ButtonEvents {
+    index: usize,
+    interval: Duration,
+    seen: EventCounts,
+    counts: Arc<Mutex<[EventCounts; BUTTONS.len()]>>,
+}
+
+impl ButtonEvents {
+    /// Takes the next event which has not yet been taken, if any. When events
+    /// of several kinds are pending, they are taken in the order of the
+    /// variants of [`ButtonEvent`].
+    pub fn next_event(&mut self) -> Option<ButtonEvent> {
+        [
+            ButtonEvent::Pressed,
+            ButtonEvent::DoubleTapped,
+            ButtonEvent::Held,
+            ButtonEvent::Released,
+        ]
+        .into_iter()
+        .find(|&event| self.take(event))
+    }
+
+    /// A [`Selectable`] event which resolves to the next event taken by
+    /// [`ButtonEvents::next_event()`].
+    pub fn select(&'_ mut self) -> impl Selectable<ButtonEvent> + '_ {
+        struct EventSelect<'a>(&'a mut ButtonEvents);
+
+        impl<'a> Selectable<ButtonEvent> for EventSelect<'a> {
+            fn poll(self) -> Result<ButtonEvent, Self> {
+                self.0.next_event().ok_or(self)
+            }
+
+            fn sleep(&self) -> GenericSleep {
+                GenericSleep::Timestamp(time_since_start() + self.0.interval)
+            }
+        }
+
+        EventSelect(self)
+    }
+
+    #[inline]
+    /// A [`Selectable`] event which occurs when the button is next pressed.
+    pub fn select_press(&'_ mut self) -> impl Selectable + '_ {
+        self.select_kind(ButtonEvent::Pressed)
+    }
+
+    #[inline]
+    /// A [`Selectable`] event which occurs when the button is next released.
+    pub fn select_release(&'_ mut self) -> impl Selectable + '_ {
+        self.select_kind(ButtonEvent::Released)
+    }
+
+    #[inline]
+    /// A [`Selectable`] event which occurs when the button is next held down
+    /// for the hold time.
+    pub fn select_hold(&'_ mut self) -> impl Selectable + '_ {
+        self.select_kind(ButtonEvent::Held)
+    }
+
+    #[inline]
+    /// A [`Selectable`] event which occurs when the button is next double
+    /// tapped.
+    pub fn select_double_tap(&'_ mut self) -> impl Selectable + '_ {
+        self.select_kind(ButtonEvent::DoubleTapped)
+    }
+
+    fn select_kind(&'_ mut self, event: ButtonEvent) -> impl Selectable + '_ {
+        struct KindSelect<'a> {
+            events: &'a mut ButtonEvents,
+            event: ButtonEvent,
+        }
+
+        impl<'a> Selectable for KindSelect<'a> {
+            fn poll(self) -> Result<(), Self> {
+                if self.events.take(self.event) {
+                    Ok(())
+                } else {
+                    Err(self)
+                }
+            }
+
+            fn sleep(&self) -> GenericSleep {
+                GenericSleep::Timestamp(time_since_start() + self.events.interval)
+            }
+        }
+
+        KindSelect {
+            events: self,
+            event,
+        }
+    }
+
+    /// Marks one event of the given kind as taken, returning `false` if there
+    /// are none pending.
+    fn take(&mut self, event: ButtonEvent) -> bool {
+        let counts = self.counts.lock()[self.index];
+        let (seen, count) = match event {
+            ButtonEvent::Pressed => (&mut self.seen.pressed, counts.pressed),
+            ButtonEvent::DoubleTapped => (&mut self.seen.double_tapped, counts.double_tapped),
+            ButtonEvent::Held => (&mut self.seen.held, counts.held),
+            ButtonEvent::Released => (&mut self.seen.released, counts.released),
+        };
+        if *seen == count {
+            false
+        } else {
+            *seen = seen.wrapping_add(1);
+            true
+        }
+    }
+}
+
+/// The number of events of each kind which have occurred on a button.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct EventCounts {
+    pressed: u32,
+    double_tapped: u32,
+    held: u32,
+    released: u32,
+}
+
+/// Detects the events of a button from successive samples.
+#[derive(Clone, Copy, Default)]
+struct ButtonTracker {
+    /// The time at which the current press started, if the button is pressed.
+    pressed_at: Option<Instant>,
+    /// Whether the current press has been reported as held.
+    held: bool,
+    /// The time at which the previous press started, if it may be the first
+    /// half of a double tap.
+    tapped_at: Option<Instant>,
+}
+
+impl ButtonTracker {
+    fn update(
+        &mut self,
+        pressed: bool,
+        now: Instant,
+        hold_time: Duration,
+        double_tap_time: Duration,
+        counts: &mut EventCounts,
+    ) {
+        match (self.pressed_at, pressed) {
+            (None, true) => {
+                counts.pressed = counts.pressed.wrapping_add(1);
+                let double_tapped = self
+                    .tapped_at
+                    .and_then(|t| now.checked_sub_instant(t))
+                    .map_or(false, |d| d <= double_tap_time);
+                if double_tapped {
+                    counts.double_tapped = counts.double_tapped.wrapping_add(1);
+                    self.tapped_at = None;
+                } else {
+                    self.tapped_at = Some(now);
+                }
+                self.pressed_at = Some(now);
+                self.held = false;
+            }
+            (Some(start), true) => {
+                let held = now
+                    .checked_sub_instant(start)
+                    .map_or(false, |d| d >= hold_time);
+                if held && !self.held {
+                    counts.held = counts.held.wrapping_add(1);
+                    self.held = true;
+                    // A long press cannot start a double tap.
+                    self.tapped_at = None;
+                }
+            }
+            (Some(_), false) => {
+                counts.released = counts.released.wrapping_add(1);
+                self.pressed_at = None;
+            }
+            (None, false) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::PeripheralsBuilder;
+
+    /// Feeds a tracker samples of the button, each the time in milliseconds
+    /// and whether it is pressed, returning the events counted.
+    fn track(hold_time: u64, samples: &[(u64, bool)]) -> EventCounts {
+        let mut tracker = ButtonTracker::default();
+        let mut counts = EventCounts::default();
+        for &(time, pressed) in samples {
+            tracker.update(
+                pressed,
+                Instant::from_millis(time),
+                Duration::from_millis(hold_time),
+                DEFAULT_DOUBLE_TAP_TIME,
+                &mut counts,
+            );
+        }
+        counts
+    }
+
+    fn counts(pressed: u32, double_tapped: u32, held: u32, released: u32) -> EventCounts {
+        EventCounts {
+            pressed,
+            double_tapped,
+            held,
+            released,
+        }
+    }
+
+    #[test]
+    fn press_and_release() {
+        assert_eq!(track(500, &[(0, false), (10, false)]), counts(0, 0, 0, 0));
+        assert_eq!(track(500, &[(0, false), (10, true)]), counts(1, 0, 0, 0));
+        assert_eq!(
+            track(500, &[(0, false), (10, true), (20, true), (30, false)]),
+            counts(1, 0, 0, 1)
+        );
+        assert_eq!(
+            track(500, &[(0, true), (10, false), (1000, true), (1010, false)]),
+            counts(2, 0, 0, 2)
+        );
+    }
+
+    #[test]
+    fn held_after_hold_time() {
+        assert_eq!(track(500, &[(0, true), (490, true)]), counts(1, 0, 0, 0));
+        assert_eq!(track(500, &[(0, true), (500, true)]), counts(1, 0, 1, 0));
+        assert_eq!(
+            track(500, &[(0, true), (500, true), (1000, true), (1010, false)]),
+            counts(1, 0, 1, 1)
+        );
+        // The hold time is measured from the start of the current press.
+        assert_eq!(
+            track(500, &[(0, true), (300, false), (310, true), (700, true)]),
+            counts(2, 0, 0, 1)
+        );
+    }
+
+    #[test]
+    fn double_tap_within_double_tap_time() {
+        assert_eq!(
+            track(500, &[(0, true), (100, false), (300, true)]),
+            counts(2, 1, 0, 1)
+        );
+        assert_eq!(
+            track(500, &[(0, true), (100, false), (310, true)]),
+            counts(2, 0, 0, 1)
+        );
+        // A late second press may start a double tap of its own.
+        let late = [
+            (0, true),
+            (100, false),
+            (310, true),
+            (400, false),
+            (500, true),
+        ];
+        assert_eq!(track(500, &late), counts(3, 1, 0, 2));
+        // The second press of a double tap does not start another.
+        let triple = [
+            (0, true),
+            (50, false),
+            (100, true),
+            (150, false),
+            (200, true),
+        ];
+        assert_eq!(track(500, &triple), counts(3, 1, 0, 2));
+    }
+
+    #[test]
+    fn long_press_cancels_double_tap() {
+        assert_eq!(
+            track(200, &[(0, true), (190, true), (210, false), (250, true)]),
+            counts(2, 1, 0, 1)
+        );
+        assert_eq!(
+            track(200, &[(0, true), (200, true), (210, false), (250, true)]),
+            counts(2, 0, 1, 1)
+        );
+    }
+
+    #[test]
+    fn sampler_reports_events() {
+        let pressed = |t: Duration| {
+            let t = t.as_millis();
+            (100..200).contains(&t) || (250..350).contains(&t) || (600..1200).contains(&t)
+        };
+        let (peripherals, _sim) = PeripheralsBuilder::new()
+            .controller(ControllerId::Master, move |t| {
+                Some(ControllerData {
+                    a: pressed(t),
+                    ..Default::default()
+                })
+            })
+            .build();
+        let master = &peripherals.master_controller;
+        let sampler = master.sampler(Duration::from_millis(10));
+        let mut events = sampler.events(&master.a);
+        let mut b_events = sampler.events(&master.b);
+
+        let mut seen = Vec::new();
+        for _ in 0..130 {
+            Task::delay(Duration::from_millis(10));
+            seen.extend(core::iter::from_fn(|| events.next_event()));
+        }
+        assert_eq!(
+            seen,
+            [
+                ButtonEvent::Pressed,
+                ButtonEvent::Released,
+                ButtonEvent::Pressed,
+                ButtonEvent::DoubleTapped,
+                ButtonEvent::Released,
+                ButtonEvent::Pressed,
+                ButtonEvent::Held,
+                ButtonEvent::Released,
+            ]
+        );
+        assert_eq!(b_events.next_event(), None);
+        assert!(!sampler.value().a);
+    }
+}
@@ -1,20 +1,46 @@
 //! Controller API.
 
-use alloc::collections::VecDeque;
-use core::{convert::TryInto, fmt, time::Duration};
+use alloc::{collections::VecDeque, sync::Arc};
+use core::{
+    convert::TryInto,
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 use slice_copy::copy;
 
 use crate::{
     bindings,
     error::{get_errno, Error},
     io::eprintln,
-    rtos::{delay_until, queue, time_since_start, DataSource, SendQueue, Task},
+    rtos::{
+        delay_until, queue, time_since_start, DataSource, GenericSleep, Selectable, SendQueue, Task,
+    },
     select,
 };
 
+mod events;
+mod page;
+mod replay;
+mod shaping;
+
+pub use events::*;
+pub use page::*;
+pub use replay::*;
+pub use shaping::*;
+
 const SCREEN_SUCCESS_DELAY: Duration = Duration::from_millis(50);
 const SCREEN_FAILURE_DELAY: Duration = Duration::from_millis(5);
 
+/// The number of lines on the controller screen.
+pub const SCREEN_LINES: u8 = 3;
+
+/// The number of columns on the controller screen.
+pub const SCREEN_COLUMNS: u8 = 19;
+
+/// How often [`Screen::select_flushed()`] checks whether updates are pending.
+const FLUSH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Represents a Vex controller.
 pub struct Controller {
     id: bindings::controller_id_e_t,
@@ -120,12 +146,20 @@ impl Controller {
                 id,
                 button: bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_A,
             },
-            screen: Screen { id, queue: None },
+            screen: Screen {
+                id,
+                queue: None,
+                sent: 0,
+                flushed: Arc::new(AtomicUsize::new(0)),
+            },
         }
     }
 
     /// Returns false or true if the controller is connected.
     pub fn is_connected(&self) -> Result<bool, ControllerError> {
+        if let Some(sample) = replay::replayed(self.id) {
+            return Ok(sample.connected);
+        }
         match unsafe { bindings::controller_is_connected(self.id) } {
             0 => Ok(false),
             1 => Ok(true),
@@ -135,14 +169,31 @@ impl Controller {
 
     /// Gets the battery level of the controller.
     pub fn get_battery_level(&self) -> Result<i32, ControllerError> {
+        if let Some(sample) = replay::replayed(self.id) {
+            return Ok(sample.data.battery_level);
+        }
+        self.read_battery_level()
+    }
+
+    /// Gets the battery capacity of the controller.
+    pub fn get_battery_capacity(&self) -> Result<i32, ControllerError> {
+        if let Some(sample) = replay::replayed(self.id) {
+            return Ok(sample.data.battery_capacity);
+        }
+        self.read_battery_capacity()
+    }
+
+    /// Gets the battery level of the physical controller, ignoring any replay.
+    fn read_battery_level(&self) -> Result<i32, ControllerError> {
         match unsafe { bindings::controller_get_battery_level(self.id) } {
             bindings::PROS_ERR_ => Err(ControllerError::from_errno()),
             x => Ok(x),
         }
     }
 
-    /// Gets the battery capacity of the controller.
-    pub fn get_battery_capacity(&self) -> Result<i32, ControllerError> {
+    /// Gets the battery capacity of the physical controller, ignoring any
+    /// replay.
+    fn read_battery_capacity(&self) -> Result<i32, ControllerError> {
         match unsafe { bindings::controller_get_battery_capacity(self.id) } {
             bindings::PROS_ERR_ => Err(ControllerError::from_errno()),
             x => Ok(x),
@@ -162,25 +213,30 @@ impl DataSource for Controller {
     type Error = ControllerError;
 
     fn read(&self) -> Result<Self::Data, Self::Error> {
+        // Every field comes from the same replayed sample, or none of them do.
+        if let Some(sample) = replay::replayed(self.id) {
+            return Ok(sample.data);
+        }
+        let (left, right) = (&self.left_stick, &self.right_stick);
         Ok(ControllerData {
-            left_x: self.left_stick.get_x()?,
-            left_y: self.left_stick.get_y()?,
-            right_x: self.right_stick.get_x()?,
-            right_y: self.right_stick.get_y()?,
-            l1: self.l1.is_pressed()?,
-            l2: self.l2.is_pressed()?,
-            r1: self.r1.is_pressed()?,
-            r2: self.r2.is_pressed()?,
-            up: self.up.is_pressed()?,
-            down: self.down.is_pressed()?,
-            left: self.left.is_pressed()?,
-            right: self.right.is_pressed()?,
-            x: self.x.is_pressed()?,
-            y: self.y.is_pressed()?,
-            a: self.a.is_pressed()?,
-            b: self.b.is_pressed()?,
-            battery_level: self.get_battery_level()?,
-            battery_capacity: self.get_battery_capacity()?,
+            left_x: left.read_channel(left.x_channel)?,
+            left_y: left.read_channel(left.y_channel)?,
+            right_x: right.read_channel(right.x_channel)?,
+            right_y: right.read_channel(right.y_channel)?,
+            l1: self.l1.read_pressed()?,
+            l2: self.l2.read_pressed()?,
+            r1: self.r1.read_pressed()?,
+            r2: self.r2.read_pressed()?,
+            up: self.up.read_pressed()?,
+            down: self.down.read_pressed()?,
+            left: self.left.read_pressed()?,
+            right: self.right.read_pressed()?,
+            x: self.x.read_pressed()?,
+            y: self.y.read_pressed()?,
+            a: self.a.read_pressed()?,
+            b: self.b.read_pressed()?,
+            battery_level: self.read_battery_level()?,
+            battery_capacity: self.read_battery_capacity()?,
         })
     }
 }
@@ -226,6 +282,26 @@ pub struct ControllerData {
     pub battery_capacity: i32,
 }
 
+impl ControllerData {
+    pub(super) fn digital(&self, button: bindings::controller_digital_e_t) -> bool {
+        match button {
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_L1 => self.l1,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_L2 => self.l2,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_R1 => self.r1,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_R2 => self.r2,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_UP => self.up,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_DOWN => self.down,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_LEFT => self.left,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_RIGHT => self.right,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_X => self.x,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_B => self.b,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_Y => self.y,
+            bindings::controller_digital_e_t_E_CONTROLLER_DIGITAL_A => self.a,
+            _ => false,
+        }
+    }
+}
+
 /// Represents one of two analog sticks on a Vex controller.
 pub struct AnalogStick {
     id: bindings::controller_id_e_t,
@@ -249,6 +325,17 @@ impl AnalogStick {
     }
 
     fn get_channel(&self, channel: bindings::controller_analog_e_t) -> Result<i8, ControllerError> {
+        if let Some(sample) = replay::replayed(self.id) {
+            return Ok(sample.analog(channel));
+        }
+        self.read_channel(channel)
+    }
+
+    /// Reads a channel of the physical controller, ignoring any replay.
+    fn read_channel(
+        &self,
+        channel: bindings::controller_analog_e_t,
+    ) -> Result<i8, ControllerError> {
         match unsafe { bindings::controller_get_analog(self.id, channel) } {
             bindings::PROS_ERR_ => Err(ControllerError::from_errno()),
             x => match x.try_into() {
@@ -274,6 +361,14 @@ impl Button {
     /// Checks if a given button is pressed. Returns false if the controller is
     /// not connected.
     pub fn is_pressed(&self) -> Result<bool, ControllerError> {
+        if let Some(sample) = replay::replayed(self.id) {
+            return Ok(sample.digital(self.button));
+        }
+        self.read_pressed()
+    }
+
+    /// Checks the button on the physical controller, ignoring any replay.
+    fn read_pressed(&self) -> Result<bool, ControllerError> {
         match unsafe { bindings::controller_get_digital(self.id, self.button) } {
             0 => Ok(false),
             1 => Ok(true),
@@ -283,9 +378,17 @@ impl Button {
 }
 
 /// Represents the screen on a Vex controller
+///
+/// Updates are sent to the controller by a background task, since the
+/// controller accepts at most one update every 50 milliseconds.
 pub struct Screen {
     id: bindings::controller_id_e_t,
     queue: Option<SendQueue<ScreenCommand>>,
+    /// The number of commands sent to the task.
+    sent: usize,
+    /// The number of commands the task had received when it last finished
+    /// sending updates to the controller.
+    flushed: Arc<AtomicUsize>,
 }
 
 impl Screen {
@@ -310,15 +413,67 @@ impl Screen {
             return;
         }
         let mut chars: [libc::c_char; 19] = Default::default();
-        copy(&mut chars, str.as_bytes());
+        for (c, b) in chars.iter_mut().zip(str.bytes()) {
+            *c = b as libc::c_char;
+        }
         self.command(ScreenCommand::Print {
             chars,
             line,
             column,
-            length: str.as_bytes().len() as u8,
+            length: str.len().min((SCREEN_COLUMNS - column) as usize) as u8,
         });
     }
 
+    /// Creates a writer which replaces the text of a line of the controller
+    /// screen when it is dropped, for use with [`write!`](core::write!). Lines
+    /// range from 0 to 2.
+    ///
+    /// Text beyond the end of the line is discarded, and the write which
+    /// overflows returns an error. Panics if the line is out of range.
+    pub fn line(&mut self, line: u8) -> ScreenLine<'_> {
+        assert!(line < SCREEN_LINES, "line out of range: {}", line);
+        ScreenLine {
+            screen: self,
+            line,
+            chars: [0x20; SCREEN_COLUMNS as usize],
+            len: 0,
+        }
+    }
+
+    /// Shows a full screen of text. Only the lines which differ from the text
+    /// already shown are sent to the controller.
+    pub fn show(&mut self, page: &ScreenPage) {
+        self.command(ScreenCommand::Page(page.rows));
+    }
+
+    /// Checks whether every update made so far has been sent to the
+    /// controller.
+    pub fn is_flushed(&self) -> bool {
+        self.queue.is_none() || self.flushed.load(Ordering::Acquire) == self.sent
+    }
+
+    /// A [`Selectable`] event which occurs once every update made so far has
+    /// been sent to the controller.
+    pub fn select_flushed(&self) -> impl Selectable + '_ {
+        struct FlushedSelect<'b>(&'b Screen);
+
+        impl<'b> Selectable for FlushedSelect<'b> {
+            fn poll(self) -> Result<(), Self> {
+                if self.0.is_flushed() {
+                    Ok(())
+                } else {
+                    Err(self)
+                }
+            }
+
+            fn sleep(&self) -> GenericSleep {
+                GenericSleep::Timestamp(time_since_start() + FLUSH_POLL_INTERVAL)
+            }
+        }
+
+        FlushedSelect(self)
+    }
+
     /// Rumble the controller. Rumble pattern is a string consisting of the
     /// characters ‘.’, ‘-’, and ‘ ‘, where dots are short rumbles, dashes are
     /// long rumbles, and spaces are pauses; all other characters are ignored.
@@ -343,6 +498,7 @@ impl Screen {
 
     fn command(&mut self, cmd: ScreenCommand) {
         self.queue().send(cmd);
+        self.sent = self.sent.wrapping_add(1);
     }
 
     fn queue(&mut self) -> &mut SendQueue<ScreenCommand> {
@@ -353,6 +509,7 @@ impl Screen {
                 _ => "",
             };
             let id = self.id;
+            let flushed = self.flushed.clone();
             let (send, recv) = queue(VecDeque::<ScreenCommand>::new());
             Task::spawn_ext(
                 name,
@@ -364,12 +521,14 @@ impl Screen {
                     let mut clear = false;
                     let mut buffer = [ScreenRow::default(); 3];
                     let mut rumble: Option<[libc::c_char; 9]> = None;
+                    let mut received = 0usize;
                     'main: loop {
                         let command: Option<ScreenCommand> = select! {
                             cmd = recv.select() => Some(cmd),
                             _ = delay_until(t); Some(t) = delay_target => None,
                         };
                         if let Some(cmd) = command {
+                            received = received.wrapping_add(1);
                             match cmd {
                                 ScreenCommand::Clear => {
                                     offset = 0;
@@ -394,6 +553,16 @@ impl Screen {
                                     );
                                     row.dirty = true;
                                 }
+                                ScreenCommand::Page(rows) => {
+                                    for (row, chars) in buffer.iter_mut().zip(rows.iter()) {
+                                        if row.chars[..chars.len()] != chars[..] {
+                                            copy(&mut row.chars, chars);
+                                            // Setting the text replaces the whole line.
+                                            row.needs_clear = false;
+                                            row.dirty = true;
+                                        }
+                                    }
+                                }
                                 ScreenCommand::Rumble(pattern) => {
                                     let mut buf: [libc::c_char; 9] = Default::default();
                                     copy(&mut buf, &pattern);
@@ -471,11 +640,13 @@ impl Screen {
                             }
                             // No updates were made; delay indefinitely until next command.
                             delay_target = None;
+                            flushed.store(received, Ordering::Release);
                         }
                     }
                 },
             )
-            .unwrap();
+            .unwrap()
+            .mark_service();
             send
         })
     }
@@ -495,6 +666,48 @@ impl Drop for Screen {
     }
 }
 
+/// Writes the text of a line of the controller screen; see [`Screen::line()`].
+pub struct ScreenLine<'a> {
+    screen: &'a mut Screen,
+    line: u8,
+    chars: [libc::c_char; SCREEN_COLUMNS as usize],
+    len: usize,
+}
+
+impl<'a> fmt::Write for ScreenLine<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_row(&mut self.chars, &mut self.len, s)
+    }
+}
+
+impl<'a> Drop for ScreenLine<'a> {
+    fn drop(&mut self) {
+        self.screen.command(ScreenCommand::Print {
+            chars: self.chars,
+            line: self.line,
+            column: 0,
+            length: SCREEN_COLUMNS,
+        });
+    }
+}
+
+/// Writes text into a line of the screen after the `len` characters already
+/// written, failing if it does not fit.
+fn write_row(
+    chars: &mut [libc::c_char; SCREEN_COLUMNS as usize],
+    len: &mut usize,
+    s: &str,
+) -> fmt::Result {
+    for b in s.bytes() {
+        match chars.get_mut(*len) {
+            Some(c) => *c = b as libc::c_char,
+            None => return Err(fmt::Error),
+        }
+        *len += 1;
+    }
+    Ok(())
+}
+
 #[derive(Copy, Clone)]
 struct ScreenRow {
     chars: [libc::c_char; 20],
@@ -525,6 +738,7 @@ enum ScreenCommand {
         column: u8,
         length: u8,
     },
+    Page([[libc::c_char; SCREEN_COLUMNS as usize]; SCREEN_LINES as usize]),
     Rumble([libc::c_char; 8]),
     Stop,
 }
@@ -576,3 +790,68 @@ impl From<ControllerError> for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::PeripheralsBuilder;
+
+    /// Shows a page and waits until it has been sent to the controller,
+    /// returning how long that took in milliseconds.
+    fn show(screen: &mut Screen, page: &ScreenPage) -> u128 {
+        let start = time_since_start();
+        screen.show(page);
+        select! {
+            _ = screen.select_flushed() => {},
+        }
+        (time_since_start() - start).as_millis()
+    }
+
+    #[test]
+    fn show_sends_changed_rows() {
+        let (mut peripherals, sim) = PeripheralsBuilder::new().build();
+        let screen = &mut peripherals.master_controller.screen;
+        assert!(screen.is_flushed());
+
+        let mut page = ScreenPage::new();
+        page.set_line(0, "one");
+        page.set_line(1, "two");
+        page.set_line(2, "three");
+        let start = time_since_start();
+        screen.show(&page);
+        assert!(!screen.is_flushed());
+        select! {
+            _ = screen.select_flushed() => {},
+        }
+        assert!(screen.is_flushed());
+        // Each row is a separate update, and updates are 50 ms apart.
+        let elapsed = (time_since_start() - start).as_millis();
+        assert!((150..200).contains(&elapsed), "took {} ms", elapsed);
+        assert_eq!(
+            sim.controller_text(ControllerId::Master),
+            [
+                format!("{:19}", "one"),
+                format!("{:19}", "two"),
+                format!("{:19}", "three")
+            ]
+        );
+
+        page.set_line(1, "changed");
+        let elapsed = show(screen, &page);
+        assert!((50..100).contains(&elapsed), "took {} ms", elapsed);
+        assert_eq!(
+            sim.controller_text(ControllerId::Master)[1],
+            format!("{:19}", "changed")
+        );
+
+        let elapsed = show(screen, &page);
+        assert!(elapsed < 50, "took {} ms", elapsed);
+
+        let elapsed = show(screen, &ScreenPage::new());
+        assert!((150..200).contains(&elapsed), "took {} ms", elapsed);
+        assert_eq!(
+            sim.controller_text(ControllerId::Master),
+            vec![" ".repeat(19); 3]
+        );
+    }
+}
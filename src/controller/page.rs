@@ -0,0 +1,267 @@
+//! Full-screen layouts for the controller screen.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt::{self, Write};
+
+use super::{write_row, SCREEN_COLUMNS, SCREEN_LINES};
+
+/// A full screen of text for the controller, shown with
+/// [`Screen::show()`](super::Screen::show()).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ScreenPage {
+    pub(super) rows: [[libc::c_char; SCREEN_COLUMNS as usize]; SCREEN_LINES as usize],
+}
+
+impl ScreenPage {
+    /// Creates a blank page.
+    pub fn new() -> Self {
+        Self {
+            rows: [[0x20; SCREEN_COLUMNS as usize]; SCREEN_LINES as usize],
+        }
+    }
+
+    /// Creates a writer which replaces the text of a line of the page, for use
+    /// with [`write!`](core::write!). Lines range from 0 to 2.
+    ///
+    /// Text beyond the end of the line is discarded, and the write which
+    /// overflows returns an error. Panics if the line is out of range.
+    pub fn line(&mut self, line: u8) -> PageLine<'_> {
+        assert!(line < SCREEN_LINES, "line out of range: {}", line);
+        let chars = &mut self.rows[line as usize];
+        *chars = [0x20; SCREEN_COLUMNS as usize];
+        PageLine { chars, len: 0 }
+    }
+
+    /// Replaces the text of a line of the page, discarding any text beyond the
+    /// end of the line. Panics if the line is out of range.
+    pub fn set_line(&mut self, line: u8, text: &str) {
+        let _ = self.line(line).write_str(text);
+    }
+}
+
+impl Default for ScreenPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ScreenPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        for row in self.rows.iter() {
+            let text: String = row.iter().map(|&c| c as u8 as char).collect();
+            list.entry(&text);
+        }
+        list.finish()
+    }
+}
+
+/// Writes the text of a line of a [`ScreenPage`]; see [`ScreenPage::line()`].
+pub struct PageLine<'a> {
+    chars: &'a mut [libc::c_char; SCREEN_COLUMNS as usize],
+    len: usize,
+}
+
+impl<'a> fmt::Write for PageLine<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_row(self.chars, &mut self.len, s)
+    }
+}
+
+/// A scrolling menu for the controller screen, with an optional title.
+///
+/// The menu shows as many items as fit below the title, marking the selected
+/// item, and scrolls to keep the selected item in view.
+#[derive(Clone, Debug, Default)]
+pub struct ScreenMenu {
+    title: Option<String>,
+    items: Vec<String>,
+    selected: usize,
+    offset: usize,
+}
+
+impl ScreenMenu {
+    /// Creates an empty menu without a title.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the title, which is shown on the first line.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.into());
+        self.scroll();
+        self
+    }
+
+    /// Adds an item to the end of the menu. The first item is initially
+    /// selected.
+    pub fn item(mut self, item: &str) -> Self {
+        self.items.push(item.into());
+        self
+    }
+
+    /// Gets the index of the selected item, or `None` if the menu is empty.
+    pub fn selected(&self) -> Option<usize> {
+        if self.items.is_empty() {
+            None
+        } else {
+            Some(self.selected)
+        }
+    }
+
+    /// Gets the selected item, or `None` if the menu is empty.
+    pub fn selected_item(&self) -> Option<&str> {
+        self.items.get(self.selected).map(String::as_str)
+    }
+
+    /// Selects the item with the given index. Panics if the index is out of
+    /// range.
+    pub fn select(&mut self, index: usize) {
+        assert!(index < self.items.len(), "index out of range: {}", index);
+        self.selected = index;
+        self.scroll();
+    }
+
+    /// Selects the next item, wrapping around to the first.
+    pub fn select_next(&mut self) {
+        if !self.items.is_empty() {
+            self.select((self.selected + 1) % self.items.len());
+        }
+    }
+
+    /// Selects the previous item, wrapping around to the last.
+    pub fn select_previous(&mut self) {
+        if !self.items.is_empty() {
+            let len = self.items.len();
+            self.select((self.selected + len - 1) % len);
+        }
+    }
+
+    /// Lays out the menu as a page, for
+    /// [`Screen::show()`](super::Screen::show()).
+    pub fn page(&self) -> ScreenPage {
+        let mut page = ScreenPage::new();
+        let mut line = 0;
+        if let Some(title) = &self.title {
+            page.set_line(0, title);
+            line += 1;
+        }
+        for (index, item) in self.items.iter().enumerate().skip(self.offset) {
+            if line >= SCREEN_LINES {
+                break;
+            }
+            let marker = if index == self.selected { '>' } else { ' ' };
+            let _ = write!(page.line(line), "{}{}", marker, item);
+            line += 1;
+        }
+        page
+    }
+
+    /// The number of lines available for items.
+    fn visible(&self) -> usize {
+        SCREEN_LINES as usize - self.title.is_some() as usize
+    }
+
+    /// Scrolls the menu so that the selected item is shown.
+    fn scroll(&mut self) {
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + self.visible() {
+            self.offset = self.selected + 1 - self.visible();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(page: &ScreenPage) -> Vec<String> {
+        page.rows
+            .iter()
+            .map(|row| {
+                let text: String = row.iter().map(|&c| c as u8 as char).collect();
+                text.trim_end().into()
+            })
+            .collect()
+    }
+
+    fn numbered(title: Option<&str>, items: usize) -> ScreenMenu {
+        let mut menu = ScreenMenu::new();
+        if let Some(title) = title {
+            menu = menu.title(title);
+        }
+        for i in 0..items {
+            menu = menu.item(&format!("item {}", i));
+        }
+        menu
+    }
+
+    #[test]
+    fn page_lines() {
+        let mut page = ScreenPage::new();
+        assert_eq!(lines(&page), ["", "", ""]);
+        page.set_line(1, "a line which is too long to fit");
+        assert_eq!(lines(&page), ["", "a line which is too", ""]);
+        assert!(write!(page.line(2), "{:>19}", 42).is_ok());
+        assert_eq!(lines(&page)[2], format!("{:>19}", 42));
+        assert!(write!(page.line(0), "{:>20}", 42).is_err());
+        assert_eq!(lines(&page)[0], format!("{:>19}", 4));
+        page.set_line(1, "short");
+        assert_eq!(lines(&page)[1], "short");
+    }
+
+    #[test]
+    fn menu_without_title_scrolls() {
+        let mut menu = numbered(None, 5);
+        assert_eq!(lines(&menu.page()), [">item 0", " item 1", " item 2"]);
+        menu.select(2);
+        assert_eq!(lines(&menu.page()), [" item 0", " item 1", ">item 2"]);
+        menu.select_next();
+        assert_eq!(lines(&menu.page()), [" item 1", " item 2", ">item 3"]);
+        menu.select(4);
+        assert_eq!(lines(&menu.page()), [" item 2", " item 3", ">item 4"]);
+        menu.select_previous();
+        menu.select_previous();
+        assert_eq!(lines(&menu.page()), [">item 2", " item 3", " item 4"]);
+        menu.select(0);
+        assert_eq!(lines(&menu.page()), [">item 0", " item 1", " item 2"]);
+    }
+
+    #[test]
+    fn menu_with_title_scrolls() {
+        let mut menu = numbered(Some("Autonomous"), 4);
+        assert_eq!(lines(&menu.page()), ["Autonomous", ">item 0", " item 1"]);
+        menu.select_next();
+        assert_eq!(lines(&menu.page()), ["Autonomous", " item 0", ">item 1"]);
+        menu.select_next();
+        assert_eq!(lines(&menu.page()), ["Autonomous", " item 1", ">item 2"]);
+        menu.select(0);
+        assert_eq!(lines(&menu.page()), ["Autonomous", ">item 0", " item 1"]);
+
+        // Adding a title leaves fewer lines for the items.
+        let mut menu = numbered(None, 3);
+        menu.select(2);
+        let menu = menu.title("Title");
+        assert_eq!(lines(&menu.page()), ["Title", " item 1", ">item 2"]);
+    }
+
+    #[test]
+    fn menu_selection_wraps() {
+        let mut menu = numbered(Some("Title"), 3);
+        menu.select_previous();
+        assert_eq!(menu.selected(), Some(2));
+        assert_eq!(menu.selected_item(), Some("item 2"));
+        assert_eq!(lines(&menu.page()), ["Title", " item 1", ">item 2"]);
+        menu.select_next();
+        assert_eq!(menu.selected(), Some(0));
+        assert_eq!(lines(&menu.page()), ["Title", ">item 0", " item 1"]);
+
+        let mut empty = numbered(Some("Title"), 0);
+        empty.select_next();
+        empty.select_previous();
+        assert_eq!(empty.selected(), None);
+        assert_eq!(empty.selected_item(), None);
+        assert_eq!(lines(&empty.page()), ["Title", "", ""]);
+    }
+}
@@ -0,0 +1,517 @@
+//! Recording and replay of controller inputs.
+
+use alloc::{format, vec::Vec};
+use core::time::Duration;
+
+use super::{Controller, ControllerData, ControllerError};
+use crate::{
+    bindings,
+    error::Error,
+    once::Once,
+    rtos::{time_since_start, DataSource, Instant, Mutex},
+};
+
+/// Identifies a controller log. Followed by the format version.
+const MAGIC: &[u8; 4] = b"VXCR";
+
+const VERSION: u8 = 1;
+
+const HEADER_SIZE: usize = MAGIC.len() + 1;
+
+/// The size of an encoded sample, excluding its timestamp.
+const SAMPLE_SIZE: usize = 8;
+
+static PLAYBACK: Once<Mutex<[Option<Playback>; 2]>> = Once::new();
+
+/// A snapshot of a controller's inputs, as stored in a [`ControllerLog`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ControllerSample {
+    /// The time since recording started.
+    pub time: Duration,
+    /// Whether the controller was connected.
+    pub connected: bool,
+    /// The state of the controller's inputs.
+    pub data: ControllerData,
+}
+
+impl ControllerSample {
+    pub(super) fn analog(&self, channel: bindings::controller_analog_e_t) -> i8 {
+        match channel {
+            bindings::controller_analog_e_t_E_CONTROLLER_ANALOG_LEFT_X => self.data.left_x,
+            bindings::controller_analog_e_t_E_CONTROLLER_ANALOG_LEFT_Y => self.data.left_y,
+            bindings::controller_analog_e_t_E_CONTROLLER_ANALOG_RIGHT_X => self.data.right_x,
+            bindings::controller_analog_e_t_E_CONTROLLER_ANALOG_RIGHT_Y => self.data.right_y,
+            _ => 0,
+        }
+    }
+
+    pub(super) fn digital(&self, button: bindings::controller_digital_e_t) -> bool {
+        self.data.digital(button)
+    }
+
+    /// Appends the sample to `out`, timestamped `delta` microseconds after the
+    /// previous one.
+    fn encode(&self, delta: u64, out: &mut Vec<u8>) {
+        let mut delta = delta;
+        while delta >= 0x80 {
+            out.push(delta as u8 | 0x80);
+            delta >>= 7;
+        }
+        out.push(delta as u8);
+
+        let data = &self.data;
+        let buttons = [
+            data.l1,
+            data.l2,
+            data.r1,
+            data.r2,
+            data.up,
+            data.down,
+            data.left,
+            data.right,
+            data.x,
+            data.b,
+            data.y,
+            data.a,
+            self.connected,
+        ]
+        .iter()
+        .enumerate()
+        .fold(0u16, |bits, (i, &b)| bits | (b as u16) << i);
+        out.extend_from_slice(&[
+            data.left_x as u8,
+            data.left_y as u8,
+            data.right_x as u8,
+            data.right_y as u8,
+        ]);
+        out.extend_from_slice(&buttons.to_le_bytes());
+        out.push(data.battery_level.clamp(0, u8::MAX as i32) as u8);
+        out.push(data.battery_capacity.clamp(0, u8::MAX as i32) as u8);
+    }
+
+    /// Reads a sample from the front of `bytes`, timestamped relative to
+    /// `previous`. Returns `None` if the sample is incomplete.
+    fn decode(bytes: &mut &[u8], previous: Duration) -> Option<Self> {
+        let mut delta = 0u64;
+        let mut shift = 0;
+        loop {
+            let (&byte, rest) = bytes.split_first()?;
+            *bytes = rest;
+            if shift >= u64::BITS {
+                return None;
+            }
+            delta |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+
+        if bytes.len() < SAMPLE_SIZE {
+            return None;
+        }
+        let (b, rest) = bytes.split_at(SAMPLE_SIZE);
+        *bytes = rest;
+        let buttons = u16::from_le_bytes([b[4], b[5]]);
+        let bit = |i: u32| buttons & (1 << i) != 0;
+        Some(Self {
+            time: previous + Duration::from_micros(delta),
+            connected: bit(12),
+            data: ControllerData {
+                left_x: b[0] as i8,
+                left_y: b[1] as i8,
+                right_x: b[2] as i8,
+                right_y: b[3] as i8,
+                l1: bit(0),
+                l2: bit(1),
+                r1: bit(2),
+                r2: bit(3),
+                up: bit(4),
+                down: bit(5),
+                left: bit(6),
+                right: bit(7),
+                x: bit(8),
+                b: bit(9),
+                y: bit(10),
+                a: bit(11),
+                battery_level: b[6] as i32,
+                battery_capacity: b[7] as i32,
+            },
+        })
+    }
+}
+
+/// A recording of a controller's inputs in a compact binary format, as
+/// produced by a [`ControllerRecorder`].
+///
+/// Each sample holds until the time of the next one; the last sample marks
+/// the end of the recording.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ControllerLog(Vec<u8>);
+
+impl ControllerLog {
+    /// Parses a log from its binary form, as given by
+    /// [`ControllerLog::as_bytes()`].
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        if bytes.len() < HEADER_SIZE || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(Error::Custom("not a controller log".into()));
+        }
+        if bytes[MAGIC.len()] != VERSION {
+            return Err(Error::Custom(format!(
+                "unsupported controller log version: {}",
+                bytes[MAGIC.len()]
+            )));
+        }
+        let mut rest = &bytes[HEADER_SIZE..];
+        let mut time = Duration::ZERO;
+        while !rest.is_empty() {
+            time = ControllerSample::decode(&mut rest, time)
+                .ok_or_else(|| Error::Custom("truncated controller log".into()))?
+                .time;
+        }
+        Ok(Self(bytes))
+    }
+
+    /// Gets the binary form of the log.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Converts the log into its binary form.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Iterates over the samples in the log, in chronological order.
+    pub fn samples(&self) -> impl Iterator<Item = ControllerSample> + '_ {
+        let mut rest = &self.0[HEADER_SIZE..];
+        let mut time = Duration::ZERO;
+        core::iter::from_fn(move || {
+            let sample = ControllerSample::decode(&mut rest, time)?;
+            time = sample.time;
+            Some(sample)
+        })
+    }
+
+    /// Gets the length of the recording.
+    pub fn duration(&self) -> Duration {
+        self.samples().last().map_or(Duration::ZERO, |s| s.time)
+    }
+}
+
+/// Records a controller's inputs into a [`ControllerLog`].
+///
+/// Samples are only stored when the inputs change, so the recorder may be fed
+/// as often as is convenient (e.g., on every iteration of a driver control
+/// loop).
+pub struct ControllerRecorder {
+    bytes: Vec<u8>,
+    start: Instant,
+    last: Option<ControllerSample>,
+}
+
+impl ControllerRecorder {
+    /// Creates a new recorder. Sample times are measured from now.
+    pub fn new() -> Self {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        Self {
+            bytes,
+            start: time_since_start(),
+            last: None,
+        }
+    }
+
+    /// Reads the inputs of a controller and records them.
+    pub fn record(&mut self, controller: &Controller) -> Result<(), ControllerError> {
+        let connected = controller.is_connected()?;
+        let data = controller.read()?;
+        self.push(connected, data);
+        Ok(())
+    }
+
+    /// Records the given inputs at the current time.
+    pub fn push(&mut self, connected: bool, data: ControllerData) {
+        if matches!(self.last, Some(last) if last.connected == connected && last.data == data) {
+            return;
+        }
+        self.append(connected, data);
+    }
+
+    /// Stops recording, returning the log. The last inputs recorded are held
+    /// until now.
+    pub fn finish(mut self) -> ControllerLog {
+        if let Some(last) = self.last {
+            self.append(last.connected, last.data);
+        }
+        ControllerLog(self.bytes)
+    }
+
+    fn append(&mut self, connected: bool, data: ControllerData) {
+        let sample = ControllerSample {
+            time: time_since_start() - self.start,
+            connected,
+            data,
+        };
+        let previous = self.last.map_or(Duration::ZERO, |s| s.time);
+        sample.encode((sample.time - previous).as_micros() as u64, &mut self.bytes);
+        self.last = Some(sample);
+    }
+}
+
+impl Default for ControllerRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Playback {
+    start: Instant,
+    samples: Vec<ControllerSample>,
+}
+
+fn playback() -> &'static Mutex<[Option<Playback>; 2]> {
+    PLAYBACK.call_once(|| Mutex::new([None, None]))
+}
+
+/// Gets the sample currently being replayed through a controller, if any.
+pub(super) fn replayed(id: bindings::controller_id_e_t) -> Option<ControllerSample> {
+    let mut playback = PLAYBACK.get()?.lock();
+    let slot = playback.get_mut(id as usize)?;
+    let Playback { start, samples } = slot.as_ref()?;
+    let elapsed = time_since_start() - *start;
+    if samples.last().map_or(true, |last| elapsed > last.time) {
+        *slot = None;
+        return None;
+    }
+    let index = samples.partition_point(|s| s.time <= elapsed);
+    Some(samples[index.saturating_sub(1)])
+}
+
+impl Controller {
+    /// Replays a log through this controller, starting now.
+    ///
+    /// Until the end of the log has passed or [`Controller::stop_replay()`] is
+    /// called, the controller and its sticks and buttons report the recorded
+    /// inputs instead of those of the physical controller. The last sample is
+    /// reported up to and including its own time.
+    pub fn replay(&self, log: &ControllerLog) {
+        if let Some(slot) = playback().lock().get_mut(self.id as usize) {
+            *slot = Some(Playback {
+                start: time_since_start(),
+                samples: log.samples().collect(),
+            });
+        }
+    }
+
+    /// Stops any replay in progress, so that the controller reports the inputs
+    /// of the physical controller again.
+    pub fn stop_replay(&self) {
+        if let Some(slot) = playback().lock().get_mut(self.id as usize) {
+            *slot = None;
+        }
+    }
+
+    /// Checks whether a log is being replayed through this controller.
+    pub fn is_replaying(&self) -> bool {
+        replayed(self.id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{controller::ControllerId, rtos::Task, sim::PeripheralsBuilder};
+
+    fn round_trip(sample: &ControllerSample, previous: Duration) -> ControllerSample {
+        let mut bytes = Vec::new();
+        sample.encode((sample.time - previous).as_micros() as u64, &mut bytes);
+        let mut rest = bytes.as_slice();
+        let decoded = ControllerSample::decode(&mut rest, previous).unwrap();
+        assert!(rest.is_empty());
+        decoded
+    }
+
+    #[test]
+    fn sticks_round_trip() {
+        for value in [i8::MIN, -127, -1, 0, 1, i8::MAX] {
+            let sample = ControllerSample {
+                time: Duration::from_millis(10),
+                connected: true,
+                data: ControllerData {
+                    left_x: value,
+                    left_y: value.wrapping_neg(),
+                    right_x: !value,
+                    right_y: value,
+                    ..Default::default()
+                },
+            };
+            assert_eq!(round_trip(&sample, Duration::ZERO), sample);
+        }
+    }
+
+    #[test]
+    fn buttons_round_trip() {
+        let buttons: [fn(&mut ControllerData) -> &mut bool; 12] = [
+            |d| &mut d.l1,
+            |d| &mut d.l2,
+            |d| &mut d.r1,
+            |d| &mut d.r2,
+            |d| &mut d.up,
+            |d| &mut d.down,
+            |d| &mut d.left,
+            |d| &mut d.right,
+            |d| &mut d.x,
+            |d| &mut d.y,
+            |d| &mut d.a,
+            |d| &mut d.b,
+        ];
+        for (i, button) in buttons.iter().enumerate() {
+            for connected in [false, true] {
+                let mut sample = ControllerSample {
+                    connected,
+                    ..Default::default()
+                };
+                *button(&mut sample.data) = true;
+                assert_eq!(round_trip(&sample, Duration::ZERO), sample, "button {}", i);
+            }
+        }
+
+        let mut all = ControllerSample {
+            connected: true,
+            ..Default::default()
+        };
+        for button in buttons {
+            *button(&mut all.data) = true;
+        }
+        assert_eq!(round_trip(&all, Duration::ZERO), all);
+    }
+
+    #[test]
+    fn battery_is_clamped() {
+        for (value, expected) in [(-5, 0), (0, 0), (80, 80), (255, 255), (1000, 255)] {
+            let sample = ControllerSample {
+                data: ControllerData {
+                    battery_level: value,
+                    battery_capacity: value,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let decoded = round_trip(&sample, Duration::ZERO);
+            assert_eq!(decoded.data.battery_level, expected);
+            assert_eq!(decoded.data.battery_capacity, expected);
+        }
+    }
+
+    #[test]
+    fn timestamps_round_trip() {
+        let previous = Duration::from_secs(3);
+        for (delta, len) in [
+            (0, 1),
+            (1, 1),
+            (0x7f, 1),
+            (0x80, 2),
+            (0x3fff, 2),
+            (0x4000, 3),
+            (u32::MAX as u64, 5),
+            (u64::MAX, 10),
+        ] {
+            let sample = ControllerSample::default();
+            let mut bytes = Vec::new();
+            sample.encode(delta, &mut bytes);
+            assert_eq!(bytes.len(), len + SAMPLE_SIZE, "delta {}", delta);
+
+            let mut rest = bytes.as_slice();
+            let decoded = ControllerSample::decode(&mut rest, previous).unwrap();
+            assert!(rest.is_empty());
+            assert_eq!(decoded.time, previous + Duration::from_micros(delta));
+
+            for end in 0..bytes.len() {
+                assert_eq!(ControllerSample::decode(&mut &bytes[..end], previous), None);
+            }
+        }
+    }
+
+    #[test]
+    fn log_from_bytes() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        let samples = [
+            ControllerSample {
+                connected: true,
+                ..Default::default()
+            },
+            ControllerSample {
+                time: Duration::from_millis(250),
+                connected: true,
+                data: ControllerData {
+                    left_y: 127,
+                    a: true,
+                    ..Default::default()
+                },
+            },
+        ];
+        let mut previous = Duration::ZERO;
+        for sample in &samples {
+            sample.encode((sample.time - previous).as_micros() as u64, &mut bytes);
+            previous = sample.time;
+        }
+
+        let log = ControllerLog::from_bytes(bytes.clone()).unwrap();
+        assert!(log.samples().eq(samples));
+        assert_eq!(log.duration(), Duration::from_millis(250));
+        assert_eq!(log.as_bytes(), bytes);
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(ControllerLog::from_bytes(bad_magic).is_err());
+        let mut bad_version = bytes.clone();
+        bad_version[MAGIC.len()] = VERSION + 1;
+        assert!(ControllerLog::from_bytes(bad_version).is_err());
+        bytes.pop();
+        assert!(ControllerLog::from_bytes(bytes).is_err());
+    }
+
+    #[test]
+    fn replay_holds_last_sample_until_end() {
+        let physical = ControllerData {
+            battery_level: 100,
+            ..Default::default()
+        };
+        let (peripherals, _sim) = PeripheralsBuilder::new()
+            .controller(ControllerId::Master, move |_| Some(physical))
+            .build();
+        let master = &peripherals.master_controller;
+        let first = ControllerData {
+            left_x: 50,
+            ..Default::default()
+        };
+        let last = ControllerData {
+            left_x: -50,
+            b: true,
+            ..Default::default()
+        };
+
+        let mut recorder = ControllerRecorder::new();
+        recorder.push(true, first);
+        Task::delay(Duration::from_millis(20));
+        recorder.push(true, last);
+        Task::delay(Duration::from_millis(20));
+        let log = recorder.finish();
+        assert_eq!(log.duration(), Duration::from_millis(40));
+
+        master.replay(&log);
+        assert_eq!(master.read().unwrap(), first);
+        Task::delay(Duration::from_millis(20));
+        assert_eq!(master.read().unwrap(), last);
+        Task::delay(Duration::from_millis(20));
+        assert!(master.is_replaying());
+        assert_eq!(master.read().unwrap(), last);
+        assert_eq!(master.left_stick.get_x().unwrap(), -50);
+        Task::delay(Duration::from_millis(1));
+        assert!(!master.is_replaying());
+        assert_eq!(master.read().unwrap(), physical);
+    }
+}
@@ -0,0 +1,240 @@
+//! Shaping of analog stick inputs.
+
+use super::{AnalogStick, ControllerError};
+use crate::math::{exp, sqrt};
+
+/// A response curve applied to an analog stick axis, mapping an input from -1
+/// to 1 to an output over the same range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Curve {
+    /// The output equals the input.
+    Linear,
+    /// A blend of the linear and cubic responses, `(1 - w) * x + w * x^3`,
+    /// with the given weight `w` from 0 (linear) to 1 (fully cubic).
+    Cubic(f64),
+    /// An exponential response, `(e^(k * |x|) - 1) / (e^k - 1)`, with the
+    /// given strength `k`. Larger values give finer control near the centre;
+    /// values near 0 are close to linear.
+    Exponential(f64),
+}
+
+impl Curve {
+    /// Applies the curve to an input from -1 to 1.
+    pub fn apply(&self, x: f64) -> f64 {
+        match *self {
+            Self::Linear => x,
+            Self::Cubic(weight) => (1.0 - weight) * x + weight * x * x * x,
+            Self::Exponential(k) if k > 1e-6 => {
+                let curve = |x: f64| (exp(k * x) - 1.0) / (exp(k) - 1.0);
+                if x < 0.0 {
+                    -curve(-x)
+                } else {
+                    curve(x)
+                }
+            }
+            Self::Exponential(_) => x,
+        }
+    }
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self::Linear
+    }
+}
+
+/// Shapes the raw readings of an analog stick, applying deadbands and a
+/// response curve and scaling each axis to the range -1 to 1.
+///
+/// The axial deadband is applied to each axis on its own, then the radial
+/// deadband to the position of the stick as a whole, and finally the curve to
+/// each axis. Both deadbands rescale the remaining travel, so that the output
+/// still starts from 0 at the edge of the deadband and reaches 1 at full
+/// deflection.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StickShaper {
+    axial_deadband: f64,
+    radial_deadband: f64,
+    curve: Curve,
+}
+
+impl StickShaper {
+    #[inline]
+    /// Creates a shaper with no deadbands and a linear response.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the axial deadband, as a fraction of full deflection from 0 to 1.
+    /// Panics if the deadband is out of range.
+    pub fn axial_deadband(mut self, deadband: f64) -> Self {
+        assert!(
+            (0.0..1.0).contains(&deadband),
+            "deadband must be from 0 to 1"
+        );
+        self.axial_deadband = deadband;
+        self
+    }
+
+    /// Sets the radial deadband, as a fraction of full deflection from 0 to 1.
+    /// Panics if the deadband is out of range.
+    pub fn radial_deadband(mut self, deadband: f64) -> Self {
+        assert!(
+            (0.0..1.0).contains(&deadband),
+            "deadband must be from 0 to 1"
+        );
+        self.radial_deadband = deadband;
+        self
+    }
+
+    /// Sets the response curve.
+    pub fn curve(mut self, curve: Curve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    /// Shapes a single axis. The radial deadband does not apply.
+    pub fn shape_axis(&self, value: i8) -> f64 {
+        self.curve
+            .apply(rescale(normalize(value), self.axial_deadband))
+    }
+
+    /// Shapes both axes of a stick, returning the shaped x and y values.
+    pub fn shape(&self, x: i8, y: i8) -> (f64, f64) {
+        let x = rescale(normalize(x), self.axial_deadband);
+        let y = rescale(normalize(y), self.axial_deadband);
+        let magnitude = sqrt(x * x + y * y);
+        if magnitude == 0.0 {
+            return (0.0, 0.0);
+        }
+        let scale = rescale(magnitude, self.radial_deadband) / magnitude;
+        (
+            self.curve.apply((x * scale).clamp(-1.0, 1.0)),
+            self.curve.apply((y * scale).clamp(-1.0, 1.0)),
+        )
+    }
+}
+
+impl AnalogStick {
+    /// Reads both axes of the analog stick and shapes them with the given
+    /// shaper, returning the shaped x and y values.
+    pub fn get_shaped(&self, shaper: &StickShaper) -> Result<(f64, f64), ControllerError> {
+        Ok(shaper.shape(self.get_x()?, self.get_y()?))
+    }
+}
+
+/// Converts a raw stick reading to the range -1 to 1.
+fn normalize(value: i8) -> f64 {
+    (value as f64 / 127.0).clamp(-1.0, 1.0)
+}
+
+/// Applies a deadband to a value from -1 to 1, rescaling the remaining range.
+fn rescale(value: f64, deadband: f64) -> f64 {
+    if value > deadband {
+        (value - deadband) / (1.0 - deadband)
+    } else if value < -deadband {
+        (value + deadband) / (1.0 - deadband)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [Curve; 4] = [
+        Curve::Linear,
+        Curve::Cubic(0.5),
+        Curve::Cubic(1.0),
+        Curve::Exponential(3.0),
+    ];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn axial_deadband_edges() {
+        let shaper = StickShaper::new().axial_deadband(10.0 / 127.0);
+        assert_eq!(shaper.shape_axis(0), 0.0);
+        assert_eq!(shaper.shape_axis(10), 0.0);
+        assert_eq!(shaper.shape_axis(-10), 0.0);
+        assert_close(shaper.shape_axis(11), 1.0 / 117.0);
+        assert_close(shaper.shape_axis(-11), -1.0 / 117.0);
+    }
+
+    #[test]
+    fn full_deflection() {
+        for curve in CURVES {
+            for deadband in [0.0, 0.1] {
+                let shaper = StickShaper::new().axial_deadband(deadband).curve(curve);
+                assert_close(shaper.shape_axis(127), 1.0);
+                assert_close(shaper.shape_axis(-127), -1.0);
+                assert_close(shaper.shape_axis(-128), -1.0);
+
+                let shaper = shaper.radial_deadband(deadband);
+                let (x, y) = shaper.shape(0, 127);
+                assert_close(x, 0.0);
+                assert_close(y, 1.0);
+                let (x, y) = shaper.shape(-128, 0);
+                assert_close(x, -1.0);
+                assert_close(y, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn radial_deadband() {
+        let shaper = StickShaper::new().radial_deadband(0.1);
+        // Each axis is inside the deadband on its own, but not together.
+        assert_eq!(shaper.shape(8, 8), (0.0, 0.0));
+        let (x, y) = shaper.shape(9, 9);
+        assert!(x > 0.0 && x < 0.01, "{}", x);
+        assert_eq!(x, y);
+        assert_eq!(shaper.shape_axis(8), 8.0 / 127.0);
+
+        // Beyond full deflection on the diagonal, each axis is clamped.
+        assert_eq!(shaper.shape(127, 127), (1.0, 1.0));
+        assert_eq!(shaper.shape(-128, 127), (-1.0, 1.0));
+        let (x, y) = shaper.shape(64, 64);
+        assert_eq!(x, y);
+        let magnitude = (x * x + y * y).sqrt();
+        assert_close(magnitude, (64.0 / 127.0 * 2f64.sqrt() - 0.1) / 0.9);
+    }
+
+    #[test]
+    fn exponential_near_zero() {
+        for x in [-1.0, -0.5, -0.1, 0.0, 0.3, 0.75, 1.0] {
+            assert_eq!(Curve::Exponential(0.0).apply(x), x);
+            assert_eq!(Curve::Exponential(1e-7).apply(x), x);
+            for k in [2e-6, 1e-5, 1e-4, 1e-3] {
+                let y = Curve::Exponential(k).apply(x);
+                assert!((y - x).abs() <= k, "k = {}, x = {}: {}", k, x, y);
+            }
+        }
+        assert!(Curve::Exponential(3.0).apply(0.5) < 0.5);
+    }
+
+    #[test]
+    fn sign_symmetry() {
+        for curve in CURVES {
+            let shaper = StickShaper::new()
+                .axial_deadband(0.05)
+                .radial_deadband(0.1)
+                .curve(curve);
+            for value in -127..=127 {
+                assert_eq!(shaper.shape_axis(-value), -shaper.shape_axis(value));
+                let (x, y) = shaper.shape(value, 50);
+                assert_eq!(shaper.shape(-value, 50), (-x, y));
+                assert_eq!(shaper.shape(value, -50), (x, -y));
+                assert_eq!(shaper.shape(50, value), (y, x));
+            }
+        }
+    }
+}
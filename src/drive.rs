@@ -0,0 +1,187 @@
+//! Drive mixers, which turn driver inputs into motor voltages.
+//!
+//! Inputs range from -1 to 1, such as the shaped stick values from
+//! [`StickShaper`](crate::controller::StickShaper). Outputs are in millivolts,
+//! from -12000 to 12000, suitable for
+//! [`Motor::move_voltage()`](crate::motor::Motor::move_voltage()) or
+//! [`VoltageActuator::set_voltage()`].
+//!
+//! When a mix asks for more than full power on any side or wheel, all of the
+//! outputs are scaled down together, so that the robot keeps the requested
+//! direction of travel.
+
+use crate::{error::Error, hal::VoltageActuator};
+
+/// The largest voltage produced by the mixers, in millivolts.
+pub const MAX_VOLTAGE: i32 = 12000;
+
+/// The voltages for the two sides of a tank-style drive, in millivolts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TankVoltages {
+    /// The voltage for the left side.
+    pub left: i32,
+    /// The voltage for the right side.
+    pub right: i32,
+}
+
+impl TankVoltages {
+    /// Applies the voltages to the actuators on each side.
+    pub fn apply(
+        &self,
+        left: &mut impl VoltageActuator,
+        right: &mut impl VoltageActuator,
+    ) -> Result<(), Error> {
+        left.set_voltage(self.left).map_err(Into::into)?;
+        right.set_voltage(self.right).map_err(Into::into)
+    }
+}
+
+/// The voltages for the four wheels of a holonomic drive, such as an X-drive
+/// or mecanum drive, in millivolts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HolonomicVoltages {
+    /// The voltage for the front left wheel.
+    pub front_left: i32,
+    /// The voltage for the front right wheel.
+    pub front_right: i32,
+    /// The voltage for the back left wheel.
+    pub back_left: i32,
+    /// The voltage for the back right wheel.
+    pub back_right: i32,
+}
+
+impl HolonomicVoltages {
+    /// Applies the voltages to the actuators for each wheel.
+    pub fn apply(
+        &self,
+        front_left: &mut impl VoltageActuator,
+        front_right: &mut impl VoltageActuator,
+        back_left: &mut impl VoltageActuator,
+        back_right: &mut impl VoltageActuator,
+    ) -> Result<(), Error> {
+        front_left
+            .set_voltage(self.front_left)
+            .map_err(Into::into)?;
+        front_right
+            .set_voltage(self.front_right)
+            .map_err(Into::into)?;
+        back_left.set_voltage(self.back_left).map_err(Into::into)?;
+        back_right.set_voltage(self.back_right).map_err(Into::into)
+    }
+}
+
+/// Tank drive: each input drives one side of the robot directly.
+pub fn tank(left: f64, right: f64) -> TankVoltages {
+    TankVoltages {
+        left: to_voltage(left.clamp(-1.0, 1.0)),
+        right: to_voltage(right.clamp(-1.0, 1.0)),
+    }
+}
+
+/// Arcade drive: one input drives the robot forwards and the other turns it
+/// clockwise.
+pub fn arcade(forward: f64, turn: f64) -> TankVoltages {
+    let [left, right] = desaturate([forward + turn, forward - turn]);
+    TankVoltages {
+        left: to_voltage(left),
+        right: to_voltage(right),
+    }
+}
+
+/// Curvature drive, also known as "cheesy" drive: one input drives the robot
+/// forwards and the other sets the curvature of its path, so that the turning
+/// radius stays the same at any speed.
+///
+/// Since the robot cannot turn without moving forwards, `quick_turn` switches
+/// to turning in place as in [`arcade()`], typically while a button is held.
+pub fn curvature(forward: f64, turn: f64, quick_turn: bool) -> TankVoltages {
+    let turn = if quick_turn {
+        turn
+    } else if forward < 0.0 {
+        -forward * turn
+    } else {
+        forward * turn
+    };
+    arcade(forward, turn)
+}
+
+/// Holonomic drive for an X-drive or mecanum drive: the robot drives forwards,
+/// strafes to the right and turns clockwise at once.
+pub fn holonomic(forward: f64, strafe: f64, turn: f64) -> HolonomicVoltages {
+    let [front_left, front_right, back_left, back_right] = desaturate([
+        forward + strafe + turn,
+        forward - strafe - turn,
+        forward - strafe + turn,
+        forward + strafe - turn,
+    ]);
+    HolonomicVoltages {
+        front_left: to_voltage(front_left),
+        front_right: to_voltage(front_right),
+        back_left: to_voltage(back_left),
+        back_right: to_voltage(back_right),
+    }
+}
+
+/// Scales the values down together so that none exceeds 1 in magnitude.
+fn desaturate<const N: usize>(mut values: [f64; N]) -> [f64; N] {
+    let max = values
+        .iter()
+        .map(|&v| if v < 0.0 { -v } else { v })
+        .fold(1.0, f64::max);
+    for v in values.iter_mut() {
+        *v /= max;
+    }
+    values
+}
+
+fn to_voltage(value: f64) -> i32 {
+    (value * MAX_VOLTAGE as f64) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::fake::FakeActuator;
+
+    #[test]
+    fn tank_voltages_apply() {
+        let (mut left, mut right) = (FakeActuator::default(), FakeActuator::default());
+        arcade(0.5, 0.25).apply(&mut left, &mut right).unwrap();
+        tank(-2.0, 1.0).apply(&mut left, &mut right).unwrap();
+        assert_eq!(left.voltages, [9000, -12000]);
+        assert_eq!(right.voltages, [3000, 12000]);
+    }
+
+    #[test]
+    fn tank_voltages_apply_stops_at_error() {
+        let mut left = FakeActuator {
+            fail: true,
+            ..Default::default()
+        };
+        let mut right = FakeActuator::default();
+        assert!(tank(1.0, 1.0).apply(&mut left, &mut right).is_err());
+        assert!(left.voltages.is_empty());
+        assert!(right.voltages.is_empty());
+
+        let mut left = FakeActuator::default();
+        let mut right = FakeActuator {
+            fail: true,
+            ..Default::default()
+        };
+        assert!(tank(1.0, 1.0).apply(&mut left, &mut right).is_err());
+        assert_eq!(left.voltages, [12000]);
+    }
+
+    #[test]
+    fn holonomic_voltages_apply() {
+        let mut wheels: [FakeActuator; 4] = Default::default();
+        let [front_left, front_right, back_left, back_right] = &mut wheels;
+        holonomic(0.5, 0.25, 0.0)
+            .apply(front_left, front_right, back_left, back_right)
+            .unwrap();
+        assert_eq!(
+            wheels.map(|wheel| wheel.voltages),
+            [[9000], [3000], [3000], [9000]]
+        );
+    }
+}
@@ -100,10 +100,14 @@ impl<T> SentinelError for *mut T {
 }
 
 // Need to manually declare until https://github.com/rust-lang/libc/issues/1995 is resolved.
+#[cfg(not(feature = "sim"))]
 extern "C" {
     fn __errno() -> *mut i32;
 }
 
+#[cfg(feature = "sim")]
+use bindings::__errno;
+
 /// Gets the value of `errno` for the current task.
 #[inline]
 pub fn get_errno() -> libc::c_int {
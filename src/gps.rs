@@ -0,0 +1,285 @@
+//! # GPS Sensor API.
+
+use core::time::Duration;
+
+use crate::{
+    bindings,
+    error::{get_errno, Error},
+    hal::HeadingSensor,
+    rtos::DataSource,
+};
+
+/// A struct which represents a V5 smart port configured as a GPS sensor.
+///
+/// Positions are measured in metres from the centre of the field, and
+/// headings in degrees clockwise from the positive y-axis.
+pub struct GpsSensor {
+    port: u8,
+}
+
+impl GpsSensor {
+    /// Constructs a new GPS sensor.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it allows the user to create multiple
+    /// mutable references to the same GPS sensor. You likely want to
+    /// implement [`Robot::new()`](crate::robot::Robot::new()) instead.
+    pub unsafe fn new(port: u8) -> GpsSensor {
+        GpsSensor { port }
+    }
+
+    /// Sets the initial pose of the robot and the offset of the sensor from
+    /// the robot's centre of rotation.
+    ///
+    /// The initial pose is used until the sensor can see the field code strip.
+    pub fn initialize(&mut self, pose: GpsPose, offset: GpsOffset) -> Result<(), GpsSensorError> {
+        match unsafe {
+            bindings::gps_initialize_full(
+                self.port,
+                pose.x,
+                pose.y,
+                pose.heading,
+                offset.x,
+                offset.y,
+            )
+        } {
+            bindings::PROS_ERR_ => Err(GpsSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the offset of the sensor from the robot's centre of rotation.
+    pub fn set_offset(&mut self, offset: GpsOffset) -> Result<(), GpsSensorError> {
+        match unsafe { bindings::gps_set_offset(self.port, offset.x, offset.y) } {
+            bindings::PROS_ERR_ => Err(GpsSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the offset of the sensor from the robot's centre of rotation.
+    pub fn get_offset(&self) -> Result<GpsOffset, GpsSensorError> {
+        let mut offset = GpsOffset { x: 0.0, y: 0.0 };
+        match unsafe { bindings::gps_get_offset(self.port, &mut offset.x, &mut offset.y) } {
+            bindings::PROS_ERR_ => Err(GpsSensorError::from_errno()),
+            _ => Ok(offset),
+        }
+    }
+
+    /// Sets the pose of the robot, which is used until the sensor can see the
+    /// field code strip.
+    pub fn set_position(&mut self, pose: GpsPose) -> Result<(), GpsSensorError> {
+        match unsafe { bindings::gps_set_position(self.port, pose.x, pose.y, pose.heading) } {
+            bindings::PROS_ERR_ => Err(GpsSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the interval at which the sensor reports new data. This is
+    /// rounded down to a multiple of 5 milliseconds, with a minimum of 5
+    /// milliseconds.
+    pub fn set_data_rate(&mut self, rate: Duration) -> Result<(), GpsSensorError> {
+        let rate = rate.as_millis().min(u32::MAX as u128) as u32;
+        match unsafe { bindings::gps_set_data_rate(self.port, rate) } {
+            bindings::PROS_ERR_ => Err(GpsSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the sensor's estimate of the error in its position, in metres.
+    pub fn get_error(&self) -> Result<f64, GpsSensorError> {
+        match unsafe { bindings::gps_get_error(self.port) } {
+            x if x == bindings::PROS_ERR_F_ => Err(GpsSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Gets the position of the robot and the orientation of the sensor.
+    pub fn get_status(&self) -> Result<GpsStatus, GpsSensorError> {
+        match unsafe { bindings::gps_get_status(self.port) } {
+            x if x.x == bindings::PROS_ERR_F_ => Err(GpsSensorError::from_errno()),
+            x => Ok(GpsStatus {
+                x: x.x,
+                y: x.y,
+                pitch: x.pitch,
+                roll: x.roll,
+                yaw: x.yaw,
+            }),
+        }
+    }
+
+    /// Gets the heading of the robot.
+    ///
+    /// This value is bounded by [0, 360).
+    pub fn get_heading(&self) -> Result<f64, GpsSensorError> {
+        match unsafe { bindings::gps_get_heading(self.port) } {
+            x if x == bindings::PROS_ERR_F_ => Err(GpsSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Gets the heading of the robot, without any smoothing applied.
+    ///
+    /// This value is bounded by [0, 360).
+    pub fn get_heading_raw(&self) -> Result<f64, GpsSensorError> {
+        match unsafe { bindings::gps_get_heading_raw(self.port) } {
+            x if x == bindings::PROS_ERR_F_ => Err(GpsSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Gets the total number of degrees the robot has turned clockwise.
+    ///
+    /// This value is theoretically unbounded.
+    pub fn get_rotation(&self) -> Result<f64, GpsSensorError> {
+        match unsafe { bindings::gps_get_rotation(self.port) } {
+            x if x == bindings::PROS_ERR_F_ => Err(GpsSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Sets the current rotation value to the given target.
+    pub fn set_rotation(&mut self, rotation: f64) -> Result<(), GpsSensorError> {
+        match unsafe { bindings::gps_set_rotation(self.port, rotation) } {
+            bindings::PROS_ERR_ => Err(GpsSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Resets the current rotation value to zero.
+    pub fn tare_rotation(&mut self) -> Result<(), GpsSensorError> {
+        match unsafe { bindings::gps_tare_rotation(self.port) } {
+            bindings::PROS_ERR_ => Err(GpsSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the rotation rates of the sensor, in degrees per second.
+    pub fn get_gyro_rate(&self) -> Result<GpsRaw, GpsSensorError> {
+        match unsafe { bindings::gps_get_gyro_rate(self.port) } {
+            x if x.x == bindings::PROS_ERR_F_ => Err(GpsSensorError::from_errno()),
+            x => Ok(GpsRaw {
+                x: x.x,
+                y: x.y,
+                z: x.z,
+            }),
+        }
+    }
+
+    /// Gets the accelerations of the sensor, in g.
+    pub fn get_accel(&self) -> Result<GpsRaw, GpsSensorError> {
+        match unsafe { bindings::gps_get_accel(self.port) } {
+            x if x.x == bindings::PROS_ERR_F_ => Err(GpsSensorError::from_errno()),
+            x => Ok(GpsRaw {
+                x: x.x,
+                y: x.y,
+                z: x.z,
+            }),
+        }
+    }
+}
+
+impl HeadingSensor for GpsSensor {
+    type Error = GpsSensorError;
+
+    fn heading(&self) -> Result<f64, Self::Error> {
+        self.get_rotation()
+    }
+
+    fn set_heading(&mut self, heading: f64) -> Result<(), Self::Error> {
+        self.set_rotation(heading)
+    }
+}
+
+impl DataSource for GpsSensor {
+    type Data = GpsPose;
+
+    type Error = GpsSensorError;
+
+    fn read(&self) -> Result<Self::Data, Self::Error> {
+        let status = self.get_status()?;
+        Ok(GpsPose {
+            x: status.x,
+            y: status.y,
+            heading: self.get_heading()?,
+        })
+    }
+}
+
+/// Represents the pose of a robot on the field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpsPose {
+    /// The x-coordinate of the robot, in metres.
+    pub x: f64,
+    /// The y-coordinate of the robot, in metres.
+    pub y: f64,
+    /// The heading of the robot, in degrees from 0 to 360.
+    pub heading: f64,
+}
+
+/// Represents the offset of a GPS sensor from the robot's centre of rotation,
+/// in metres.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpsOffset {
+    /// The offset to the right.
+    pub x: f64,
+    /// The offset to the front.
+    pub y: f64,
+}
+
+/// Represents the position and orientation returned from a GPS sensor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpsStatus {
+    /// The x-coordinate of the robot, in metres.
+    pub x: f64,
+    /// The y-coordinate of the robot, in metres.
+    pub y: f64,
+    /// The pitch of the sensor, in degrees.
+    pub pitch: f64,
+    /// The roll of the sensor, in degrees.
+    pub roll: f64,
+    /// The yaw of the sensor, in degrees.
+    pub yaw: f64,
+}
+
+/// Represents raw values returned from a GPS sensor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpsRaw {
+    /// The x value.
+    pub x: f64,
+    /// The y value.
+    pub y: f64,
+    /// The z value.
+    pub z: f64,
+}
+
+/// Represents possible errors for GPS sensor operations.
+#[derive(Debug)]
+pub enum GpsSensorError {
+    /// Port is out of range (1-21).
+    PortOutOfRange,
+    /// Port cannot be configured as a GPS sensor.
+    PortNotGpsSensor,
+    /// Unknown error.
+    Unknown(i32),
+}
+
+impl GpsSensorError {
+    fn from_errno() -> Self {
+        match get_errno() {
+            libc::ENXIO => Self::PortOutOfRange,
+            libc::ENODEV => Self::PortNotGpsSensor,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl From<GpsSensorError> for Error {
+    fn from(err: GpsSensorError) -> Self {
+        match err {
+            GpsSensorError::PortOutOfRange => Error::Custom("port out of range".into()),
+            GpsSensorError::PortNotGpsSensor => Error::Custom("port not a gps sensor".into()),
+            GpsSensorError::Unknown(n) => Error::System(n),
+        }
+    }
+}
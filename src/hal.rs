@@ -0,0 +1,153 @@
+//! Hardware abstraction traits.
+//!
+//! These traits are implemented by the device types in this crate, so that
+//! higher-level code (drivetrains, odometry, control loops, etc.) can be
+//! written generically over the devices it uses, and tested against fakes.
+//!
+//! Regardless of the units a device uses natively, positions and headings are
+//! given in degrees and velocities in degrees per second.
+
+use crate::error::Error;
+
+/// Describes a device which is driven by a voltage, such as a motor.
+pub trait VoltageActuator {
+    /// The type of errors which could occur while driving the device.
+    type Error: Into<Error>;
+
+    /// Sets the voltage applied to the device, in millivolts, from -12000 to
+    /// 12000.
+    fn set_voltage(&mut self, voltage: i32) -> Result<(), Self::Error>;
+}
+
+/// Describes a device which can drive a shaft to a position under its own
+/// closed-loop control, such as a smart motor.
+pub trait PositionActuator {
+    /// The type of errors which could occur while driving the device.
+    type Error: Into<Error>;
+
+    /// Sets the position, in degrees, towards which the device drives the
+    /// shaft.
+    fn set_position(&mut self, position: f64) -> Result<(), Self::Error>;
+}
+
+/// Describes a device which can drive a shaft at a velocity under its own
+/// closed-loop control, such as a smart motor.
+pub trait VelocityActuator {
+    /// The type of errors which could occur while driving the device.
+    type Error: Into<Error>;
+
+    /// Sets the velocity, in degrees per second, at which the device drives the
+    /// shaft.
+    fn set_velocity(&mut self, velocity: f64) -> Result<(), Self::Error>;
+}
+
+/// Describes a sensor which measures the angular position of a shaft.
+pub trait PositionSensor {
+    /// The type of errors which could occur while reading the sensor.
+    type Error: Into<Error>;
+
+    /// Gets the position of the shaft, in degrees.
+    fn position(&self) -> Result<f64, Self::Error>;
+
+    /// Sets the current position of the shaft to be zero.
+    fn reset_position(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Describes a sensor which measures the angular velocity of a shaft.
+pub trait VelocitySensor {
+    /// The type of errors which could occur while reading the sensor.
+    type Error: Into<Error>;
+
+    /// Gets the velocity of the shaft, in degrees per second.
+    fn velocity(&self) -> Result<f64, Self::Error>;
+}
+
+/// Describes a sensor which measures the heading of the robot.
+pub trait HeadingSensor {
+    /// The type of errors which could occur while reading the sensor.
+    type Error: Into<Error>;
+
+    /// Gets the heading of the robot, in degrees clockwise.
+    ///
+    /// This value is unbounded, so that it changes continuously as the robot
+    /// turns through a full revolution.
+    fn heading(&self) -> Result<f64, Self::Error>;
+
+    /// Sets the current heading of the robot, in degrees clockwise.
+    fn set_heading(&mut self, heading: f64) -> Result<(), Self::Error>;
+}
+
+#[cfg(test)]
+pub(crate) mod fake {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    /// An actuator which records the commands it is given, for testing code
+    /// written against the actuator traits. Once `fail` is set, every command
+    /// fails and is not recorded.
+    #[derive(Debug, Default)]
+    pub(crate) struct FakeActuator {
+        pub(crate) voltages: Vec<i32>,
+        pub(crate) velocities: Vec<f64>,
+        pub(crate) fail: bool,
+    }
+
+    impl FakeActuator {
+        fn check(&self) -> Result<(), Error> {
+            if self.fail {
+                Err(Error::Custom("fake actuator failed".into()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl VoltageActuator for FakeActuator {
+        type Error = Error;
+
+        fn set_voltage(&mut self, voltage: i32) -> Result<(), Self::Error> {
+            self.check()?;
+            self.voltages.push(voltage);
+            Ok(())
+        }
+    }
+
+    impl VelocityActuator for FakeActuator {
+        type Error = Error;
+
+        fn set_velocity(&mut self, velocity: f64) -> Result<(), Self::Error> {
+            self.check()?;
+            self.velocities.push(velocity);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fake::FakeActuator, *};
+
+    /// Drives an actuator through the traits, as generic code would.
+    fn command<A: VoltageActuator + VelocityActuator>(
+        actuator: &mut A,
+        voltage: i32,
+        velocity: f64,
+    ) -> Result<(), Error> {
+        actuator.set_voltage(voltage).map_err(Into::into)?;
+        actuator.set_velocity(velocity).map_err(Into::into)
+    }
+
+    #[test]
+    fn fake_actuator_records_commands() {
+        let mut actuator = FakeActuator::default();
+        command(&mut actuator, 6000, 90.0).unwrap();
+        command(&mut actuator, -12000, -45.0).unwrap();
+        assert_eq!(actuator.voltages, [6000, -12000]);
+        assert_eq!(actuator.velocities, [90.0, -45.0]);
+
+        actuator.fail = true;
+        assert!(command(&mut actuator, 0, 0.0).is_err());
+        assert_eq!(actuator.voltages, [6000, -12000]);
+    }
+}
@@ -7,6 +7,7 @@ use alloc::format;
 use crate::{
     bindings,
     error::{get_errno, Error},
+    hal::HeadingSensor,
     rtos::DataSource,
 };
 
@@ -272,6 +273,18 @@ impl InertialSensor {
     }
 }
 
+impl HeadingSensor for InertialSensor {
+    type Error = InertialSensorError;
+
+    fn heading(&self) -> Result<f64, Self::Error> {
+        self.get_rotation()
+    }
+
+    fn set_heading(&mut self, heading: f64) -> Result<(), Self::Error> {
+        self.set_rotation(heading)
+    }
+}
+
 impl DataSource for InertialSensor {
     type Data = InertialSensorData;
 
@@ -0,0 +1,258 @@
+//! # LLEMU API.
+//!
+//! The Legacy LCD Emulator (LLEMU) shows eight lines of text on the brain
+//! screen, above three buttons, in the style of the VEX Cortex LCD. It is
+//! claimed by converting the [`BrainScreen`](crate::screen::BrainScreen) with
+//! [`BrainScreen::into_lcd()`](crate::screen::BrainScreen::into_lcd()).
+
+use alloc::{collections::VecDeque, vec, vec::Vec};
+use core::time::Duration;
+
+use crate::{
+    bindings,
+    error::{get_errno, Error},
+    io::eprintln,
+    rtos::{delay_until, queue, time_since_start, GenericSleep, Selectable, SendQueue, Task},
+    select,
+};
+
+/// The number of lines of text on the LCD.
+pub const LCD_LINES: u8 = 8;
+
+const LCD_SUCCESS_DELAY: Duration = Duration::from_millis(10);
+const LCD_FAILURE_DELAY: Duration = Duration::from_millis(5);
+
+/// How often [`LcdButton::select_press()`] checks the button.
+const BUTTON_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Represents the LLEMU on the brain screen.
+pub struct Lcd {
+    queue: Option<SendQueue<LcdCommand>>,
+    /// The left button.
+    pub left: LcdButton,
+    /// The center button.
+    pub center: LcdButton,
+    /// The right button.
+    pub right: LcdButton,
+}
+
+impl Lcd {
+    /// Initializes the LLEMU.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it allows the user to create multiple
+    /// mutable references to the LLEMU, or to use it alongside the brain
+    /// screen. You likely want to use
+    /// [`BrainScreen::into_lcd()`](crate::screen::BrainScreen::into_lcd())
+    /// instead.
+    pub unsafe fn new() -> Result<Self, LcdError> {
+        if !bindings::lcd_initialize() {
+            return Err(LcdError::from_errno());
+        }
+        Ok(Lcd {
+            queue: None,
+            left: LcdButton {
+                mask: bindings::LCD_BTN_LEFT as u8,
+            },
+            center: LcdButton {
+                mask: bindings::LCD_BTN_CENTER as u8,
+            },
+            right: LcdButton {
+                mask: bindings::LCD_BTN_RIGHT as u8,
+            },
+        })
+    }
+
+    /// Clears all of the lines of the LCD.
+    pub fn clear(&mut self) {
+        self.command(LcdCommand::Clear);
+    }
+
+    /// Clears an individual line of the LCD. Lines range from 0 to 7.
+    pub fn clear_line(&mut self, line: u8) {
+        if line >= LCD_LINES {
+            return;
+        }
+        self.command(LcdCommand::Print(line, vec![0]));
+    }
+
+    /// Replaces the text on a line of the LCD. Lines range from 0 to 7.
+    pub fn print(&mut self, line: u8, str: &str) {
+        if line >= LCD_LINES {
+            return;
+        }
+        let mut chars = Vec::with_capacity(str.len() + 1);
+        chars.extend_from_slice(str.as_bytes());
+        chars.push(0);
+        self.command(LcdCommand::Print(line, chars));
+    }
+
+    fn command(&mut self, cmd: LcdCommand) {
+        self.queue().send(cmd);
+    }
+
+    fn queue(&mut self) -> &mut SendQueue<LcdCommand> {
+        self.queue.get_or_insert_with(|| {
+            let (send, recv) = queue(VecDeque::<LcdCommand>::new());
+            Task::spawn_ext(
+                "lcd",
+                bindings::TASK_PRIORITY_MAX,
+                bindings::TASK_STACK_DEPTH_DEFAULT as u16,
+                move || {
+                    let mut delay_target = None;
+                    let mut offset = 0usize;
+                    let mut buffer: [LcdRow; LCD_LINES as usize] = Default::default();
+                    'main: loop {
+                        let command: Option<LcdCommand> = select! {
+                            cmd = recv.select() => Some(cmd),
+                            _ = delay_until(t); Some(t) = delay_target => None,
+                        };
+                        if let Some(cmd) = command {
+                            match cmd {
+                                LcdCommand::Clear => {
+                                    for row in buffer.iter_mut() {
+                                        *row = LcdRow {
+                                            chars: vec![0],
+                                            dirty: true,
+                                        };
+                                    }
+                                }
+                                LcdCommand::Print(line, chars) => {
+                                    buffer[line as usize] = LcdRow { chars, dirty: true };
+                                }
+                                LcdCommand::Stop => break,
+                            }
+                        }
+                        for i in 0..buffer.len() {
+                            let index = (offset + i) % buffer.len();
+                            let row = &mut buffer[index];
+                            if !row.dirty {
+                                continue;
+                            }
+                            if unsafe {
+                                bindings::lcd_set_text(index as i16, row.chars.as_ptr() as *const _)
+                            } {
+                                delay_target = Some(time_since_start() + LCD_SUCCESS_DELAY);
+                                row.dirty = false;
+                            } else {
+                                delay_target = Some(time_since_start() + LCD_FAILURE_DELAY);
+                                eprintln!("{:?}", LcdError::from_errno());
+                            }
+                            offset = index + 1;
+                            continue 'main;
+                        }
+                        // No updates were made; delay indefinitely until next command.
+                        delay_target = None;
+                    }
+                },
+            )
+            .unwrap()
+            .mark_service();
+            send
+        })
+    }
+}
+
+impl Drop for Lcd {
+    fn drop(&mut self) {
+        if self.queue.is_some() {
+            self.command(LcdCommand::Stop);
+        }
+    }
+}
+
+/// Represents one of the three buttons on the LCD.
+pub struct LcdButton {
+    mask: u8,
+}
+
+impl LcdButton {
+    /// Checks if the button is pressed.
+    pub fn is_pressed(&self) -> Result<bool, LcdError> {
+        match unsafe { bindings::lcd_read_buttons() } {
+            x if x == bindings::PROS_ERR_ as u8 => Err(LcdError::from_errno()),
+            x => Ok(x & self.mask != 0),
+        }
+    }
+
+    /// A [`Selectable`] event which resolves when the button is next pressed.
+    /// If the button is already pressed, it must first be released.
+    pub fn select_press(&self) -> impl '_ + Selectable<Result<(), LcdError>> {
+        struct PressSelect<'b> {
+            button: &'b LcdButton,
+            was_pressed: Option<bool>,
+        }
+
+        impl<'b> Selectable<Result<(), LcdError>> for PressSelect<'b> {
+            fn poll(mut self) -> Result<Result<(), LcdError>, Self> {
+                match self.button.is_pressed() {
+                    Ok(true) if self.was_pressed == Some(false) => Ok(Ok(())),
+                    Ok(pressed) => {
+                        self.was_pressed = Some(pressed);
+                        Err(self)
+                    }
+                    Err(err) => Ok(Err(err)),
+                }
+            }
+
+            fn sleep(&self) -> GenericSleep {
+                GenericSleep::Timestamp(time_since_start() + BUTTON_POLL_INTERVAL)
+            }
+        }
+
+        PressSelect {
+            button: self,
+            was_pressed: None,
+        }
+    }
+}
+
+struct LcdRow {
+    /// Null-terminated text of the row.
+    chars: Vec<u8>,
+    dirty: bool,
+}
+
+impl Default for LcdRow {
+    fn default() -> Self {
+        Self {
+            chars: vec![0],
+            dirty: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum LcdCommand {
+    Clear,
+    Print(u8, Vec<u8>),
+    Stop,
+}
+
+/// Represents possible errors for LCD operations.
+#[derive(Debug)]
+pub enum LcdError {
+    /// The LCD has not been initialized.
+    NotInitialized,
+    /// Unknown error.
+    Unknown(i32),
+}
+
+impl LcdError {
+    fn from_errno() -> Self {
+        match get_errno() {
+            libc::ENXIO => Self::NotInitialized,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl From<LcdError> for Error {
+    fn from(err: LcdError) -> Self {
+        match err {
+            LcdError::NotInitialized => Error::Custom("lcd not initialized".into()),
+            LcdError::Unknown(n) => Error::System(n),
+        }
+    }
+}
@@ -1,38 +1,62 @@
 //! A crate for running rust on the VEX V5.
 
-#![no_std]
-#![feature(alloc_error_handler)]
+#![cfg_attr(not(feature = "sim"), no_std)]
+#![cfg_attr(not(feature = "sim"), feature(alloc_error_handler))]
 #![feature(negative_impls)]
 #![warn(missing_docs)]
 
 extern crate alloc;
 
+#[cfg(not(feature = "sim"))]
 use core::panic::PanicInfo;
 
+#[cfg(not(feature = "sim"))]
 mod allocator;
+#[cfg(not(feature = "sim"))]
 mod bindings;
 mod error;
+mod math;
+
+#[cfg(feature = "sim")]
+use sim::bindings;
 
 pub mod adi;
+pub mod autonomous;
 pub mod battery;
+pub mod competition;
+pub mod control;
 pub mod controller;
 pub mod distance;
+pub mod drive;
+pub mod gps;
+pub mod hal;
 pub mod imu;
 pub mod io;
+pub mod lcd;
+pub mod link;
 pub mod machine;
 pub mod macros;
 pub mod motor;
+pub mod odometry;
+pub mod optical;
 pub mod peripherals;
 pub mod prelude;
+pub mod pursuit;
 pub mod robot;
 pub mod rotation;
 pub mod rtos;
+pub mod screen;
+pub mod sd;
 pub mod serial;
+#[cfg(feature = "sim")]
+pub mod sim;
 pub mod smart_port;
+pub mod vision;
 
 #[doc(hidden)]
 pub use spin::once;
 
+#[cfg(not(feature = "sim"))]
 #[panic_handler]
 fn panic(panic_info: &PanicInfo) -> ! {
     crate::io::eprintln!("panic occurred!: {:#?}", panic_info);
@@ -0,0 +1,261 @@
+//! # VEXlink API.
+//!
+//! A V5 radio which is not connected to a controller can be used to exchange
+//! data with a radio on another robot. One robot initializes its link as a
+//! [`LinkRole::Transmitter`] and the other as a [`LinkRole::Receiver`], using
+//! the same link ID; data can then be sent in both directions.
+
+use alloc::{vec, vec::Vec};
+use core::{convert::TryInto, time::Duration};
+
+use cstring_interop::with_cstring;
+
+use crate::{
+    bindings,
+    error::{get_errno, Error},
+    rtos::{time_since_start, GenericSleep, Selectable},
+};
+
+/// The number of bytes added to each message by [`Link::send()`]: a start
+/// byte, two bytes of size and a checksum.
+pub const LINK_MESSAGE_OVERHEAD: usize = 4;
+
+/// How often [`Selectable`] events for a link check for new data.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A struct which represents a V5 smart port configured as a VEXlink radio.
+pub struct Link {
+    port: u8,
+}
+
+impl Link {
+    /// Initializes a link on a V5 radio.
+    ///
+    /// The link connects to a radio on another robot whose link has the same
+    /// ID and the opposite role. Connecting takes some time, during which
+    /// most operations fail with [`LinkError::NotConnected`].
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it allows the user to create multiple
+    /// mutable references to the same radio. You likely want to implement
+    /// [`Robot::new()`](crate::robot::Robot::new()) instead.
+    pub unsafe fn new(port: u8, id: &str, role: LinkRole) -> Result<Link, LinkError> {
+        let result: Result<u32, Error> = with_cstring(id.into(), |id| {
+            Ok(bindings::link_init(port, id.into_raw(), role.into()))
+        });
+        match result.map_err(|_| LinkError::InvalidId)? {
+            x if x == bindings::PROS_ERR_ as u32 => Err(LinkError::from_errno()),
+            _ => Ok(Link { port }),
+        }
+    }
+
+    /// Checks whether the link is connected to the other robot.
+    pub fn is_connected(&self) -> bool {
+        unsafe { bindings::link_connected(self.port) }
+    }
+
+    /// Gets the number of bytes waiting to be received.
+    pub fn receivable_size(&self) -> Result<usize, LinkError> {
+        match unsafe { bindings::link_raw_receivable_size(self.port) } {
+            x if x == bindings::PROS_ERR_ as u32 => Err(LinkError::from_errno()),
+            x => Ok(x as usize),
+        }
+    }
+
+    /// Gets the number of bytes which can currently be sent.
+    pub fn transmittable_size(&self) -> Result<usize, LinkError> {
+        match unsafe { bindings::link_raw_transmittable_size(self.port) } {
+            x if x == bindings::PROS_ERR_ as u32 => Err(LinkError::from_errno()),
+            x => Ok(x as usize),
+        }
+    }
+
+    /// Sends bytes over the link without any framing, returning the number
+    /// sent.
+    pub fn send_raw(&mut self, data: &[u8]) -> Result<usize, LinkError> {
+        let size = data
+            .len()
+            .try_into()
+            .map_err(|_| LinkError::MessageTooLarge)?;
+        match unsafe { bindings::link_transmit_raw(self.port, data.as_ptr() as *mut _, size) } {
+            x if x == bindings::PROS_ERR_ as u32 => Err(LinkError::from_errno()),
+            x => Ok(x as usize),
+        }
+    }
+
+    /// Receives as many bytes as are available and fit in the given buffer,
+    /// returning the number received.
+    pub fn receive_raw(&mut self, buffer: &mut [u8]) -> Result<usize, LinkError> {
+        let size = buffer.len().min(u16::MAX as usize) as u16;
+        match unsafe { bindings::link_receive_raw(self.port, buffer.as_mut_ptr() as *mut _, size) }
+        {
+            x if x == bindings::PROS_ERR_ as u32 => Err(LinkError::from_errno()),
+            x => Ok(x as usize),
+        }
+    }
+
+    /// Sends a message over the link, framed with its size and a checksum so
+    /// that [`Link::receive()`] on the other robot can validate it.
+    pub fn send(&mut self, message: &[u8]) -> Result<(), LinkError> {
+        let size = message
+            .len()
+            .try_into()
+            .map_err(|_| LinkError::MessageTooLarge)?;
+        match unsafe { bindings::link_transmit(self.port, message.as_ptr() as *mut _, size) } {
+            x if x == bindings::PROS_ERR_ as u32 => Err(LinkError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Receives a message sent with [`Link::send()`], which must be exactly
+    /// the size of the given buffer. Returns `false` if the whole message has
+    /// not arrived yet.
+    pub fn receive(&mut self, buffer: &mut [u8]) -> Result<bool, LinkError> {
+        let size = buffer
+            .len()
+            .try_into()
+            .map_err(|_| LinkError::MessageTooLarge)?;
+        match unsafe { bindings::link_receive(self.port, buffer.as_mut_ptr() as *mut _, size) } {
+            0 => Ok(false),
+            x if x == bindings::PROS_ERR_ as u32 => Err(LinkError::from_errno()),
+            _ => Ok(true),
+        }
+    }
+
+    /// Discards all bytes waiting to be received.
+    pub fn clear_receive_buffer(&mut self) -> Result<(), LinkError> {
+        match unsafe { bindings::link_clear_receive_buf(self.port) } {
+            x if x == bindings::PROS_ERR_ as u32 => Err(LinkError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// A [`Selectable`] event which resolves with all waiting bytes once any
+    /// have been received.
+    pub fn select_raw(&mut self) -> impl '_ + Selectable<Result<Vec<u8>, LinkError>> {
+        struct RawSelect<'b>(&'b mut Link);
+
+        impl<'b> Selectable<Result<Vec<u8>, LinkError>> for RawSelect<'b> {
+            fn poll(self) -> Result<Result<Vec<u8>, LinkError>, Self> {
+                match self.0.receivable_size() {
+                    Ok(0) | Err(LinkError::NotConnected) => Err(self),
+                    Ok(n) => {
+                        let mut buffer = vec![0; n];
+                        Ok(self.0.receive_raw(&mut buffer).map(|n| {
+                            buffer.truncate(n);
+                            buffer
+                        }))
+                    }
+                    Err(err) => Ok(Err(err)),
+                }
+            }
+
+            fn sleep(&self) -> GenericSleep {
+                GenericSleep::Timestamp(time_since_start() + POLL_INTERVAL)
+            }
+        }
+
+        RawSelect(self)
+    }
+
+    /// A [`Selectable`] event which resolves once a message of the given size
+    /// sent with [`Link::send()`] has been received.
+    pub fn select_message(
+        &mut self,
+        size: usize,
+    ) -> impl '_ + Selectable<Result<Vec<u8>, LinkError>> {
+        struct MessageSelect<'b> {
+            link: &'b mut Link,
+            size: usize,
+        }
+
+        impl<'b> Selectable<Result<Vec<u8>, LinkError>> for MessageSelect<'b> {
+            fn poll(self) -> Result<Result<Vec<u8>, LinkError>, Self> {
+                match self.link.receivable_size() {
+                    Ok(n) if n < self.size + LINK_MESSAGE_OVERHEAD => Err(self),
+                    Err(LinkError::NotConnected) => Err(self),
+                    Ok(_) => {
+                        let mut buffer = vec![0; self.size];
+                        match self.link.receive(&mut buffer) {
+                            Ok(false) => Err(self),
+                            Ok(true) => Ok(Ok(buffer)),
+                            Err(err) => Ok(Err(err)),
+                        }
+                    }
+                    Err(err) => Ok(Err(err)),
+                }
+            }
+
+            fn sleep(&self) -> GenericSleep {
+                GenericSleep::Timestamp(time_since_start() + POLL_INTERVAL)
+            }
+        }
+
+        MessageSelect { link: self, size }
+    }
+}
+
+/// Represents the role of a robot in a link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkRole {
+    /// The receiving robot.
+    Receiver,
+    /// The transmitting robot.
+    Transmitter,
+}
+
+impl From<LinkRole> for bindings::link_type_e_t {
+    fn from(role: LinkRole) -> Self {
+        match role {
+            LinkRole::Receiver => bindings::link_type_e_E_LINK_RX,
+            LinkRole::Transmitter => bindings::link_type_e_E_LINK_TX,
+        }
+    }
+}
+
+/// Represents possible errors for link operations.
+#[derive(Debug)]
+pub enum LinkError {
+    /// Port is out of range (1-21), or the link is not connected.
+    NotConnected,
+    /// Port cannot be configured as a radio.
+    PortNotRadio,
+    /// The link ID contains a null byte.
+    InvalidId,
+    /// There is no room to send the data.
+    Busy,
+    /// The message is too large to be sent or received.
+    MessageTooLarge,
+    /// A received message was corrupted or had an unexpected size.
+    BadMessage,
+    /// Unknown error.
+    Unknown(i32),
+}
+
+impl LinkError {
+    fn from_errno() -> Self {
+        match get_errno() {
+            libc::ENXIO => Self::NotConnected,
+            libc::ENODEV => Self::PortNotRadio,
+            libc::EBUSY => Self::Busy,
+            libc::EINVAL => Self::MessageTooLarge,
+            libc::EBADMSG => Self::BadMessage,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl From<LinkError> for Error {
+    fn from(err: LinkError) -> Self {
+        match err {
+            LinkError::NotConnected => Error::Custom("link not connected".into()),
+            LinkError::PortNotRadio => Error::Custom("port not a radio".into()),
+            LinkError::InvalidId => Error::Custom("link id contains a null byte".into()),
+            LinkError::Busy => Error::Custom("link busy".into()),
+            LinkError::MessageTooLarge => Error::Custom("message too large".into()),
+            LinkError::BadMessage => Error::Custom("bad message received over link".into()),
+            LinkError::Unknown(n) => Error::System(n),
+        }
+    }
+}
@@ -47,5 +47,28 @@ macro_rules! entry {
         extern "C" fn disabled() {
             ROBOT.wait().disabled();
         }
+
+        $crate::sim_main!();
+    };
+}
+
+#[cfg(feature = "sim")]
+#[doc(hidden)]
+#[macro_export]
+/// Generates the host entrypoint which runs the robot in the simulator.
+macro_rules! sim_main {
+    () => {
+        #[cfg(not(test))]
+        #[no_mangle]
+        extern "C" fn main(_argc: i32, _argv: *const *const u8) -> i32 {
+            $crate::sim::run(initialize, opcontrol)
+        }
     };
 }
+
+#[cfg(not(feature = "sim"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! sim_main {
+    () => {};
+}
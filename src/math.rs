@@ -0,0 +1,71 @@
+//! Floating-point functions which `core` does not provide, taken from the C
+//! math library.
+
+mod ffi {
+    extern "C" {
+        pub fn cbrt(x: f64) -> f64;
+        pub fn cos(x: f64) -> f64;
+        pub fn exp(x: f64) -> f64;
+        pub fn round(x: f64) -> f64;
+        pub fn sin(x: f64) -> f64;
+        pub fn sqrt(x: f64) -> f64;
+    }
+}
+
+#[inline]
+/// Computes the absolute value of `x`.
+pub(crate) fn abs(x: f64) -> f64 {
+    if x < 0.0 {
+        -x
+    } else {
+        x
+    }
+}
+
+#[inline]
+/// Computes the cube root of `x`.
+pub(crate) fn cbrt(x: f64) -> f64 {
+    unsafe { ffi::cbrt(x) }
+}
+
+#[inline]
+/// Computes the cosine of `x`, in radians.
+pub(crate) fn cos(x: f64) -> f64 {
+    unsafe { ffi::cos(x) }
+}
+
+#[inline]
+/// Computes `e` raised to the power `x`.
+pub(crate) fn exp(x: f64) -> f64 {
+    unsafe { ffi::exp(x) }
+}
+
+#[inline]
+/// Rounds `x` to the nearest integer, rounding half-way cases away from 0.
+pub(crate) fn round(x: f64) -> f64 {
+    unsafe { ffi::round(x) }
+}
+
+#[inline]
+/// Computes the sine of `x`, in radians.
+pub(crate) fn sin(x: f64) -> f64 {
+    unsafe { ffi::sin(x) }
+}
+
+#[inline]
+/// Computes the square root of `x`.
+pub(crate) fn sqrt(x: f64) -> f64 {
+    unsafe { ffi::sqrt(x) }
+}
+
+#[inline]
+/// Gets the sign of `x`, or 0 if `x` is 0.
+pub(crate) fn signum(x: f64) -> f64 {
+    if x > 0.0 {
+        1.0
+    } else if x < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
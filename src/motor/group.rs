@@ -0,0 +1,307 @@
+use alloc::{format, vec::Vec};
+use core::ops::Add;
+
+use super::{BrakeMode, EncoderUnits, Gearset, Motor, MotorError};
+use crate::{
+    error::Error,
+    hal::{PositionActuator, PositionSensor, VelocityActuator, VelocitySensor, VoltageActuator},
+    rtos::DataSource,
+};
+
+/// A set of motors which are driven together, such as the motors on one side
+/// of a drivetrain.
+///
+/// Commands are forwarded to every member in turn. If a member fails, the
+/// command is still sent to the rest of the group, and the first failure is
+/// reported. Readings are aggregated over the members.
+pub struct MotorGroup {
+    motors: Vec<Motor>,
+}
+
+impl MotorGroup {
+    /// Creates a group from a set of motors. Each motor keeps its own gearset,
+    /// encoder units and reversal. Panics if `motors` is empty.
+    pub fn new(motors: Vec<Motor>) -> Self {
+        assert!(!motors.is_empty(), "a motor group needs at least one motor");
+        Self { motors }
+    }
+
+    /// Gets the members of the group.
+    pub fn motors(&self) -> &[Motor] {
+        &self.motors
+    }
+
+    /// Gets mutable access to the members of the group.
+    pub fn motors_mut(&mut self) -> &mut [Motor] {
+        &mut self.motors
+    }
+
+    /// Breaks the group up into its members.
+    pub fn into_motors(self) -> Vec<Motor> {
+        self.motors
+    }
+
+    /// Sets the voltage for every motor from -127 to 127; see
+    /// [`Motor::move_i8()`].
+    pub fn move_i8(&mut self, voltage: i8) -> Result<(), MotorGroupError> {
+        self.command(|m| m.move_i8(voltage))
+    }
+
+    /// Sets the target absolute position for every motor to move to; see
+    /// [`Motor::move_absolute()`].
+    pub fn move_absolute(&mut self, position: f64, velocity: i32) -> Result<(), MotorGroupError> {
+        self.command(|m| m.move_absolute(position, velocity))
+    }
+
+    /// Sets the relative target position for every motor to move to; see
+    /// [`Motor::move_relative()`].
+    pub fn move_relative(&mut self, position: f64, velocity: i32) -> Result<(), MotorGroupError> {
+        self.command(|m| m.move_relative(position, velocity))
+    }
+
+    /// Sets the velocity for every motor; see [`Motor::move_velocity()`].
+    pub fn move_velocity(&mut self, velocity: i32) -> Result<(), MotorGroupError> {
+        self.command(|m| m.move_velocity(velocity))
+    }
+
+    /// Sets the output voltage for every motor from -12000 to 12000 in
+    /// millivolts.
+    pub fn move_voltage(&mut self, voltage: i32) -> Result<(), MotorGroupError> {
+        self.command(|m| m.move_voltage(voltage))
+    }
+
+    /// Changes the output velocity for a profiled movement; see
+    /// [`Motor::modify_profiled_velocity()`].
+    pub fn modify_profiled_velocity(&mut self, velocity: i32) -> Result<(), MotorGroupError> {
+        self.command(|m| m.modify_profiled_velocity(velocity))
+    }
+
+    /// Sets one of [`BrakeMode`] for every motor.
+    pub fn set_brake_mode(&mut self, mode: BrakeMode) -> Result<(), MotorGroupError> {
+        self.command(|m| m.set_brake_mode(mode))
+    }
+
+    /// Sets the current limit for every motor in milliamperes.
+    pub fn set_current_limit(&mut self, limit: i32) -> Result<(), MotorGroupError> {
+        self.command(|m| m.set_current_limit(limit))
+    }
+
+    /// Sets one of [`Gearset`] for every motor.
+    pub fn set_gearing(&mut self, gearset: Gearset) -> Result<(), MotorGroupError> {
+        self.command(|m| m.set_gearing(gearset))
+    }
+
+    /// Sets the voltage limit for every motor in millivolts.
+    pub fn set_voltage_limit(&mut self, limit: i32) -> Result<(), MotorGroupError> {
+        self.command(|m| m.set_voltage_limit(limit))
+    }
+
+    /// Sets the [`EncoderUnits`] for every motor.
+    pub fn set_encoder_units(&mut self, units: EncoderUnits) -> Result<(), MotorGroupError> {
+        self.command(|m| m.set_encoder_units(units))
+    }
+
+    /// Sets the "absolute" zero position of every motor to its encoder value.
+    pub fn set_zero_position(&mut self, position: f64) -> Result<(), MotorGroupError> {
+        self.command(|m| m.set_zero_position(position))
+    }
+
+    /// Sets the "absolute" zero position of every motor to its current
+    /// position.
+    pub fn tare_position(&mut self) -> Result<(), MotorGroupError> {
+        self.command(Motor::tare_position)
+    }
+
+    /// Gets the mean absolute position of the motors in their encoder units.
+    pub fn get_position(&self) -> Result<f64, MotorGroupError> {
+        Ok(self.sum(Motor::get_position)? / self.len())
+    }
+
+    /// Gets the mean actual velocity of the motors.
+    pub fn get_actual_velocity(&self) -> Result<f64, MotorGroupError> {
+        Ok(self.sum(Motor::get_actual_velocity)? / self.len())
+    }
+
+    /// Gets the total current drawn by the motors in milliamperes.
+    pub fn get_current_draw(&self) -> Result<i32, MotorGroupError> {
+        self.sum(Motor::get_current_draw)
+    }
+
+    /// Gets the total power drawn by the motors in Watts.
+    pub fn get_power(&self) -> Result<f64, MotorGroupError> {
+        self.sum(Motor::get_power)
+    }
+
+    /// Gets the total torque of the motors in Newton-Meters.
+    pub fn get_torque(&self) -> Result<f64, MotorGroupError> {
+        self.sum(Motor::get_torque)
+    }
+
+    /// Gets the mean voltage delivered to the motors in millivolts.
+    pub fn get_voltage(&self) -> Result<i32, MotorGroupError> {
+        Ok(self.sum(Motor::get_voltage)? / self.motors.len() as i32)
+    }
+
+    /// Gets the temperature of the hottest motor in degrees Celsius.
+    pub fn get_temperature(&self) -> Result<f64, MotorGroupError> {
+        self.fold(f64::MIN, |max, m| Ok(max.max(m.get_temperature()?)))
+    }
+
+    /// Checks if any motor is drawing over its current limit.
+    pub fn is_over_current(&self) -> Result<bool, MotorGroupError> {
+        self.fold(false, |any, m| Ok(any || m.is_over_current()?))
+    }
+
+    /// Checks if any motor's temperature is above its limit.
+    pub fn is_over_temp(&self) -> Result<bool, MotorGroupError> {
+        self.fold(false, |any, m| Ok(any || m.is_over_temp()?))
+    }
+
+    fn len(&self) -> f64 {
+        self.motors.len() as f64
+    }
+
+    fn command(
+        &mut self,
+        mut f: impl FnMut(&mut Motor) -> Result<(), MotorError>,
+    ) -> Result<(), MotorGroupError> {
+        let mut result = Ok(());
+        for motor in self.motors.iter_mut() {
+            if let Err(error) = f(motor) {
+                if result.is_ok() {
+                    result = Err(MotorGroupError {
+                        port: motor.port(),
+                        error,
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    fn fold<T>(
+        &self,
+        init: T,
+        mut f: impl FnMut(T, &Motor) -> Result<T, MotorError>,
+    ) -> Result<T, MotorGroupError> {
+        self.motors.iter().try_fold(init, |acc, motor| {
+            f(acc, motor).map_err(|error| MotorGroupError {
+                port: motor.port(),
+                error,
+            })
+        })
+    }
+
+    fn sum<T: Add<Output = T> + Default>(
+        &self,
+        f: impl Fn(&Motor) -> Result<T, MotorError>,
+    ) -> Result<T, MotorGroupError> {
+        self.fold(T::default(), |sum, m| Ok(sum + f(m)?))
+    }
+}
+
+impl DataSource for MotorGroup {
+    type Data = MotorGroupData;
+
+    type Error = MotorGroupError;
+
+    fn read(&self) -> Result<Self::Data, Self::Error> {
+        Ok(MotorGroupData {
+            position: self.get_position()?,
+            actual_velocity: self.get_actual_velocity()?,
+            current_draw: self.get_current_draw()?,
+            power: self.get_power()?,
+            torque: self.get_torque()?,
+            voltage: self.get_voltage()?,
+            temperature: self.get_temperature()?,
+            over_current: self.is_over_current()?,
+            over_temp: self.is_over_temp()?,
+        })
+    }
+}
+
+impl VoltageActuator for MotorGroup {
+    type Error = MotorGroupError;
+
+    fn set_voltage(&mut self, voltage: i32) -> Result<(), Self::Error> {
+        self.move_voltage(voltage)
+    }
+}
+
+impl PositionActuator for MotorGroup {
+    type Error = MotorGroupError;
+
+    fn set_position(&mut self, position: f64) -> Result<(), Self::Error> {
+        self.command(|m| m.set_position(position))
+    }
+}
+
+impl VelocityActuator for MotorGroup {
+    type Error = MotorGroupError;
+
+    fn set_velocity(&mut self, velocity: f64) -> Result<(), Self::Error> {
+        self.command(|m| m.set_velocity(velocity))
+    }
+}
+
+impl PositionSensor for MotorGroup {
+    type Error = MotorGroupError;
+
+    fn position(&self) -> Result<f64, Self::Error> {
+        Ok(self.sum(<Motor as PositionSensor>::position)? / self.len())
+    }
+
+    fn reset_position(&mut self) -> Result<(), Self::Error> {
+        self.tare_position()
+    }
+}
+
+impl VelocitySensor for MotorGroup {
+    type Error = MotorGroupError;
+
+    fn velocity(&self) -> Result<f64, Self::Error> {
+        Ok(self.sum(<Motor as VelocitySensor>::velocity)? / self.len())
+    }
+}
+
+/// Represents the data that can be read from a motor group.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MotorGroupData {
+    /// The mean absolute position of the motors in their encoder units.
+    pub position: f64,
+    /// The mean actual velocity of the motors.
+    pub actual_velocity: f64,
+    /// The total current drawn by the motors in milliamperes.
+    pub current_draw: i32,
+    /// The total power drawn by the motors in watts.
+    pub power: f64,
+    /// The total torque of the motors in newton-metres.
+    pub torque: f64,
+    /// The mean voltage delivered to the motors in millivolts.
+    pub voltage: i32,
+    /// The temperature of the hottest motor in degrees Celsius.
+    pub temperature: f64,
+    /// Whether any motor is drawing over its current limit.
+    pub over_current: bool,
+    /// Whether any motor's temperature is above its limit.
+    pub over_temp: bool,
+}
+
+/// Represents an error from a member of a motor group.
+#[derive(Debug)]
+pub struct MotorGroupError {
+    /// The smart port of the motor which failed.
+    pub port: u8,
+    /// The error from the motor.
+    pub error: MotorError,
+}
+
+impl From<MotorGroupError> for Error {
+    fn from(err: MotorGroupError) -> Self {
+        Error::Custom(format!(
+            "motor on port {}: {}",
+            err.port,
+            Error::from(err.error)
+        ))
+    }
+}
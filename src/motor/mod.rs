@@ -3,9 +3,15 @@
 use crate::{
     bindings,
     error::{get_errno, Error},
+    hal::{PositionActuator, PositionSensor, VelocityActuator, VelocitySensor, VoltageActuator},
+    math::round,
     rtos::DataSource,
 };
 
+mod group;
+
+pub use group::*;
+
 /// A struct which represents a V5 smart port configured as a motor.
 pub struct Motor {
     port: u8,
@@ -34,6 +40,11 @@ impl Motor {
         }
     }
 
+    /// Gets the smart port the motor is plugged into.
+    pub fn port(&self) -> u8 {
+        self.port
+    }
+
     /// Sets the voltage for the motor from -127 to 127.
     ///
     /// This is designed to map easily to the input from the controller's analog
@@ -249,9 +260,9 @@ impl Motor {
     /// Gets the gearset that was set for the motor.
     pub fn get_gearing(&self) -> Result<Gearset, MotorError> {
         match unsafe { bindings::motor_get_gearing(self.port) } {
-            bindings::motor_gearset_e_E_MOTOR_GEARSET_36 => Ok(Gearset::SixToOne),
+            bindings::motor_gearset_e_E_MOTOR_GEARSET_36 => Ok(Gearset::ThirtySixToOne),
             bindings::motor_gearset_e_E_MOTOR_GEARSET_18 => Ok(Gearset::EighteenToOne),
-            bindings::motor_gearset_e_E_MOTOR_GEARSET_06 => Ok(Gearset::ThirtySixToOne),
+            bindings::motor_gearset_e_E_MOTOR_GEARSET_06 => Ok(Gearset::SixToOne),
             bindings::motor_gearset_e_E_MOTOR_GEARSET_INVALID => Err(MotorError::from_errno()),
             x => panic!(
                 "bindings::motor_get_gearing returned unexpected value: {}.",
@@ -364,6 +375,15 @@ impl Motor {
             x => panic!("bindings:get_encoder_units returned unexpected value {}", x),
         }
     }
+
+    /// The number of degrees in one of the motor's encoder units.
+    fn degrees_per_unit(&self) -> Result<f64, MotorError> {
+        Ok(match self.get_encoder_units()? {
+            EncoderUnits::EncoderTicks => 360.0 / self.get_gearing()?.ticks_per_rev(),
+            EncoderUnits::Degrees => 1.0,
+            EncoderUnits::Rotations => 360.0,
+        })
+    }
 }
 
 impl DataSource for Motor {
@@ -393,6 +413,53 @@ impl DataSource for Motor {
     }
 }
 
+impl VoltageActuator for Motor {
+    type Error = MotorError;
+
+    fn set_voltage(&mut self, voltage: i32) -> Result<(), Self::Error> {
+        self.move_voltage(voltage)
+    }
+}
+
+impl PositionActuator for Motor {
+    type Error = MotorError;
+
+    fn set_position(&mut self, position: f64) -> Result<(), Self::Error> {
+        let gearing = self.get_gearing()?;
+        self.move_absolute(position / self.degrees_per_unit()?, gearing.max_rpm())
+    }
+}
+
+impl VelocityActuator for Motor {
+    type Error = MotorError;
+
+    fn set_velocity(&mut self, velocity: f64) -> Result<(), Self::Error> {
+        // Degrees per second to RPM.
+        self.move_velocity(round(velocity / 6.0) as i32)
+    }
+}
+
+impl PositionSensor for Motor {
+    type Error = MotorError;
+
+    fn position(&self) -> Result<f64, Self::Error> {
+        Ok(self.get_position()? * self.degrees_per_unit()?)
+    }
+
+    fn reset_position(&mut self) -> Result<(), Self::Error> {
+        self.tare_position()
+    }
+}
+
+impl VelocitySensor for Motor {
+    type Error = MotorError;
+
+    fn velocity(&self) -> Result<f64, Self::Error> {
+        // RPM to degrees per second.
+        Ok(self.get_actual_velocity()? * 6.0)
+    }
+}
+
 /// Represents the data that can be read from a motor.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct MotorData {
@@ -493,6 +560,24 @@ pub enum Gearset {
     ThirtySixToOne,
 }
 
+impl Gearset {
+    fn ticks_per_rev(self) -> f64 {
+        match self {
+            Gearset::SixToOne => 300.0,
+            Gearset::EighteenToOne => 900.0,
+            Gearset::ThirtySixToOne => 1800.0,
+        }
+    }
+
+    fn max_rpm(self) -> i32 {
+        match self {
+            Gearset::SixToOne => 600,
+            Gearset::EighteenToOne => 200,
+            Gearset::ThirtySixToOne => 100,
+        }
+    }
+}
+
 impl From<Gearset> for bindings::motor_gearset_e {
     fn from(gearset: Gearset) -> Self {
         match gearset {
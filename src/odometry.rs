@@ -0,0 +1,540 @@
+//! Tracking-wheel odometry, which estimates the position of the robot on the
+//! field.
+//!
+//! Positions are measured in whichever units the tracking wheel diameters are
+//! given in, typically inches. Headings are measured in degrees clockwise, as
+//! for [`HeadingSensor`], with a heading of 0 facing along the positive y axis
+//! and a heading of 90 facing along the positive x axis.
+
+use alloc::{boxed::Box, sync::Arc};
+use core::{f64::consts::PI, time::Duration};
+
+use crate::{
+    bindings,
+    error::Error,
+    hal::{HeadingSensor, PositionSensor},
+    math::{abs, cos, sin},
+    rtos::{Broadcast, BroadcastListener, Context, Loop, Mutex, Task},
+    select,
+};
+
+/// The position and heading of the robot on the field.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Pose {
+    /// The x coordinate of the robot.
+    pub x: f64,
+    /// The y coordinate of the robot.
+    pub y: f64,
+    /// The heading of the robot, in degrees clockwise.
+    pub heading: f64,
+}
+
+impl Pose {
+    #[inline]
+    /// Creates a pose with the given coordinates and heading.
+    pub fn new(x: f64, y: f64, heading: f64) -> Self {
+        Self { x, y, heading }
+    }
+
+    /// Moves the pose along an arc, given the distances travelled forwards and
+    /// to the right relative to the robot and the change in heading, in
+    /// degrees.
+    fn advance(&mut self, forward: f64, right: f64, turn: f64) {
+        let turn_rad = turn * PI / 180.0;
+        // Over an arc, the robot ends up along the chord rather than the
+        // tangent, which is shorter and points half way through the turn.
+        let scale = if abs(turn_rad) < 1e-9 {
+            1.0
+        } else {
+            2.0 * sin(turn_rad / 2.0) / turn_rad
+        };
+        let (forward, right) = (forward * scale, right * scale);
+        let heading = (self.heading + turn / 2.0) * PI / 180.0;
+        self.x += forward * sin(heading) + right * cos(heading);
+        self.y += forward * cos(heading) - right * sin(heading);
+        self.heading += turn;
+    }
+}
+
+/// Describes a source of the current pose of the robot, such as an
+/// [`OdometryTracker`].
+pub trait PoseSource {
+    /// Gets the current pose of the robot.
+    fn pose(&self) -> Pose;
+}
+
+impl PoseSource for Broadcast<Pose> {
+    fn pose(&self) -> Pose {
+        self.value()
+    }
+}
+
+type Reader = Box<dyn Fn() -> Result<f64, Error> + Send>;
+
+/// An unpowered wheel which rolls along the field as the robot moves, with a
+/// sensor measuring its rotation.
+pub struct TrackingWheel {
+    sensor: Reader,
+    /// The distance travelled per degree of rotation of the sensor.
+    scale: f64,
+    offset: f64,
+}
+
+impl TrackingWheel {
+    /// Creates a tracking wheel of the given diameter, measured by a sensor
+    /// such as an [`AdiEncoder`](crate::adi::AdiEncoder),
+    /// [`RotationSensor`](crate::rotation::RotationSensor) or
+    /// [`Motor`](crate::motor::Motor).
+    ///
+    /// The offset is the distance of the wheel from the tracking center of the
+    /// robot, perpendicular to the direction in which it rolls. For a wheel
+    /// which rolls forwards, it is positive to the right of the center; for a
+    /// wheel which rolls sideways, it is positive in front of the center.
+    pub fn new<S: PositionSensor + Send + 'static>(sensor: S, diameter: f64, offset: f64) -> Self {
+        Self {
+            sensor: Box::new(move || sensor.position().map_err(Into::into)),
+            scale: PI * diameter / 360.0,
+            offset,
+        }
+    }
+
+    /// Sets the number of rotations the wheel makes for each rotation of the
+    /// sensor, for a wheel which is geared to its sensor.
+    pub fn gear_ratio(mut self, ratio: f64) -> Self {
+        self.scale *= ratio;
+        self
+    }
+
+    /// Gets the distance the wheel has travelled since its sensor was last
+    /// reset.
+    pub fn distance(&self) -> Result<f64, Error> {
+        Ok((self.sensor)()? * self.scale)
+    }
+}
+
+/// Configures odometry from two or three tracking wheels and an optional
+/// heading sensor.
+///
+/// At least one wheel must roll forwards. The heading is taken from the heading
+/// sensor if there is one, or otherwise from the difference between two
+/// forward wheels. A wheel which rolls sideways tracks sideways motion, such as
+/// when the robot is pushed; without one, the robot is assumed not to move
+/// sideways.
+///
+/// ```
+/// # use vex_rt::{odometry::*, prelude::*};
+/// # fn f(left: AdiEncoder, right: AdiEncoder, back: RotationSensor) {
+/// let odometry = Odometry::new(TrackingWheel::new(left, 2.75, -5.0))
+///     .parallel(TrackingWheel::new(right, 2.75, 5.0))
+///     .perpendicular(TrackingWheel::new(back, 2.75, -4.5))
+///     .spawn();
+/// let mut poses = odometry.listen();
+/// loop {
+///     println!("{:?}", select! { pose = poses.select() => pose });
+/// }
+/// # }
+/// ```
+pub struct Odometry {
+    first: TrackingWheel,
+    second: Option<TrackingWheel>,
+    perpendicular: Option<TrackingWheel>,
+    heading: Option<Reader>,
+    interval: Duration,
+    initial_pose: Pose,
+}
+
+impl Odometry {
+    /// Starts configuring odometry with a wheel which rolls forwards.
+    ///
+    /// Initially, the pose is updated every 10 milliseconds from the origin.
+    pub fn new(parallel: TrackingWheel) -> Self {
+        Self {
+            first: parallel,
+            second: None,
+            perpendicular: None,
+            heading: None,
+            interval: Duration::from_millis(10),
+            initial_pose: Pose::default(),
+        }
+    }
+
+    /// Adds a second wheel which rolls forwards. Panics if there is already a
+    /// second forward wheel, or if the two wheels have the same offset.
+    pub fn parallel(mut self, wheel: TrackingWheel) -> Self {
+        assert!(self.second.is_none(), "too many parallel tracking wheels");
+        assert!(
+            wheel.offset != self.first.offset,
+            "parallel tracking wheels must have different offsets"
+        );
+        self.second = Some(wheel);
+        self
+    }
+
+    /// Adds a wheel which rolls sideways, positive to the right.
+    pub fn perpendicular(mut self, wheel: TrackingWheel) -> Self {
+        self.perpendicular = Some(wheel);
+        self
+    }
+
+    /// Adds a heading sensor, such as an
+    /// [`InertialSensor`](crate::imu::InertialSensor).
+    pub fn heading_sensor<S: HeadingSensor + Send + 'static>(mut self, sensor: S) -> Self {
+        self.heading = Some(Box::new(move || sensor.heading().map_err(Into::into)));
+        self
+    }
+
+    /// Sets the interval between updates.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the pose of the robot when odometry starts.
+    pub fn initial_pose(mut self, pose: Pose) -> Self {
+        self.initial_pose = pose;
+        self
+    }
+
+    /// Starts a background task which updates the pose of the robot. Panics
+    /// if there is neither a second forward wheel nor a heading sensor.
+    ///
+    /// The task stops when the returned tracker is dropped.
+    pub fn spawn(self) -> OdometryTracker {
+        assert!(
+            self.second.is_some() || self.heading.is_some(),
+            "odometry needs two parallel tracking wheels or a heading sensor"
+        );
+
+        let pose = Arc::new(Mutex::new(self.initial_pose));
+        let broadcast = Arc::new(Broadcast::new(self.initial_pose));
+        let ctx = Context::new_global();
+
+        let tracker = OdometryTracker {
+            pose: pose.clone(),
+            broadcast: broadcast.clone(),
+            ctx: ctx.clone(),
+        };
+
+        Task::spawn_ext(
+            "odometry",
+            bindings::TASK_PRIORITY_MAX,
+            bindings::TASK_STACK_DEPTH_DEFAULT as u16,
+            move || {
+                let mut l = Loop::new(self.interval);
+                let mut last = None;
+                loop {
+                    if let Ok(reading) = self.read() {
+                        if let Some(last) = last {
+                            let mut pose = pose.lock();
+                            self.update(&mut pose, &last, &reading);
+                            broadcast.publish(*pose);
+                        }
+                        last = Some(reading);
+                    }
+
+                    select! {
+                        _ = ctx.done() => break,
+                        _ = l.select() => continue,
+                    }
+                }
+            },
+        )
+        .unwrap();
+
+        tracker
+    }
+
+    fn read(&self) -> Result<Reading, Error> {
+        Ok(Reading {
+            first: self.first.distance()?,
+            second: self
+                .second
+                .as_ref()
+                .map(TrackingWheel::distance)
+                .transpose()?,
+            perpendicular: self
+                .perpendicular
+                .as_ref()
+                .map(TrackingWheel::distance)
+                .transpose()?,
+            heading: self.heading.as_ref().map(|f| f()).transpose()?,
+        })
+    }
+
+    fn update(&self, pose: &mut Pose, last: &Reading, reading: &Reading) {
+        let first = reading.first - last.first;
+        let second = reading.second.zip(last.second).map(|(r, l)| r - l);
+
+        // A clockwise turn moves wheels on the left forwards and wheels in
+        // front to the right, in proportion to their offsets.
+        let turn = match (reading.heading.zip(last.heading), &self.second, second) {
+            (Some((r, l)), _, _) => (r - l) * PI / 180.0,
+            (None, Some(wheel), Some(second)) => {
+                (first - second) / (wheel.offset - self.first.offset)
+            }
+            _ => return,
+        };
+
+        let mut forward = first + self.first.offset * turn;
+        if let (Some(wheel), Some(second)) = (&self.second, second) {
+            forward = (forward + second + wheel.offset * turn) / 2.0;
+        }
+        let right = match (
+            &self.perpendicular,
+            reading.perpendicular,
+            last.perpendicular,
+        ) {
+            (Some(wheel), Some(r), Some(l)) => r - l - wheel.offset * turn,
+            _ => 0.0,
+        };
+
+        pose.advance(forward, right, turn * 180.0 / PI);
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Reading {
+    first: f64,
+    second: Option<f64>,
+    perpendicular: Option<f64>,
+    heading: Option<f64>,
+}
+
+/// Tracks the pose of the robot from a background task; see
+/// [`Odometry::spawn()`].
+///
+/// The task stops when the tracker is dropped.
+pub struct OdometryTracker {
+    pose: Arc<Mutex<Pose>>,
+    broadcast: Arc<Broadcast<Pose>>,
+    ctx: Context,
+}
+
+impl OdometryTracker {
+    #[inline]
+    /// Gets the current pose of the robot.
+    pub fn pose(&self) -> Pose {
+        self.broadcast.value()
+    }
+
+    /// Overrides the current pose of the robot, such as at the start of an
+    /// autonomous routine. Future updates continue from the new pose.
+    pub fn set_pose(&self, pose: Pose) {
+        let mut current = self.pose.lock();
+        *current = pose;
+        self.broadcast.publish(pose);
+    }
+
+    #[inline]
+    /// Creates a [`BroadcastListener`] which receives every update to the pose
+    /// of the robot.
+    pub fn listen(&self) -> BroadcastListener<Pose> {
+        self.broadcast.listen()
+    }
+}
+
+impl Drop for OdometryTracker {
+    fn drop(&mut self) {
+        self.ctx.cancel();
+    }
+}
+
+impl PoseSource for OdometryTracker {
+    fn pose(&self) -> Pose {
+        OdometryTracker::pose(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADIUS: f64 = 24.0;
+
+    /// A tracking wheel which is never read, since the tests pass readings to
+    /// the odometry directly.
+    fn wheel(offset: f64) -> TrackingWheel {
+        TrackingWheel {
+            sensor: Box::new(|| Ok(0.0)),
+            scale: 1.0,
+            offset,
+        }
+    }
+
+    fn reading(first: f64, second: Option<f64>, perpendicular: Option<f64>) -> Reading {
+        Reading {
+            first,
+            second,
+            perpendicular,
+            heading: None,
+        }
+    }
+
+    /// The pose after driving clockwise from the origin along an arc of the
+    /// given radius, turning by the given angle in degrees.
+    fn arc(radius: f64, turn: f64) -> Pose {
+        let turn_rad = turn * PI / 180.0;
+        Pose::new(radius * (1.0 - cos(turn_rad)), radius * sin(turn_rad), turn)
+    }
+
+    fn assert_pose(actual: Pose, expected: Pose) {
+        assert!(
+            abs(actual.x - expected.x) < 1e-9
+                && abs(actual.y - expected.y) < 1e-9
+                && abs(actual.heading - expected.heading) < 1e-9,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn advance_straight() {
+        let mut pose = Pose::new(1.0, 2.0, 0.0);
+        pose.advance(10.0, 0.0, 0.0);
+        assert_pose(pose, Pose::new(1.0, 12.0, 0.0));
+
+        let mut pose = Pose::new(1.0, 2.0, 90.0);
+        pose.advance(10.0, 3.0, 0.0);
+        assert_pose(pose, Pose::new(11.0, -1.0, 90.0));
+    }
+
+    #[test]
+    fn advance_turn_in_place() {
+        let mut pose = Pose::new(1.0, 2.0, 30.0);
+        pose.advance(0.0, 0.0, 90.0);
+        assert_pose(pose, Pose::new(1.0, 2.0, 120.0));
+        pose.advance(0.0, 0.0, -120.0);
+        assert_pose(pose, Pose::new(1.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn advance_arc() {
+        for turn in [1.0, 45.0, 90.0, 180.0] {
+            let length = RADIUS * turn * PI / 180.0;
+            let mut pose = Pose::default();
+            pose.advance(length, 0.0, turn);
+            assert_pose(pose, arc(RADIUS, turn));
+
+            // The result does not depend on how the arc is divided.
+            let mut pose = Pose::default();
+            for _ in 0..100 {
+                pose.advance(length / 100.0, 0.0, turn / 100.0);
+            }
+            assert_pose(pose, arc(RADIUS, turn));
+
+            // Counterclockwise arcs are mirrored.
+            let mut pose = Pose::default();
+            pose.advance(length, 0.0, -turn);
+            let expected = arc(RADIUS, turn);
+            assert_pose(pose, Pose::new(-expected.x, expected.y, -turn));
+        }
+    }
+
+    #[test]
+    fn two_wheels() {
+        let odometry = Odometry::new(wheel(-5.0)).parallel(wheel(5.0));
+        let start = reading(0.0, Some(0.0), None);
+
+        let mut pose = Pose::default();
+        odometry.update(&mut pose, &start, &reading(10.0, Some(10.0), None));
+        assert_pose(pose, Pose::new(0.0, 10.0, 0.0));
+
+        // Turning in place moves the wheels in opposite directions.
+        let turn = PI / 2.0;
+        let mut pose = Pose::default();
+        odometry.update(
+            &mut pose,
+            &start,
+            &reading(5.0 * turn, Some(-5.0 * turn), None),
+        );
+        assert_pose(pose, Pose::new(0.0, 0.0, 90.0));
+
+        // On an arc, the outer wheel travels further.
+        let mut pose = Pose::default();
+        odometry.update(
+            &mut pose,
+            &start,
+            &reading((RADIUS + 5.0) * turn, Some((RADIUS - 5.0) * turn), None),
+        );
+        assert_pose(pose, arc(RADIUS, 90.0));
+    }
+
+    #[test]
+    fn three_wheels() {
+        let odometry = Odometry::new(wheel(-5.0))
+            .parallel(wheel(5.0))
+            .perpendicular(wheel(-4.5));
+        let start = reading(0.0, Some(0.0), Some(0.0));
+
+        // The same arc as with two wheels, during which a wheel behind the
+        // center rolls to the left.
+        let turn = PI / 2.0;
+        let mut pose = Pose::default();
+        odometry.update(
+            &mut pose,
+            &start,
+            &reading(
+                (RADIUS + 5.0) * turn,
+                Some((RADIUS - 5.0) * turn),
+                Some(-4.5 * turn),
+            ),
+        );
+        assert_pose(pose, arc(RADIUS, 90.0));
+
+        // Sideways motion is only seen by the perpendicular wheel.
+        let mut pose = Pose::new(0.0, 0.0, 90.0);
+        odometry.update(&mut pose, &start, &reading(0.0, Some(0.0), Some(6.0)));
+        assert_pose(pose, Pose::new(0.0, -6.0, 90.0));
+        let mut pose = Pose::default();
+        let two_wheels = Odometry::new(wheel(-5.0)).parallel(wheel(5.0));
+        two_wheels.update(&mut pose, &start, &reading(0.0, Some(0.0), Some(6.0)));
+        assert_pose(pose, Pose::default());
+    }
+
+    #[test]
+    fn heading_sensor() {
+        let mut odometry = Odometry::new(wheel(-5.0));
+        odometry.heading = Some(Box::new(|| Ok(0.0)));
+        let start = Reading {
+            heading: Some(0.0),
+            ..reading(0.0, None, None)
+        };
+
+        let turn = PI / 2.0;
+        let mut pose = Pose::default();
+        odometry.update(
+            &mut pose,
+            &start,
+            &Reading {
+                heading: Some(90.0),
+                ..reading((RADIUS + 5.0) * turn, None, None)
+            },
+        );
+        assert_pose(pose, arc(RADIUS, 90.0));
+
+        // The heading sensor is preferred to the difference between two wheels.
+        let mut odometry = Odometry::new(wheel(-5.0)).parallel(wheel(5.0));
+        odometry.heading = Some(Box::new(|| Ok(0.0)));
+        let start = Reading {
+            second: Some(0.0),
+            ..start
+        };
+        let mut pose = Pose::default();
+        odometry.update(
+            &mut pose,
+            &start,
+            &Reading {
+                heading: Some(0.0),
+                ..reading(10.0, Some(12.0), None)
+            },
+        );
+        assert_pose(pose, Pose::new(0.0, 11.0, 0.0));
+
+        // Without a heading, the pose cannot be updated.
+        let odometry = Odometry::new(wheel(-5.0));
+        let mut pose = Pose::default();
+        odometry.update(&mut pose, &start, &reading(10.0, None, None));
+        assert_pose(pose, Pose::default());
+    }
+}
@@ -0,0 +1,289 @@
+//! # Optical Sensor API.
+
+use crate::{
+    bindings,
+    error::{get_errno, Error},
+    rtos::DataSource,
+};
+
+/// A struct which represents a V5 smart port configured as an optical sensor.
+pub struct OpticalSensor {
+    port: u8,
+}
+
+impl OpticalSensor {
+    /// Constructs a new optical sensor.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it allows the user to create multiple
+    /// mutable references to the same optical sensor. You likely want to
+    /// implement [`Robot::new()`](crate::robot::Robot::new()) instead.
+    pub unsafe fn new(port: u8) -> OpticalSensor {
+        OpticalSensor { port }
+    }
+
+    /// Gets the detected color hue.
+    ///
+    /// This is a value in the range [0, 360).
+    pub fn get_hue(&self) -> Result<f64, OpticalSensorError> {
+        match unsafe { bindings::optical_get_hue(self.port) } {
+            x if x == bindings::PROS_ERR_F_ => Err(OpticalSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Gets the detected color saturation.
+    ///
+    /// This is a value in the range [0, 1].
+    pub fn get_saturation(&self) -> Result<f64, OpticalSensorError> {
+        match unsafe { bindings::optical_get_saturation(self.port) } {
+            x if x == bindings::PROS_ERR_F_ => Err(OpticalSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Gets the detected color brightness.
+    ///
+    /// This is a value in the range [0, 1].
+    pub fn get_brightness(&self) -> Result<f64, OpticalSensorError> {
+        match unsafe { bindings::optical_get_brightness(self.port) } {
+            x if x == bindings::PROS_ERR_F_ => Err(OpticalSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Gets the detected proximity value.
+    ///
+    /// This is a value in the range 0 to 255, where higher values mean that
+    /// the object is closer.
+    pub fn get_proximity(&self) -> Result<i32, OpticalSensorError> {
+        match unsafe { bindings::optical_get_proximity(self.port) } {
+            x if x == bindings::PROS_ERR_ => Err(OpticalSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Sets the brightness of the sensor's LED, in percent from 0 to 100.
+    pub fn set_led_pwm(&mut self, value: u8) -> Result<(), OpticalSensorError> {
+        match unsafe { bindings::optical_set_led_pwm(self.port, value) } {
+            bindings::PROS_ERR_ => Err(OpticalSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the brightness of the sensor's LED, in percent from 0 to 100.
+    pub fn get_led_pwm(&self) -> Result<i32, OpticalSensorError> {
+        match unsafe { bindings::optical_get_led_pwm(self.port) } {
+            x if x == bindings::PROS_ERR_ => Err(OpticalSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Gets the processed RGB values from the sensor.
+    pub fn get_rgb(&self) -> Result<OpticalRgb, OpticalSensorError> {
+        match unsafe { bindings::optical_get_rgb(self.port) } {
+            x if x.red == bindings::PROS_ERR_F_ => Err(OpticalSensorError::from_errno()),
+            x => Ok(OpticalRgb {
+                red: x.red,
+                green: x.green,
+                blue: x.blue,
+                brightness: x.brightness,
+            }),
+        }
+    }
+
+    /// Gets the raw, unprocessed RGBC values from the sensor.
+    pub fn get_raw(&self) -> Result<OpticalRaw, OpticalSensorError> {
+        match unsafe { bindings::optical_get_raw(self.port) } {
+            x if x.clear == bindings::PROS_ERR_ as u32 => Err(OpticalSensorError::from_errno()),
+            x => Ok(OpticalRaw {
+                clear: x.clear,
+                red: x.red,
+                green: x.green,
+                blue: x.blue,
+            }),
+        }
+    }
+
+    /// Gets the most recent gesture detected by the sensor, or `None` if no
+    /// gesture was detected.
+    ///
+    /// Gesture detection must first be enabled with
+    /// [`OpticalSensor::enable_gesture()`].
+    pub fn get_gesture(&self) -> Result<Option<Gesture>, OpticalSensorError> {
+        match unsafe { bindings::optical_get_gesture(self.port) } {
+            bindings::optical_direction_e_NO_GESTURE => Ok(None),
+            bindings::optical_direction_e_UP => Ok(Some(Gesture::Up)),
+            bindings::optical_direction_e_DOWN => Ok(Some(Gesture::Down)),
+            bindings::optical_direction_e_RIGHT => Ok(Some(Gesture::Right)),
+            bindings::optical_direction_e_LEFT => Ok(Some(Gesture::Left)),
+            bindings::optical_direction_e_ERROR => Err(OpticalSensorError::from_errno()),
+            x => panic!(
+                "bindings::optical_get_gesture returned unexpected value: {}",
+                x
+            ),
+        }
+    }
+
+    /// Gets the raw data for the most recent gesture detected by the sensor.
+    ///
+    /// Gesture detection must first be enabled with
+    /// [`OpticalSensor::enable_gesture()`].
+    pub fn get_gesture_raw(&self) -> Result<OpticalGestureRaw, OpticalSensorError> {
+        match unsafe { bindings::optical_get_gesture_raw(self.port) } {
+            x if x.time == bindings::PROS_ERR_ as u32 => Err(OpticalSensorError::from_errno()),
+            x => Ok(OpticalGestureRaw {
+                up: x.udata,
+                down: x.ddata,
+                left: x.ldata,
+                right: x.rdata,
+                gesture_type: x.type_,
+                count: x.count,
+                time: x.time,
+            }),
+        }
+    }
+
+    /// Enables gesture detection.
+    pub fn enable_gesture(&mut self) -> Result<(), OpticalSensorError> {
+        match unsafe { bindings::optical_enable_gesture(self.port) } {
+            bindings::PROS_ERR_ => Err(OpticalSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Disables gesture detection.
+    pub fn disable_gesture(&mut self) -> Result<(), OpticalSensorError> {
+        match unsafe { bindings::optical_disable_gesture(self.port) } {
+            bindings::PROS_ERR_ => Err(OpticalSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl DataSource for OpticalSensor {
+    type Data = OpticalSensorData;
+
+    type Error = OpticalSensorError;
+
+    fn read(&self) -> Result<Self::Data, Self::Error> {
+        Ok(OpticalSensorData {
+            hue: self.get_hue()?,
+            saturation: self.get_saturation()?,
+            brightness: self.get_brightness()?,
+            proximity: self.get_proximity()?,
+            rgb: self.get_rgb()?,
+            raw: self.get_raw()?,
+        })
+    }
+}
+
+/// Represents the data that can be read from an optical sensor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpticalSensorData {
+    /// The detected color hue, in the range [0, 360).
+    pub hue: f64,
+    /// The detected color saturation, in the range [0, 1].
+    pub saturation: f64,
+    /// The detected color brightness, in the range [0, 1].
+    pub brightness: f64,
+    /// The proximity value; see [`OpticalSensor::get_proximity()`] for
+    /// details.
+    pub proximity: i32,
+    /// The processed RGB values.
+    pub rgb: OpticalRgb,
+    /// The raw RGBC values.
+    pub raw: OpticalRaw,
+}
+
+/// Represents processed RGB values returned from an optical sensor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpticalRgb {
+    /// The red value.
+    pub red: f64,
+    /// The green value.
+    pub green: f64,
+    /// The blue value.
+    pub blue: f64,
+    /// The brightness value.
+    pub brightness: f64,
+}
+
+/// Represents raw RGBC values returned from an optical sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpticalRaw {
+    /// The clear (unfiltered) value.
+    pub clear: u32,
+    /// The red value.
+    pub red: u32,
+    /// The green value.
+    pub green: u32,
+    /// The blue value.
+    pub blue: u32,
+}
+
+/// Represents a gesture detected by an optical sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// An upward swipe.
+    Up,
+    /// A downward swipe.
+    Down,
+    /// A leftward swipe.
+    Left,
+    /// A rightward swipe.
+    Right,
+}
+
+/// Represents raw gesture data returned from an optical sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpticalGestureRaw {
+    /// The upward data.
+    pub up: u8,
+    /// The downward data.
+    pub down: u8,
+    /// The leftward data.
+    pub left: u8,
+    /// The rightward data.
+    pub right: u8,
+    /// The type of gesture.
+    pub gesture_type: u8,
+    /// The number of gestures detected.
+    pub count: u16,
+    /// The time of the gesture, in milliseconds.
+    pub time: u32,
+}
+
+/// Represents possible errors for optical sensor operations.
+#[derive(Debug)]
+pub enum OpticalSensorError {
+    /// Port is out of range (1-21).
+    PortOutOfRange,
+    /// Port cannot be configured as an optical sensor.
+    PortNotOpticalSensor,
+    /// Unknown error.
+    Unknown(i32),
+}
+
+impl OpticalSensorError {
+    fn from_errno() -> Self {
+        match get_errno() {
+            libc::ENXIO => Self::PortOutOfRange,
+            libc::ENODEV => Self::PortNotOpticalSensor,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl From<OpticalSensorError> for Error {
+    fn from(err: OpticalSensorError) -> Self {
+        match err {
+            OpticalSensorError::PortOutOfRange => Error::Custom("port out of range".into()),
+            OpticalSensorError::PortNotOpticalSensor => {
+                Error::Custom("port not an optical sensor".into())
+            }
+            OpticalSensorError::Unknown(n) => Error::System(n),
+        }
+    }
+}
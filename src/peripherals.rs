@@ -4,6 +4,7 @@ use crate::adi::AdiPort;
 use crate::{
     bindings,
     controller::{Controller, ControllerId},
+    screen::BrainScreen,
     smart_port::SmartPort,
 };
 
@@ -13,6 +14,8 @@ pub struct Peripherals {
     pub master_controller: Controller,
     /// Partner Controller.
     pub partner_controller: Controller,
+    /// Brain Screen.
+    pub brain_screen: BrainScreen,
     /// Smart Port 1.
     pub port01: SmartPort,
     /// Smart Port 2.
@@ -86,6 +89,7 @@ impl Peripherals {
         Peripherals {
             master_controller: Controller::new(ControllerId::Master),
             partner_controller: Controller::new(ControllerId::Partner),
+            brain_screen: BrainScreen::new(),
             port01: SmartPort::new(1),
             port02: SmartPort::new(2),
             port03: SmartPort::new(3),
@@ -4,16 +4,33 @@ pub use crate::entry;
 pub use crate::select;
 
 pub use crate::adi::*;
+pub use crate::autonomous::*;
 pub use crate::battery::*;
+pub use crate::control::*;
 pub use crate::controller::*;
 pub use crate::distance::*;
 pub use crate::error::*;
+pub use crate::gps::*;
+pub use crate::hal::*;
 pub use crate::imu::*;
 pub use crate::io::*;
+pub use crate::lcd::*;
+pub use crate::link::*;
 pub use crate::machine::*;
 pub use crate::motor::*;
+pub use crate::odometry::*;
+pub use crate::optical::*;
 pub use crate::peripherals::*;
+pub use crate::pursuit::*;
 pub use crate::robot::*;
 pub use crate::rotation::*;
 pub use crate::rtos::*;
+pub use crate::screen::*;
 pub use crate::smart_port::*;
+pub use crate::vision::*;
+
+// These modules are mostly free functions with generic names, such as
+// `competition::status()`, so only their types are re-exported.
+pub use crate::competition::{CompetitionStatus, CompetitionSystem};
+pub use crate::drive::{HolonomicVoltages, TankVoltages};
+pub use crate::sd::{File, SeekFrom};
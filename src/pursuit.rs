@@ -0,0 +1,651 @@
+//! Pure pursuit path following for differential drivetrains.
+//!
+//! The robot repeatedly steers along an arc towards a point on the path a
+//! short distance ahead of it, as if chasing it. Positions and headings follow
+//! the conventions of [`odometry`](crate::odometry), and velocities are in
+//! units of position per second.
+
+use alloc::vec::Vec;
+use core::{f64::consts::PI, time::Duration};
+
+use crate::{
+    control::FollowOutcome,
+    error::Error,
+    hal::VelocityActuator,
+    math::{abs, cos, sin, sqrt},
+    odometry::{Pose, PoseSource},
+    rtos::{time_since_start, Context, Loop},
+    select,
+};
+
+/// A path through a list of waypoints, for [`PurePursuit`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    points: Vec<(f64, f64)>,
+    /// The distance along the path to each point.
+    distances: Vec<f64>,
+    /// The curvature of the path at each point.
+    curvatures: Vec<f64>,
+}
+
+impl Path {
+    /// Creates a path through the given `(x, y)` waypoints, in order.
+    /// Repeated waypoints are ignored. Panics if there are fewer than two
+    /// distinct waypoints.
+    pub fn new(waypoints: &[(f64, f64)]) -> Self {
+        let mut points: Vec<(f64, f64)> = Vec::with_capacity(waypoints.len());
+        for &point in waypoints {
+            if points.last() != Some(&point) {
+                points.push(point);
+            }
+        }
+        assert!(
+            points.len() >= 2,
+            "path must have at least two distinct waypoints"
+        );
+
+        let mut distances = Vec::with_capacity(points.len());
+        let mut distance = 0.0;
+        distances.push(distance);
+        for pair in points.windows(2) {
+            distance += length(pair[0], pair[1]);
+            distances.push(distance);
+        }
+
+        let mut curvatures = Vec::with_capacity(points.len());
+        curvatures.push(0.0);
+        for triple in points.windows(3) {
+            // The curvature of the circle through the three points.
+            let (a, b, c) = (triple[0], triple[1], triple[2]);
+            let cross = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+            let sides = length(a, b) * length(b, c) * length(a, c);
+            curvatures.push(if sides > 0.0 {
+                2.0 * abs(cross) / sides
+            } else {
+                0.0
+            });
+        }
+        curvatures.push(0.0);
+
+        Self {
+            points,
+            distances,
+            curvatures,
+        }
+    }
+
+    /// Inserts evenly spaced points along the path, so that consecutive points
+    /// are at most `spacing` apart. A densely filled path slows the robot more
+    /// smoothly around corners. Panics if the spacing is not positive.
+    pub fn fill(self, spacing: f64) -> Self {
+        assert!(spacing > 0.0, "spacing must be positive");
+        let mut points = Vec::new();
+        for pair in self.points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let count = (length(a, b) / spacing) as usize + 1;
+            for i in 0..count {
+                let t = i as f64 / count as f64;
+                points.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+            }
+        }
+        points.extend(self.points.last());
+        Self::new(&points)
+    }
+
+    #[inline]
+    /// Gets the points along the path.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    #[inline]
+    /// Gets the total length of the path.
+    pub fn length(&self) -> f64 {
+        self.distances[self.distances.len() - 1]
+    }
+
+    /// Gets the index of the segment containing the point at the given
+    /// distance along the path.
+    fn segment(&self, distance: f64) -> usize {
+        let segments = self.points.len() - 1;
+        self.distances[1..segments]
+            .iter()
+            .take_while(|&&d| d <= distance)
+            .count()
+    }
+
+    /// Gets the point at the given distance along the path.
+    fn point_at(&self, distance: f64) -> (f64, f64) {
+        let i = self.segment(distance);
+        let (a, b) = (self.points[i], self.points[i + 1]);
+        let t = ((distance - self.distances[i]) / (self.distances[i + 1] - self.distances[i]))
+            .clamp(0.0, 1.0);
+        (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+    }
+}
+
+/// The velocities for the two sides of a differential drivetrain, in units of
+/// position per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WheelVelocities {
+    /// The velocity of the left side.
+    pub left: f64,
+    /// The velocity of the right side.
+    pub right: f64,
+}
+
+impl WheelVelocities {
+    /// Applies the velocities to the actuators on each side, for wheels of the
+    /// given diameter driven directly by the actuators.
+    pub fn apply(
+        &self,
+        left: &mut impl VelocityActuator,
+        right: &mut impl VelocityActuator,
+        wheel_diameter: f64,
+    ) -> Result<(), Error> {
+        // Distance per second to degrees per second.
+        let scale = 360.0 / (PI * wheel_diameter);
+        left.set_velocity(self.left * scale).map_err(Into::into)?;
+        right.set_velocity(self.right * scale).map_err(Into::into)
+    }
+}
+
+/// A pure pursuit controller, which drives a differential drivetrain along a
+/// [`Path`].
+///
+/// The robot only drives forwards. Its velocity is limited on tight curves of
+/// the path and as it approaches the end, where it stops, and its acceleration
+/// is limited throughout. The lookahead distance grows with the velocity, so
+/// that the robot cuts corners less when moving slowly.
+///
+/// ```
+/// # use vex_rt::{odometry::*, prelude::*, pursuit::*};
+/// # fn f(odometry: &OdometryTracker, left: &mut MotorGroup, right: &mut MotorGroup, ctx: Context) {
+/// let path = Path::new(&[(0.0, 0.0), (0.0, 24.0), (24.0, 48.0)]).fill(1.0);
+/// let outcome = PurePursuit::new(12.0, 48.0, 96.0).follow(&path, odometry, ctx, |v| {
+///     v.apply(left, right, 3.25)
+/// });
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct PurePursuit {
+    track_width: f64,
+    max_velocity: f64,
+    max_acceleration: f64,
+    turn_gain: f64,
+    min_lookahead: f64,
+    max_lookahead: f64,
+    tolerance: f64,
+    interval: Duration,
+}
+
+impl PurePursuit {
+    /// Creates a controller for a drivetrain with the given track width,
+    /// maximum velocity and maximum acceleration. Panics if any of these is
+    /// not positive.
+    ///
+    /// Initially the lookahead distance ranges from one to two track widths,
+    /// the robot slows on curves tighter than a radius of two track widths,
+    /// the path is finished within a tenth of a track width of its end, and
+    /// the outputs are updated every 10 milliseconds.
+    pub fn new(track_width: f64, max_velocity: f64, max_acceleration: f64) -> Self {
+        assert!(track_width > 0.0, "track width must be positive");
+        assert!(max_velocity > 0.0, "maximum velocity must be positive");
+        assert!(
+            max_acceleration > 0.0,
+            "maximum acceleration must be positive"
+        );
+        Self {
+            track_width,
+            max_velocity,
+            max_acceleration,
+            turn_gain: max_velocity / (2.0 * track_width),
+            min_lookahead: track_width,
+            max_lookahead: 2.0 * track_width,
+            tolerance: track_width / 10.0,
+            interval: Duration::from_millis(10),
+        }
+    }
+
+    /// Sets the range of the lookahead distance, which is `min` at rest and
+    /// `max` at the maximum velocity. Panics if `min` is not positive or is
+    /// greater than `max`.
+    pub fn lookahead(mut self, min: f64, max: f64) -> Self {
+        assert!(min > 0.0, "minimum lookahead must be positive");
+        assert!(min <= max, "lookahead minimum must not exceed maximum");
+        self.min_lookahead = min;
+        self.max_lookahead = max;
+        self
+    }
+
+    /// Sets how much the robot slows on curves: the velocity is limited to the
+    /// gain divided by the curvature of the path.
+    pub fn turn_gain(mut self, gain: f64) -> Self {
+        self.turn_gain = gain;
+        self
+    }
+
+    /// Sets how close to the end of the path the robot must be for the path to
+    /// be finished.
+    pub fn tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the interval between updates.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Follows a path from the current task, reading the pose of the robot
+    /// from `pose` and passing the wheel velocities to `output` on each
+    /// update.
+    ///
+    /// Returns once the robot reaches the end of the path, when the context is
+    /// cancelled, or with the first error returned by `output`. Zero velocities
+    /// are output before returning, so that the robot stops.
+    pub fn follow(
+        &self,
+        path: &Path,
+        pose: &impl PoseSource,
+        ctx: Context,
+        mut output: impl FnMut(WheelVelocities) -> Result<(), Error>,
+    ) -> Result<FollowOutcome, Error> {
+        let limits = self.velocity_limits(path);
+        let mut state = PursuitState {
+            progress: 0.0,
+            lookahead: 0.0,
+            velocity: 0.0,
+        };
+        let mut l = Loop::new(self.interval);
+        let mut last = time_since_start();
+        loop {
+            let now = time_since_start();
+            let dt = now
+                .checked_sub_instant(last)
+                .map_or(0.0, |d| d.as_secs_f64());
+            last = now;
+
+            match self.update(path, &limits, &mut state, &pose.pose(), dt) {
+                Some(velocities) => output(velocities)?,
+                None => {
+                    output(WheelVelocities::default())?;
+                    break Ok(FollowOutcome::Finished);
+                }
+            }
+
+            select! {
+                _ = ctx.done() => {
+                    output(WheelVelocities::default())?;
+                    break Ok(FollowOutcome::Cancelled);
+                },
+                _ = l.select() => continue,
+            }
+        }
+    }
+
+    /// Computes the fastest velocity at each point of the path, given the
+    /// curvature there and the need to slow down for later points.
+    fn velocity_limits(&self, path: &Path) -> Vec<f64> {
+        let mut limits: Vec<f64> = path
+            .curvatures
+            .iter()
+            .map(|&curvature| {
+                if curvature > 0.0 {
+                    self.max_velocity.min(self.turn_gain / curvature)
+                } else {
+                    self.max_velocity
+                }
+            })
+            .collect();
+        let last = limits.len() - 1;
+        limits[last] = 0.0;
+        for i in (0..last).rev() {
+            let segment = path.distances[i + 1] - path.distances[i];
+            let reachable =
+                sqrt(limits[i + 1] * limits[i + 1] + 2.0 * self.max_acceleration * segment);
+            limits[i] = limits[i].min(reachable);
+        }
+        limits
+    }
+
+    /// Computes the wheel velocities for the current pose, or returns `None`
+    /// if the end of the path has been reached.
+    fn update(
+        &self,
+        path: &Path,
+        limits: &[f64],
+        state: &mut PursuitState,
+        pose: &Pose,
+        dt: f64,
+    ) -> Option<WheelVelocities> {
+        let position = (pose.x, pose.y);
+        state.progress = self.closest(path, state.progress, position);
+        if path.length() - state.progress <= self.tolerance {
+            return None;
+        }
+
+        // Slow down for curves and the end of the path, and limit acceleration.
+        let segment = path.segment(state.progress);
+        let remaining = path.distances[segment + 1] - state.progress;
+        let velocity = limits[segment]
+            .min(sqrt(
+                limits[segment + 1] * limits[segment + 1] + 2.0 * self.max_acceleration * remaining,
+            ))
+            .min(state.velocity + self.max_acceleration * dt);
+        state.velocity = velocity;
+
+        let lookahead = self.min_lookahead
+            + (self.max_lookahead - self.min_lookahead) * velocity / self.max_velocity;
+        state.lookahead = self.lookahead_point(path, state, position, lookahead);
+        let target = path.point_at(state.lookahead);
+
+        // The curvature of the arc through the target which is tangent to the
+        // robot's heading, positive for clockwise.
+        let heading = pose.heading * PI / 180.0;
+        let (dx, dy) = (target.0 - position.0, target.1 - position.1);
+        let right = dx * cos(heading) - dy * sin(heading);
+        let distance_squared = dx * dx + dy * dy;
+        let curvature = if distance_squared > 0.0 {
+            2.0 * right / distance_squared
+        } else {
+            0.0
+        };
+
+        let turn = curvature * self.track_width / 2.0;
+        let (left, right) = (velocity * (1.0 + turn), velocity * (1.0 - turn));
+        // Scale both sides down together if either is too fast, to keep the
+        // same arc.
+        let scale = (abs(left).max(abs(right)) / self.max_velocity).max(1.0);
+        Some(WheelVelocities {
+            left: left / scale,
+            right: right / scale,
+        })
+    }
+
+    /// Finds the distance along the path of the point closest to the robot,
+    /// searching from the previous closest point up to the maximum lookahead
+    /// distance further on.
+    fn closest(&self, path: &Path, progress: f64, position: (f64, f64)) -> f64 {
+        let mut best = (progress, f64::INFINITY);
+        for i in path.segment(progress)..path.points.len() - 1 {
+            if path.distances[i] > progress + self.max_lookahead {
+                break;
+            }
+            let (a, b) = (path.points[i], path.points[i + 1]);
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let length = path.distances[i + 1] - path.distances[i];
+            let t = ((position.0 - a.0) * dx + (position.1 - a.1) * dy) / (length * length);
+            let distance = (path.distances[i] + t.clamp(0.0, 1.0) * length).max(progress);
+            let point = path.point_at(distance);
+            let (ex, ey) = (position.0 - point.0, position.1 - point.1);
+            let error = ex * ex + ey * ey;
+            if error < best.1 {
+                best = (distance, error);
+            }
+        }
+        best.0
+    }
+
+    /// Finds the distance along the path of the point where a circle of the
+    /// lookahead radius around the robot crosses the path, never moving
+    /// backwards along the path.
+    fn lookahead_point(
+        &self,
+        path: &Path,
+        state: &PursuitState,
+        position: (f64, f64),
+        radius: f64,
+    ) -> f64 {
+        let start = state.lookahead.max(state.progress);
+        let end = path.points[path.points.len() - 1];
+        if length(position, end) <= radius {
+            return path.length();
+        }
+        for i in path.segment(start)..path.points.len() - 1 {
+            let (a, b) = (path.points[i], path.points[i + 1]);
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let (fx, fy) = (a.0 - position.0, a.1 - position.1);
+            // Solve |a + t * (b - a) - position| = radius for the later t.
+            let qa = dx * dx + dy * dy;
+            let qb = 2.0 * (fx * dx + fy * dy);
+            let qc = fx * fx + fy * fy - radius * radius;
+            let discriminant = qb * qb - 4.0 * qa * qc;
+            if discriminant < 0.0 {
+                continue;
+            }
+            let t = (-qb + sqrt(discriminant)) / (2.0 * qa);
+            if (0.0..=1.0).contains(&t) {
+                let distance = path.distances[i] + t * (path.distances[i + 1] - path.distances[i]);
+                if distance >= start {
+                    return distance;
+                }
+            }
+        }
+        start
+    }
+}
+
+#[derive(Clone, Copy)]
+struct PursuitState {
+    /// The distance along the path of the point closest to the robot.
+    progress: f64,
+    /// The distance along the path of the lookahead point.
+    lookahead: f64,
+    /// The velocity of the robot at the last update.
+    velocity: f64,
+}
+
+fn length(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    sqrt(dx * dx + dy * dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use super::*;
+    use crate::hal::fake::FakeActuator;
+
+    const TRACK_WIDTH: f64 = 12.0;
+    const MAX_VELOCITY: f64 = 48.0;
+    const MAX_ACCELERATION: f64 = 96.0;
+    const DT: f64 = 0.01;
+
+    const STRAIGHT: [(f64, f64); 2] = [(0.0, 0.0), (0.0, 72.0)];
+    const RIGHT_TURN: [(f64, f64); 3] = [(0.0, 0.0), (0.0, 48.0), (48.0, 48.0)];
+    const LEFT_TURN: [(f64, f64); 3] = [(0.0, 0.0), (0.0, 48.0), (-48.0, 48.0)];
+
+    /// A pose source for a robot which moves exactly as it is told to.
+    struct FakeRobot(Cell<Pose>);
+
+    impl FakeRobot {
+        fn new(pose: Pose) -> Self {
+            Self(Cell::new(pose))
+        }
+
+        /// Drives with the given wheel velocities for `dt` seconds.
+        fn drive(&self, velocities: WheelVelocities, dt: f64) {
+            let mut pose = self.0.get();
+            let forward = (velocities.left + velocities.right) / 2.0 * dt;
+            let turn = (velocities.left - velocities.right) / TRACK_WIDTH * dt;
+            let heading = pose.heading * PI / 180.0 + turn / 2.0;
+            pose.x += forward * sin(heading);
+            pose.y += forward * cos(heading);
+            pose.heading += turn * 180.0 / PI;
+            self.0.set(pose);
+        }
+    }
+
+    impl PoseSource for FakeRobot {
+        fn pose(&self) -> Pose {
+            self.0.get()
+        }
+    }
+
+    fn pursuit() -> PurePursuit {
+        PurePursuit::new(TRACK_WIDTH, MAX_VELOCITY, MAX_ACCELERATION)
+    }
+
+    /// Drives the robot along the path one update at a time, returning the
+    /// state after each update and the velocities given by it.
+    fn drive(path: &Path, robot: &FakeRobot) -> Vec<(PursuitState, WheelVelocities)> {
+        let pursuit = pursuit();
+        let limits = pursuit.velocity_limits(path);
+        let mut state = PursuitState {
+            progress: 0.0,
+            lookahead: 0.0,
+            velocity: 0.0,
+        };
+        let mut updates = Vec::new();
+        while let Some(velocities) = pursuit.update(path, &limits, &mut state, &robot.pose(), DT) {
+            robot.drive(velocities, DT);
+            updates.push((state, velocities));
+            assert!(updates.len() < 10000, "robot never reached the end");
+        }
+        updates
+    }
+
+    #[test]
+    fn wheel_velocities_apply() {
+        let (mut left, mut right) = (FakeActuator::default(), FakeActuator::default());
+        let velocities = WheelVelocities {
+            left: PI,
+            right: -PI / 2.0,
+        };
+        velocities.apply(&mut left, &mut right, 2.0).unwrap();
+        // A 2 inch wheel travels 2 pi inches per revolution.
+        assert_eq!(left.velocities, [180.0]);
+        assert_eq!(right.velocities, [-90.0]);
+    }
+
+    #[test]
+    fn fill_keeps_spacing() {
+        for waypoints in [&STRAIGHT[..], &RIGHT_TURN[..]] {
+            let path = Path::new(waypoints);
+            for spacing in [0.3, 1.0, 5.0, 100.0] {
+                let filled = path.clone().fill(spacing);
+                let points = filled.points();
+                assert_eq!(points.first(), waypoints.first());
+                assert_eq!(points.last(), waypoints.last());
+                for &waypoint in waypoints {
+                    assert!(points.contains(&waypoint));
+                }
+                for pair in points.windows(2) {
+                    assert!(length(pair[0], pair[1]) <= spacing + 1e-9);
+                }
+                assert!(abs(filled.length() - path.length()) < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn velocity_limits_stop_at_end() {
+        let pursuit = pursuit();
+        for waypoints in [&STRAIGHT[..], &RIGHT_TURN[..], &LEFT_TURN[..]] {
+            let path = Path::new(waypoints).fill(1.0);
+            let limits = pursuit.velocity_limits(&path);
+            assert_eq!(limits.len(), path.points().len());
+            assert_eq!(limits[limits.len() - 1], 0.0);
+            for (i, &limit) in limits.iter().enumerate() {
+                assert!(limit >= 0.0 && limit <= MAX_VELOCITY);
+                if let Some(&next) = limits.get(i + 1) {
+                    // The robot can always slow down in time for the next point.
+                    let segment = path.distances[i + 1] - path.distances[i];
+                    assert!(limit * limit <= next * next + 2.0 * MAX_ACCELERATION * segment + 1e-9);
+                }
+            }
+        }
+
+        let path = Path::new(&RIGHT_TURN).fill(1.0);
+        let limits = pursuit.velocity_limits(&path);
+        let corner = path
+            .points()
+            .iter()
+            .position(|&p| p == (0.0, 48.0))
+            .unwrap();
+        assert!(limits[corner] < MAX_VELOCITY);
+    }
+
+    #[test]
+    fn straight_path_drives_straight() {
+        let path = Path::new(&STRAIGHT).fill(1.0);
+        let robot = FakeRobot::new(Pose::default());
+        let updates = drive(&path, &robot);
+        for (_, velocities) in &updates {
+            assert!(abs(velocities.left - velocities.right) < 1e-9);
+        }
+        let pose = robot.pose();
+        assert!(length((pose.x, pose.y), (0.0, 72.0)) <= TRACK_WIDTH / 10.0);
+        assert!(abs(pose.heading) < 1e-9);
+    }
+
+    #[test]
+    fn curvature_matches_turn_direction() {
+        for (waypoints, direction) in [(RIGHT_TURN, 1.0), (LEFT_TURN, -1.0)] {
+            let path = Path::new(&waypoints).fill(1.0);
+            let pursuit = pursuit();
+            let limits = pursuit.velocity_limits(&path);
+            let mut state = PursuitState {
+                progress: 40.0,
+                lookahead: 40.0,
+                velocity: MAX_VELOCITY,
+            };
+            // Approaching the corner, the robot steers into the turn.
+            let velocities = pursuit
+                .update(&path, &limits, &mut state, &Pose::new(0.0, 40.0, 0.0), DT)
+                .unwrap();
+            assert!(direction * (velocities.left - velocities.right) > 0.0);
+
+            let robot = FakeRobot::new(Pose::default());
+            drive(&path, &robot);
+            let pose = robot.pose();
+            assert!(length((pose.x, pose.y), waypoints[2]) <= TRACK_WIDTH / 10.0);
+            assert!(direction * pose.heading > 45.0);
+        }
+    }
+
+    #[test]
+    fn lookahead_never_moves_backwards() {
+        for waypoints in [&STRAIGHT[..], &RIGHT_TURN[..], &LEFT_TURN[..]] {
+            let path = Path::new(waypoints).fill(1.0);
+            // Start off the path, so that the robot has to cut back to it.
+            let robot = FakeRobot::new(Pose::new(6.0, -6.0, 45.0));
+            let updates = drive(&path, &robot);
+            assert!(!updates.is_empty());
+            for pair in updates.windows(2) {
+                let (previous, state) = (&pair[0].0, &pair[1].0);
+                assert!(state.lookahead >= previous.lookahead);
+                assert!(state.progress >= previous.progress);
+                assert!(state.lookahead >= state.progress);
+            }
+        }
+    }
+
+    #[test]
+    fn follow_stops_at_end() {
+        let path = Path::new(&RIGHT_TURN).fill(1.0);
+        let robot = FakeRobot::new(Pose::default());
+        let mut outputs = Vec::new();
+        let start = time_since_start();
+        let outcome = pursuit()
+            .follow(&path, &robot, Context::new_global(), |velocities| {
+                robot.drive(velocities, DT);
+                outputs.push(velocities);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(outcome, FollowOutcome::Finished);
+        assert_eq!(outputs.last(), Some(&WheelVelocities::default()));
+        let elapsed = time_since_start().checked_sub_instant(start).unwrap();
+        assert_eq!(
+            elapsed,
+            Duration::from_millis(10) * (outputs.len() as u32 - 1)
+        );
+        let pose = robot.pose();
+        assert!(length((pose.x, pose.y), RIGHT_TURN[2]) <= TRACK_WIDTH / 10.0);
+    }
+}
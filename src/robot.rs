@@ -1,6 +1,9 @@
 //! For use with the [`entry!`](crate::entry!) macro.
 
-use crate::{io::println, peripherals::Peripherals, rtos::Context, state_machine};
+use crate::{
+    autonomous::AutonomousSelector, io::println, peripherals::Peripherals, rtos::Context,
+    state_machine,
+};
 
 /// A trait representing a competition-ready VEX Robot.
 pub trait Robot: Send + Sync + 'static {
@@ -19,9 +22,24 @@ pub trait Robot: Send + Sync + 'static {
     /// without issue.
     fn initialize(&mut self, _ctx: Context) {}
 
+    /// Gets the selector holding the robot's autonomous routines, if it has
+    /// one.
+    fn autonomous_selector(&self) -> Option<&AutonomousSelector<Self>> {
+        None
+    }
+
     /// Runs during the autonomous period.
-    fn autonomous(&mut self, _ctx: Context) {
-        println!("autonomous");
+    ///
+    /// By default, this runs the routine selected in
+    /// [`Robot::autonomous_selector()`], if there is one.
+    fn autonomous(&mut self, ctx: Context) {
+        match self
+            .autonomous_selector()
+            .and_then(AutonomousSelector::selected_routine)
+        {
+            Some(routine) => routine(self, ctx),
+            None => println!("autonomous"),
+        }
     }
 
     /// Runs during the opcontrol period.
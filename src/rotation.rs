@@ -3,6 +3,7 @@
 use crate::{
     bindings,
     error::{get_errno, Error},
+    hal::{PositionSensor, VelocitySensor},
     rtos::DataSource,
 };
 
@@ -116,6 +117,26 @@ impl DataSource for RotationSensor {
     }
 }
 
+impl PositionSensor for RotationSensor {
+    type Error = RotationSensorError;
+
+    fn position(&self) -> Result<f64, Self::Error> {
+        Ok(self.get_position()? as f64 / 100.0)
+    }
+
+    fn reset_position(&mut self) -> Result<(), Self::Error> {
+        self.set_position(0)
+    }
+}
+
+impl VelocitySensor for RotationSensor {
+    type Error = RotationSensorError;
+
+    fn velocity(&self) -> Result<f64, Self::Error> {
+        Ok(self.get_velocity()? as f64 / 100.0)
+    }
+}
+
 /// Represents the data that can be read from a rotation sensor.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct RotationSensorData {
@@ -91,6 +91,12 @@ impl Context {
         }
     }
 
+    #[cfg(feature = "sim")]
+    /// Checks whether two contexts are copies of the same context.
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
     fn new_internal(parents: &[&Self], mut deadline: Option<Instant>) -> Self {
         deadline = parents
             .iter()
@@ -348,6 +348,14 @@ impl Task {
     pub unsafe fn delete(&self) {
         bindings::task_delete(self.0)
     }
+
+    #[inline]
+    /// Marks the task as a service which lives for the rest of the program. In
+    /// simulation, such tasks are never reported as lingering nor deleted.
+    pub(crate) fn mark_service(&self) {
+        #[cfg(feature = "sim")]
+        crate::sim::mark_service(self.0);
+    }
 }
 
 impl Debug for Task {
@@ -0,0 +1,420 @@
+//! # Brain Screen API.
+//!
+//! Coordinates are measured in pixels from the top-left corner of the usable
+//! area of the screen, below the status bar.
+
+use core::{
+    convert::{TryFrom, TryInto},
+    time::Duration,
+};
+
+use cstring_interop::with_cstring;
+
+use crate::{
+    bindings,
+    error::{get_errno, Error},
+    lcd::{Lcd, LcdError},
+    rtos::{time_since_start, GenericSleep, Selectable},
+};
+
+/// The width of the brain screen, in pixels.
+pub const BRAIN_SCREEN_WIDTH: i16 = 480;
+
+/// The height of the brain screen below the status bar, in pixels.
+pub const BRAIN_SCREEN_HEIGHT: i16 = 240;
+
+/// How often [`BrainScreen::select_touch()`] checks for new touches.
+const TOUCH_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A struct which represents the touchscreen on the V5 brain.
+pub struct BrainScreen {
+    last_touch: TouchStatus,
+}
+
+impl BrainScreen {
+    /// Constructs a new brain screen.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it allows the user to create multiple
+    /// mutable references to the brain screen. You likely want to use the
+    /// screen in the peripherals object passed into
+    /// [`Robot::new()`](crate::robot::Robot::new()) instead.
+    pub unsafe fn new() -> BrainScreen {
+        BrainScreen {
+            last_touch: TouchStatus {
+                state: TouchState::Released,
+                x: 0,
+                y: 0,
+                press_count: 0,
+                release_count: 0,
+            },
+        }
+    }
+
+    /// Sets the color used to draw shapes and text.
+    pub fn set_pen(&mut self, color: Color) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_set_pen(color.into_raw()) })
+    }
+
+    /// Gets the color used to draw shapes and text.
+    pub fn get_pen(&self) -> Result<Color, BrainScreenError> {
+        match unsafe { bindings::screen_get_pen() } {
+            x if x == bindings::PROS_ERR_ as u32 => Err(BrainScreenError::from_errno()),
+            x => Ok(Color::from_raw(x)),
+        }
+    }
+
+    /// Sets the color used to clear the screen and behind text.
+    pub fn set_eraser(&mut self, color: Color) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_set_eraser(color.into_raw()) })
+    }
+
+    /// Gets the color used to clear the screen and behind text.
+    pub fn get_eraser(&self) -> Result<Color, BrainScreenError> {
+        match unsafe { bindings::screen_get_eraser() } {
+            x if x == bindings::PROS_ERR_ as u32 => Err(BrainScreenError::from_errno()),
+            x => Ok(Color::from_raw(x)),
+        }
+    }
+
+    /// Clears the whole screen with the eraser color.
+    pub fn clear(&mut self) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_erase() })
+    }
+
+    /// Draws a single pixel with the pen color.
+    pub fn draw_pixel(&mut self, x: i16, y: i16) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_draw_pixel(x, y) })
+    }
+
+    /// Draws a line between two points with the pen color.
+    pub fn draw_line(
+        &mut self,
+        x0: i16,
+        y0: i16,
+        x1: i16,
+        y1: i16,
+    ) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_draw_line(x0, y0, x1, y1) })
+    }
+
+    /// Draws the outline of a rectangle with opposite corners at the given
+    /// points, with the pen color.
+    pub fn draw_rect(
+        &mut self,
+        x0: i16,
+        y0: i16,
+        x1: i16,
+        y1: i16,
+    ) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_draw_rect(x0, y0, x1, y1) })
+    }
+
+    /// Fills a rectangle with opposite corners at the given points with the
+    /// pen color.
+    pub fn fill_rect(
+        &mut self,
+        x0: i16,
+        y0: i16,
+        x1: i16,
+        y1: i16,
+    ) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_fill_rect(x0, y0, x1, y1) })
+    }
+
+    /// Draws the outline of a circle with the pen color.
+    pub fn draw_circle(&mut self, x: i16, y: i16, radius: i16) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_draw_circle(x, y, radius) })
+    }
+
+    /// Fills a circle with the pen color.
+    pub fn fill_circle(&mut self, x: i16, y: i16, radius: i16) -> Result<(), BrainScreenError> {
+        check(unsafe { bindings::screen_fill_circle(x, y, radius) })
+    }
+
+    /// Prints text on one of the lines of the screen, starting from 0 at the
+    /// top. The height of a line depends on the text format.
+    pub fn print(
+        &mut self,
+        format: TextFormat,
+        line: i16,
+        text: &str,
+    ) -> Result<(), BrainScreenError> {
+        let result: Result<u32, Error> = with_cstring(text.into(), |text| {
+            Ok(unsafe {
+                bindings::screen_print(
+                    format.into(),
+                    line,
+                    b"%s\0".as_ptr() as *const _,
+                    text.into_raw(),
+                )
+            })
+        });
+        check(result.map_err(|_| BrainScreenError::InvalidText)?)
+    }
+
+    /// Prints text with its top-left corner at the given point.
+    pub fn print_at(
+        &mut self,
+        format: TextFormat,
+        x: i16,
+        y: i16,
+        text: &str,
+    ) -> Result<(), BrainScreenError> {
+        let result: Result<u32, Error> = with_cstring(text.into(), |text| {
+            Ok(unsafe {
+                bindings::screen_print_at(
+                    format.into(),
+                    x,
+                    y,
+                    b"%s\0".as_ptr() as *const _,
+                    text.into_raw(),
+                )
+            })
+        });
+        check(result.map_err(|_| BrainScreenError::InvalidText)?)
+    }
+
+    /// Gets the current state of the touchscreen.
+    pub fn get_touch_status(&self) -> Result<TouchStatus, BrainScreenError> {
+        let status = unsafe { bindings::screen_touch_status() };
+        Ok(TouchStatus {
+            state: match status.touch_status {
+                bindings::last_touch_e_t_E_TOUCH_RELEASED => TouchState::Released,
+                bindings::last_touch_e_t_E_TOUCH_PRESSED => TouchState::Pressed,
+                bindings::last_touch_e_t_E_TOUCH_HELD => TouchState::Held,
+                _ => return Err(BrainScreenError::from_errno()),
+            },
+            x: status.x,
+            y: status.y,
+            press_count: status.press_count,
+            release_count: status.release_count,
+        })
+    }
+
+    /// Gets the next touch event since the last one returned, if any.
+    ///
+    /// Presses and releases are never missed, but those which happened since
+    /// the previous call all report the current position of the touch.
+    pub fn next_touch_event(&mut self) -> Result<Option<TouchEvent>, BrainScreenError> {
+        let status = self.get_touch_status()?;
+        let last = &mut self.last_touch;
+        let (x, y) = (status.x, status.y);
+        let event = if last.press_count == last.release_count {
+            if status.press_count != last.press_count {
+                last.press_count = last.press_count.wrapping_add(1);
+                Some(TouchEvent::Press { x, y })
+            } else {
+                None
+            }
+        } else if status.release_count != last.release_count {
+            last.release_count = last.release_count.wrapping_add(1);
+            Some(TouchEvent::Release { x, y })
+        } else if (x, y) != (last.x, last.y) {
+            Some(TouchEvent::Drag { x, y })
+        } else {
+            None
+        };
+        last.state = status.state;
+        last.x = x;
+        last.y = y;
+        Ok(event)
+    }
+
+    /// A [`Selectable`] event which resolves with the next touch event; see
+    /// [`BrainScreen::next_touch_event()`].
+    pub fn select_touch(&mut self) -> impl '_ + Selectable<Result<TouchEvent, BrainScreenError>> {
+        struct TouchSelect<'b>(&'b mut BrainScreen);
+
+        impl<'b> Selectable<Result<TouchEvent, BrainScreenError>> for TouchSelect<'b> {
+            fn poll(self) -> Result<Result<TouchEvent, BrainScreenError>, Self> {
+                match self.0.next_touch_event() {
+                    Ok(Some(event)) => Ok(Ok(event)),
+                    Ok(None) => Err(self),
+                    Err(err) => Ok(Err(err)),
+                }
+            }
+
+            fn sleep(&self) -> GenericSleep {
+                GenericSleep::Timestamp(time_since_start() + TOUCH_POLL_INTERVAL)
+            }
+        }
+
+        TouchSelect(self)
+    }
+
+    /// Turns the screen into an [`Lcd`].
+    #[inline]
+    pub fn into_lcd(self) -> Result<Lcd, LcdError> {
+        self.try_into()
+    }
+}
+
+impl TryFrom<BrainScreen> for Lcd {
+    type Error = LcdError;
+
+    /// Converts a `BrainScreen` into an [`Lcd`].
+    fn try_from(_screen: BrainScreen) -> Result<Self, Self::Error> {
+        unsafe { Lcd::new() }
+    }
+}
+
+fn check(result: u32) -> Result<(), BrainScreenError> {
+    if result == bindings::PROS_ERR_ as u32 {
+        Err(BrainScreenError::from_errno())
+    } else {
+        Ok(())
+    }
+}
+
+/// Represents a color on the brain screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Color(u32);
+
+impl Color {
+    /// Black.
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    /// White.
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    /// Gray.
+    pub const GRAY: Color = Color::rgb(128, 128, 128);
+    /// Red.
+    pub const RED: Color = Color::rgb(255, 0, 0);
+    /// Orange.
+    pub const ORANGE: Color = Color::rgb(255, 165, 0);
+    /// Yellow.
+    pub const YELLOW: Color = Color::rgb(255, 255, 0);
+    /// Green.
+    pub const GREEN: Color = Color::rgb(0, 255, 0);
+    /// Cyan.
+    pub const CYAN: Color = Color::rgb(0, 255, 255);
+    /// Blue.
+    pub const BLUE: Color = Color::rgb(0, 0, 255);
+    /// Magenta.
+    pub const MAGENTA: Color = Color::rgb(255, 0, 255);
+
+    /// Constructs a color from its red, green and blue components.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color((r as u32) << 16 | (g as u32) << 8 | b as u32)
+    }
+
+    /// Constructs a color from its raw `0xRRGGBB` value.
+    pub const fn from_raw(raw: u32) -> Color {
+        Color(raw & 0xFFFFFF)
+    }
+
+    /// Gets the raw `0xRRGGBB` value of the color.
+    pub const fn into_raw(self) -> u32 {
+        self.0
+    }
+}
+
+/// Represents the size and alignment of text printed on the brain screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextFormat {
+    /// Small text.
+    Small,
+    /// Medium text.
+    Medium,
+    /// Large text.
+    Large,
+    /// Medium text, centred horizontally on the line.
+    MediumCenter,
+    /// Large text, centred horizontally on the line.
+    LargeCenter,
+}
+
+impl From<TextFormat> for bindings::text_format_e_t {
+    fn from(format: TextFormat) -> Self {
+        match format {
+            TextFormat::Small => bindings::text_format_e_t_E_TEXT_SMALL,
+            TextFormat::Medium => bindings::text_format_e_t_E_TEXT_MEDIUM,
+            TextFormat::Large => bindings::text_format_e_t_E_TEXT_LARGE,
+            TextFormat::MediumCenter => bindings::text_format_e_t_E_TEXT_MEDIUM_CENTER,
+            TextFormat::LargeCenter => bindings::text_format_e_t_E_TEXT_LARGE_CENTER,
+        }
+    }
+}
+
+/// Represents the state of the touchscreen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchState {
+    /// The screen is not being touched.
+    Released,
+    /// The screen has just been touched.
+    Pressed,
+    /// The screen is being touched.
+    Held,
+}
+
+/// Represents the status of the touchscreen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TouchStatus {
+    /// The state of the touch.
+    pub state: TouchState,
+    /// The x-coordinate of the latest touch.
+    pub x: i16,
+    /// The y-coordinate of the latest touch.
+    pub y: i16,
+    /// The number of times the screen has been pressed.
+    pub press_count: i32,
+    /// The number of times the screen has been released.
+    pub release_count: i32,
+}
+
+/// Represents a change in the touchscreen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchEvent {
+    /// The screen was pressed at the given point.
+    Press {
+        /// The x-coordinate.
+        x: i16,
+        /// The y-coordinate.
+        y: i16,
+    },
+    /// The screen was released at the given point.
+    Release {
+        /// The x-coordinate.
+        x: i16,
+        /// The y-coordinate.
+        y: i16,
+    },
+    /// The touch moved to the given point while held.
+    Drag {
+        /// The x-coordinate.
+        x: i16,
+        /// The y-coordinate.
+        y: i16,
+    },
+}
+
+/// Represents possible errors for brain screen operations.
+#[derive(Debug)]
+pub enum BrainScreenError {
+    /// Another resource is currently accessing the screen.
+    Busy,
+    /// The text contains a null byte.
+    InvalidText,
+    /// Unknown error.
+    Unknown(i32),
+}
+
+impl BrainScreenError {
+    fn from_errno() -> Self {
+        match get_errno() {
+            libc::EACCES => Self::Busy,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl From<BrainScreenError> for Error {
+    fn from(err: BrainScreenError) -> Self {
+        match err {
+            BrainScreenError::Busy => Error::Custom("brain screen is busy".into()),
+            BrainScreenError::InvalidText => Error::Custom("text contains a null byte".into()),
+            BrainScreenError::Unknown(n) => Error::System(n),
+        }
+    }
+}
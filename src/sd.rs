@@ -0,0 +1,199 @@
+//! API for accessing files on the micro-SD card in the V5 brain.
+//!
+//! Paths are relative to the root of the SD card.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{convert::TryInto, fmt};
+
+use cstring_interop::with_cstring;
+
+use crate::{
+    bindings,
+    error::{from_errno, Error, SentinelError},
+};
+
+/// The size of the buffer used to list the contents of a directory, which
+/// limits the total length of the names returned by [`read_dir()`].
+const DIR_BUFFER_SIZE: usize = 4096;
+
+// The VEX SDK function behind directory listings is not exposed by PROS.
+#[cfg(not(feature = "sim"))]
+extern "C" {
+    fn vexFileDirectoryGet(path: *const libc::c_char, buffer: *mut libc::c_char, len: u32) -> u32;
+}
+
+#[cfg(feature = "sim")]
+use bindings::vexFileDirectoryGet;
+
+/// Checks whether an SD card is installed.
+pub fn is_installed() -> bool {
+    unsafe { bindings::usd_is_installed() == 1 }
+}
+
+/// Lists the names of the files and directories in a directory.
+pub fn read_dir(path: &str) -> Result<Vec<String>, Error> {
+    let mut buffer = vec![0u8; DIR_BUFFER_SIZE];
+    let path = format!("/{}", path.trim_start_matches('/'));
+    let result: Result<u32, Error> = with_cstring(path, |path| {
+        Ok(unsafe {
+            vexFileDirectoryGet(
+                path.into_raw(),
+                buffer.as_mut_ptr() as *mut _,
+                DIR_BUFFER_SIZE as u32,
+            )
+        })
+    });
+    match result? {
+        0 => {
+            let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+            Ok(String::from_utf8_lossy(&buffer[..len])
+                .split('\n')
+                .filter(|name| !name.is_empty())
+                .map(ToString::to_string)
+                .collect())
+        }
+        // FR_NOT_READY
+        3 => Err(Error::System(libc::ENXIO)),
+        // FR_NO_FILE, FR_NO_PATH
+        4 | 5 => Err(Error::System(libc::ENOENT)),
+        _ => Err(Error::System(libc::EIO)),
+    }
+}
+
+/// Represents an open file on the SD card.
+///
+/// The file is closed when dropped.
+pub struct File(*mut bindings::FILE);
+
+unsafe impl Send for File {}
+
+impl File {
+    /// Opens an existing file for reading.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        Self::open_with_mode(path, b"rb\0")
+    }
+
+    /// Creates a file for writing, replacing its contents if it already
+    /// exists.
+    pub fn create(path: &str) -> Result<Self, Error> {
+        Self::open_with_mode(path, b"wb\0")
+    }
+
+    /// Opens a file for writing at its end, creating it if it does not exist.
+    pub fn append(path: &str) -> Result<Self, Error> {
+        Self::open_with_mode(path, b"ab\0")
+    }
+
+    fn open_with_mode(path: &str, mode: &[u8]) -> Result<Self, Error> {
+        let path = format!("/usd/{}", path.trim_start_matches('/'));
+        with_cstring(path, |path| unsafe {
+            Ok(Self(
+                bindings::fopen(path.into_raw(), mode.as_ptr() as *const _).check()?,
+            ))
+        })
+    }
+
+    /// Reads bytes into the given buffer, returning the number read. This is
+    /// less than the length of the buffer only at the end of the file.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        let n = unsafe { bindings::fread(buffer.as_mut_ptr() as *mut _, 1, buffer.len(), self.0) };
+        if n < buffer.len() && unsafe { bindings::ferror(self.0) } != 0 {
+            Err(from_errno())
+        } else {
+            Ok(n)
+        }
+    }
+
+    /// Reads the rest of the file, appending it to the given buffer and
+    /// returning the number of bytes read.
+    pub fn read_to_end(&mut self, buffer: &mut Vec<u8>) -> Result<usize, Error> {
+        let start = buffer.len();
+        let mut chunk = [0u8; 256];
+        loop {
+            let n = self.read(&mut chunk)?;
+            buffer.extend_from_slice(&chunk[..n]);
+            if n < chunk.len() {
+                return Ok(buffer.len() - start);
+            }
+        }
+    }
+
+    /// Reads the rest of the file, which must be valid UTF-8, appending it to
+    /// the given string and returning the number of bytes read.
+    pub fn read_to_string(&mut self, buffer: &mut String) -> Result<usize, Error> {
+        let mut bytes = Vec::new();
+        let n = self.read_to_end(&mut bytes)?;
+        buffer.push_str(
+            core::str::from_utf8(&bytes).map_err(|err| Error::Custom(format!("{:?}", err)))?,
+        );
+        Ok(n)
+    }
+
+    /// Writes all of the given bytes to the file.
+    pub fn write_all(&mut self, data: &[u8]) -> Result<(), Error> {
+        let n = unsafe { bindings::fwrite(data.as_ptr() as *const _, 1, data.len(), self.0) };
+        if n < data.len() {
+            Err(from_errno())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes any buffered data to the SD card.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        match unsafe { bindings::fflush(self.0) } {
+            0 => Ok(()),
+            _ => Err(from_errno()),
+        }
+    }
+
+    /// Moves the position in the file at which the next read or write occurs,
+    /// returning the new position from the start of the file.
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset.try_into()?, bindings::SEEK_SET),
+            SeekFrom::End(offset) => (offset.try_into()?, bindings::SEEK_END),
+            SeekFrom::Current(offset) => (offset.try_into()?, bindings::SEEK_CUR),
+        };
+        match unsafe { bindings::fseek(self.0, offset, whence as libc::c_int) } {
+            0 => self.position(),
+            _ => Err(from_errno()),
+        }
+    }
+
+    /// Gets the position in the file from the start of the file.
+    pub fn position(&self) -> Result<u64, Error> {
+        match unsafe { bindings::ftell(self.0) } {
+            -1 => Err(from_errno()),
+            x => Ok(x as u64),
+        }
+    }
+}
+
+impl fmt::Write for File {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        unsafe { bindings::fclose(self.0) };
+    }
+}
+
+/// Represents a position in a file, for [`File::seek()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// A number of bytes from the start of the file.
+    Start(u64),
+    /// A number of bytes from the end of the file.
+    End(i64),
+    /// A number of bytes from the current position.
+    Current(i64),
+}
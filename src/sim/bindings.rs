@@ -0,0 +1,2145 @@
+//! Host implementation of the subset of the PROS API used by this crate.
+//!
+//! Names, types and constants mirror the bindgen-generated bindings, so that
+//! the rest of the crate is agnostic to which one is in use.
+
+#![allow(clippy::missing_safety_doc)]
+#![allow(non_upper_case_globals)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+use core::{cell::Cell, ffi::CStr};
+use libc::{c_char, c_int, c_uint, c_void};
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{Read, Seek, SeekFrom, Write},
+    ptr, slice,
+};
+
+use super::{
+    devices::{self, Devices, ImuState, LINK_BUFFER_SIZE, SERIAL_BUFFER_SIZE},
+    kernel,
+    motor::{MotorCommand, MotorState},
+    Command,
+};
+use crate::smart_port::DeviceType;
+
+pub const COMPETITION_DISABLED: u32 = 1;
+pub const COMPETITION_AUTONOMOUS: u32 = 2;
+pub const COMPETITION_CONNECTED: u32 = 4;
+pub const COMPETITION_SYSTEM: u32 = 8;
+pub const INTERNAL_ADI_PORT: u32 = 22;
+pub const LCD_BTN_CENTER: u32 = 2;
+pub const LCD_BTN_LEFT: u32 = 4;
+pub const LCD_BTN_RIGHT: u32 = 1;
+pub const PROS_ERR_: i32 = i32::MAX;
+pub const PROS_ERR_F_: f64 = f64::INFINITY;
+pub const SEEK_SET: u32 = 0;
+pub const SEEK_CUR: u32 = 1;
+pub const SEEK_END: u32 = 2;
+pub const TASK_PRIORITY_DEFAULT: u32 = 8;
+pub const TASK_PRIORITY_MAX: u32 = 16;
+pub const TASK_STACK_DEPTH_DEFAULT: u32 = 8192;
+pub const VISION_OBJECT_ERR_SIG: u32 = 255;
+
+pub type task_t = *mut c_void;
+pub type task_fn_t = Option<unsafe extern "C" fn(arg1: *mut c_void)>;
+pub type mutex_t = *mut c_void;
+pub type sem_t = *mut c_void;
+pub type FILE = c_void;
+
+pub type task_state_e_t = c_uint;
+pub const task_state_e_t_E_TASK_STATE_RUNNING: task_state_e_t = 0;
+pub const task_state_e_t_E_TASK_STATE_READY: task_state_e_t = 1;
+pub const task_state_e_t_E_TASK_STATE_BLOCKED: task_state_e_t = 2;
+pub const task_state_e_t_E_TASK_STATE_SUSPENDED: task_state_e_t = 3;
+pub const task_state_e_t_E_TASK_STATE_DELETED: task_state_e_t = 4;
+pub const task_state_e_t_E_TASK_STATE_INVALID: task_state_e_t = 5;
+
+pub type v5_device_e = c_uint;
+pub type v5_device_e_t = v5_device_e;
+pub const v5_device_e_E_DEVICE_NONE: v5_device_e = 0;
+pub const v5_device_e_E_DEVICE_MOTOR: v5_device_e = 2;
+pub const v5_device_e_E_DEVICE_ROTATION: v5_device_e = 4;
+pub const v5_device_e_E_DEVICE_IMU: v5_device_e = 6;
+pub const v5_device_e_E_DEVICE_DISTANCE: v5_device_e = 7;
+pub const v5_device_e_E_DEVICE_RADIO: v5_device_e = 8;
+pub const v5_device_e_E_DEVICE_VISION: v5_device_e = 11;
+pub const v5_device_e_E_DEVICE_ADI: v5_device_e = 12;
+pub const v5_device_e_E_DEVICE_OPTICAL: v5_device_e = 16;
+pub const v5_device_e_E_DEVICE_GPS: v5_device_e = 20;
+pub const v5_device_e_E_DEVICE_GENERIC: v5_device_e = 129;
+pub const v5_device_e_E_DEVICE_UNDEFINED: v5_device_e = 255;
+
+pub type motor_brake_mode_e = c_uint;
+pub type motor_brake_mode_e_t = motor_brake_mode_e;
+pub const motor_brake_mode_e_E_MOTOR_BRAKE_COAST: motor_brake_mode_e = 0;
+pub const motor_brake_mode_e_E_MOTOR_BRAKE_BRAKE: motor_brake_mode_e = 1;
+pub const motor_brake_mode_e_E_MOTOR_BRAKE_HOLD: motor_brake_mode_e = 2;
+pub const motor_brake_mode_e_E_MOTOR_BRAKE_INVALID: motor_brake_mode_e = 2147483647;
+
+pub type motor_encoder_units_e = c_uint;
+pub type motor_encoder_units_e_t = motor_encoder_units_e;
+pub const motor_encoder_units_e_E_MOTOR_ENCODER_DEGREES: motor_encoder_units_e = 0;
+pub const motor_encoder_units_e_E_MOTOR_ENCODER_ROTATIONS: motor_encoder_units_e = 1;
+pub const motor_encoder_units_e_E_MOTOR_ENCODER_COUNTS: motor_encoder_units_e = 2;
+pub const motor_encoder_units_e_E_MOTOR_ENCODER_INVALID: motor_encoder_units_e = 2147483647;
+
+pub type motor_gearset_e = c_uint;
+pub type motor_gearset_e_t = motor_gearset_e;
+pub const motor_gearset_e_E_MOTOR_GEARSET_36: motor_gearset_e = 0;
+pub const motor_gearset_e_E_MOTOR_GEARSET_18: motor_gearset_e = 1;
+pub const motor_gearset_e_E_MOTOR_GEARSET_06: motor_gearset_e = 2;
+pub const motor_gearset_e_E_MOTOR_GEARSET_INVALID: motor_gearset_e = 2147483647;
+
+pub type controller_id_e_t = c_uint;
+pub const controller_id_e_t_E_CONTROLLER_MASTER: controller_id_e_t = 0;
+pub const controller_id_e_t_E_CONTROLLER_PARTNER: controller_id_e_t = 1;
+
+pub type controller_analog_e_t = c_uint;
+pub const controller_analog_e_t_E_CONTROLLER_ANALOG_LEFT_X: controller_analog_e_t = 0;
+pub const controller_analog_e_t_E_CONTROLLER_ANALOG_LEFT_Y: controller_analog_e_t = 1;
+pub const controller_analog_e_t_E_CONTROLLER_ANALOG_RIGHT_X: controller_analog_e_t = 2;
+pub const controller_analog_e_t_E_CONTROLLER_ANALOG_RIGHT_Y: controller_analog_e_t = 3;
+
+pub type controller_digital_e_t = c_uint;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_L1: controller_digital_e_t = 6;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_L2: controller_digital_e_t = 7;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_R1: controller_digital_e_t = 8;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_R2: controller_digital_e_t = 9;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_UP: controller_digital_e_t = 10;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_DOWN: controller_digital_e_t = 11;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_LEFT: controller_digital_e_t = 12;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_RIGHT: controller_digital_e_t = 13;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_X: controller_digital_e_t = 14;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_B: controller_digital_e_t = 15;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_Y: controller_digital_e_t = 16;
+pub const controller_digital_e_t_E_CONTROLLER_DIGITAL_A: controller_digital_e_t = 17;
+
+pub type adi_port_config_e = c_uint;
+pub type adi_port_config_e_t = adi_port_config_e;
+pub const adi_port_config_e_E_ADI_ANALOG_IN: adi_port_config_e = 0;
+pub const adi_port_config_e_E_ADI_ANALOG_OUT: adi_port_config_e = 1;
+pub const adi_port_config_e_E_ADI_DIGITAL_IN: adi_port_config_e = 2;
+pub const adi_port_config_e_E_ADI_DIGITAL_OUT: adi_port_config_e = 3;
+pub const adi_port_config_e_E_ADI_LEGACY_GYRO: adi_port_config_e = 10;
+pub const adi_port_config_e_E_ADI_LEGACY_SERVO: adi_port_config_e = 12;
+pub const adi_port_config_e_E_ADI_LEGACY_PWM: adi_port_config_e = 13;
+pub const adi_port_config_e_E_ADI_LEGACY_ENCODER: adi_port_config_e = 14;
+pub const adi_port_config_e_E_ADI_LEGACY_ULTRASONIC: adi_port_config_e = 15;
+pub const adi_port_config_e_E_ADI_TYPE_UNDEFINED: adi_port_config_e = 255;
+
+pub type ext_adi_encoder_t = i32;
+pub type ext_adi_gyro_t = i32;
+pub type ext_adi_ultrasonic_t = i32;
+
+pub type imu_status_e = c_uint;
+pub type imu_status_e_t = imu_status_e;
+pub const imu_status_e_E_IMU_STATUS_CALIBRATING: imu_status_e = 1;
+pub const imu_status_e_E_IMU_STATUS_ERROR: imu_status_e = 255;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct quaternion_s {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub w: f64,
+}
+pub type quaternion_s_t = quaternion_s;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct imu_raw_s {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+pub type imu_gyro_s_t = imu_raw_s;
+pub type imu_accel_s_t = imu_raw_s;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct euler_s {
+    pub pitch: f64,
+    pub roll: f64,
+    pub yaw: f64,
+}
+pub type euler_s_t = euler_s;
+
+#[repr(C, packed)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct gps_status_s {
+    pub x: f64,
+    pub y: f64,
+    pub pitch: f64,
+    pub roll: f64,
+    pub yaw: f64,
+}
+pub type gps_status_s_t = gps_status_s;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct gps_raw_s {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+pub type gps_accel_s_t = gps_raw_s;
+pub type gps_gyro_s_t = gps_raw_s;
+
+pub type link_type_e = c_uint;
+pub type link_type_e_t = link_type_e;
+pub const link_type_e_E_LINK_RECIEVER: link_type_e = 0;
+pub const link_type_e_E_LINK_TRANSMITTER: link_type_e = 1;
+pub const link_type_e_E_LINK_RX: link_type_e = 0;
+pub const link_type_e_E_LINK_TX: link_type_e = 1;
+
+pub type optical_direction_e = c_uint;
+pub type optical_direction_e_t = optical_direction_e;
+pub const optical_direction_e_NO_GESTURE: optical_direction_e = 0;
+pub const optical_direction_e_UP: optical_direction_e = 1;
+pub const optical_direction_e_DOWN: optical_direction_e = 2;
+pub const optical_direction_e_RIGHT: optical_direction_e = 3;
+pub const optical_direction_e_LEFT: optical_direction_e = 4;
+pub const optical_direction_e_ERROR: optical_direction_e = PROS_ERR_ as optical_direction_e;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct optical_rgb_s {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    pub brightness: f64,
+}
+pub type optical_rgb_s_t = optical_rgb_s;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct optical_raw_s {
+    pub clear: u32,
+    pub red: u32,
+    pub green: u32,
+    pub blue: u32,
+}
+pub type optical_raw_s_t = optical_raw_s;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct optical_gesture_s {
+    pub udata: u8,
+    pub ddata: u8,
+    pub ldata: u8,
+    pub rdata: u8,
+    pub type_: u8,
+    pub pad: u8,
+    pub count: u16,
+    pub time: u32,
+}
+pub type optical_gesture_s_t = optical_gesture_s;
+
+pub type vision_object_type = c_uint;
+pub type vision_object_type_e_t = vision_object_type;
+pub const vision_object_type_E_VISION_OBJECT_NORMAL: vision_object_type = 0;
+pub const vision_object_type_E_VISION_OBJECT_COLOR_CODE: vision_object_type = 1;
+pub const vision_object_type_E_VISION_OBJECT_LINE: vision_object_type = 2;
+
+pub type vision_zero = c_uint;
+pub type vision_zero_e_t = vision_zero;
+pub const vision_zero_E_VISION_ZERO_TOPLEFT: vision_zero = 0;
+pub const vision_zero_E_VISION_ZERO_CENTER: vision_zero = 1;
+
+pub type vision_color_code_t = u16;
+
+#[repr(C, packed)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct vision_signature {
+    pub id: u8,
+    pub _pad: [u8; 3],
+    pub range: f32,
+    pub u_min: i32,
+    pub u_max: i32,
+    pub u_mean: i32,
+    pub v_min: i32,
+    pub v_max: i32,
+    pub v_mean: i32,
+    pub rgb: u32,
+    pub type_: u32,
+}
+pub type vision_signature_s_t = vision_signature;
+
+#[repr(C, packed)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct vision_object {
+    pub signature: u16,
+    pub type_: vision_object_type_e_t,
+    pub left_coord: i16,
+    pub top_coord: i16,
+    pub width: i16,
+    pub height: i16,
+    pub angle: u16,
+    pub x_middle_coord: i16,
+    pub y_middle_coord: i16,
+}
+pub type vision_object_s_t = vision_object;
+
+pub type text_format_e_t = c_uint;
+pub const text_format_e_t_E_TEXT_SMALL: text_format_e_t = 0;
+pub const text_format_e_t_E_TEXT_MEDIUM: text_format_e_t = 1;
+pub const text_format_e_t_E_TEXT_LARGE: text_format_e_t = 2;
+pub const text_format_e_t_E_TEXT_MEDIUM_CENTER: text_format_e_t = 3;
+pub const text_format_e_t_E_TEXT_LARGE_CENTER: text_format_e_t = 4;
+
+pub type last_touch_e_t = c_uint;
+pub const last_touch_e_t_E_TOUCH_RELEASED: last_touch_e_t = 0;
+pub const last_touch_e_t_E_TOUCH_PRESSED: last_touch_e_t = 1;
+pub const last_touch_e_t_E_TOUCH_HELD: last_touch_e_t = 2;
+pub const last_touch_e_t_E_TOUCH_ERROR: last_touch_e_t = 3;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct screen_touch_status_s {
+    pub touch_status: last_touch_e_t,
+    pub x: i16,
+    pub y: i16,
+    pub press_count: i32,
+    pub release_count: i32,
+}
+pub type screen_touch_status_s_t = screen_touch_status_s;
+
+thread_local! {
+    static ERRNO: Cell<i32> = Cell::new(0);
+}
+
+pub unsafe fn __errno() -> *mut i32 {
+    ERRNO.with(Cell::as_ptr)
+}
+
+fn set_errno(errno: i32) {
+    ERRNO.with(|e| e.set(errno));
+}
+
+/// Runs `f` on the global device state, translating an error into `errno` and
+/// the sentinel value `err`.
+fn with_devices<T>(err: T, f: impl FnOnce(&mut Devices) -> Result<T, i32>) -> T {
+    let now = kernel::now();
+    let mut devices = devices::lock();
+    devices.run_hooks(now);
+    let result = f(&mut devices);
+    result.unwrap_or_else(|errno| {
+        set_errno(errno);
+        err
+    })
+}
+
+fn handle(id: usize) -> *mut c_void {
+    id as *mut c_void
+}
+
+fn id(handle: *mut c_void) -> usize {
+    handle as usize
+}
+
+// Tasks
+
+pub unsafe fn micros() -> u64 {
+    kernel::now()
+}
+
+pub unsafe fn task_create(
+    function: task_fn_t,
+    parameters: *mut c_void,
+    prio: u32,
+    _stack_depth: u16,
+    name: *const c_char,
+) -> task_t {
+    let Some(function) = function else {
+        set_errno(libc::EINVAL);
+        return core::ptr::null_mut();
+    };
+    let name = if name.is_null() {
+        ""
+    } else {
+        CStr::from_ptr(name).to_str().unwrap_or("")
+    };
+    let parameters = parameters as usize;
+    match kernel::spawn(
+        name,
+        prio,
+        Box::new(move || function(parameters as *mut c_void)),
+    ) {
+        Some(id) => handle(id),
+        None => {
+            set_errno(libc::ENOMEM);
+            core::ptr::null_mut()
+        }
+    }
+}
+
+pub unsafe fn task_delay(milliseconds: u32) {
+    kernel::delay(milliseconds)
+}
+
+pub unsafe fn task_delete(task: task_t) {
+    kernel::delete(if task.is_null() {
+        kernel::current()
+    } else {
+        id(task)
+    })
+}
+
+pub unsafe fn task_get_by_name(name: *const c_char) -> task_t {
+    CStr::from_ptr(name)
+        .to_str()
+        .ok()
+        .and_then(kernel::find)
+        .map_or(core::ptr::null_mut(), handle)
+}
+
+pub unsafe fn task_get_current() -> task_t {
+    handle(kernel::current())
+}
+
+pub unsafe fn task_get_name(task: task_t) -> *mut c_char {
+    kernel::name(id(task)).map_or(core::ptr::null_mut(), |name| name as *mut c_char)
+}
+
+pub unsafe fn task_get_priority(task: task_t) -> u32 {
+    kernel::priority(id(task)).unwrap_or(0)
+}
+
+pub unsafe fn task_get_state(task: task_t) -> task_state_e_t {
+    match kernel::status(id(task)) {
+        kernel::TaskStatus::Running => task_state_e_t_E_TASK_STATE_RUNNING,
+        kernel::TaskStatus::Ready => task_state_e_t_E_TASK_STATE_READY,
+        kernel::TaskStatus::Blocked => task_state_e_t_E_TASK_STATE_BLOCKED,
+        kernel::TaskStatus::Suspended => task_state_e_t_E_TASK_STATE_SUSPENDED,
+        kernel::TaskStatus::Deleted => task_state_e_t_E_TASK_STATE_DELETED,
+        kernel::TaskStatus::Invalid => task_state_e_t_E_TASK_STATE_INVALID,
+    }
+}
+
+pub unsafe fn task_notify(task: task_t) -> u32 {
+    kernel::notify(id(task))
+}
+
+pub unsafe fn task_notify_take(clear_on_exit: bool, timeout: u32) -> u32 {
+    kernel::notify_take(clear_on_exit, timeout)
+}
+
+// Mutexes and semaphores
+
+pub unsafe fn mutex_recursive_create() -> mutex_t {
+    handle(kernel::mutex_create())
+}
+
+pub unsafe fn mutex_delete(mutex: mutex_t) {
+    kernel::mutex_delete(id(mutex))
+}
+
+pub unsafe fn mutex_recursive_give(mutex: mutex_t) -> bool {
+    kernel::mutex_give(id(mutex))
+}
+
+pub unsafe fn mutex_recursive_take(mutex: mutex_t, timeout: u32) -> bool {
+    kernel::mutex_take(id(mutex), timeout)
+}
+
+pub unsafe fn sem_create(max_count: u32, init_count: u32) -> sem_t {
+    handle(kernel::sem_create(max_count, init_count))
+}
+
+pub unsafe fn sem_delete(sem: sem_t) {
+    kernel::sem_delete(id(sem))
+}
+
+pub unsafe fn sem_get_count(sem: sem_t) -> u32 {
+    kernel::sem_count(id(sem))
+}
+
+pub unsafe fn sem_post(sem: sem_t) -> bool {
+    kernel::sem_post(id(sem))
+}
+
+pub unsafe fn sem_wait(sem: sem_t, timeout: u32) -> bool {
+    kernel::sem_wait(id(sem), timeout)
+}
+
+// Registry
+
+pub unsafe fn registry_get_plugged_type(port: u8) -> v5_device_e_t {
+    match devices::lock().plugged_type(port) {
+        DeviceType::None => v5_device_e_E_DEVICE_NONE,
+        DeviceType::Motor => v5_device_e_E_DEVICE_MOTOR,
+        DeviceType::Rotation => v5_device_e_E_DEVICE_ROTATION,
+        DeviceType::Imu => v5_device_e_E_DEVICE_IMU,
+        DeviceType::Distance => v5_device_e_E_DEVICE_DISTANCE,
+        DeviceType::Radio => v5_device_e_E_DEVICE_RADIO,
+        DeviceType::Vision => v5_device_e_E_DEVICE_VISION,
+        DeviceType::Adi => v5_device_e_E_DEVICE_ADI,
+        DeviceType::Optical => v5_device_e_E_DEVICE_OPTICAL,
+        DeviceType::Gps => v5_device_e_E_DEVICE_GPS,
+        DeviceType::Serial => v5_device_e_E_DEVICE_GENERIC,
+        DeviceType::Undefined => v5_device_e_E_DEVICE_UNDEFINED,
+        DeviceType::Unknown(x) => x,
+    }
+}
+
+// Battery
+
+pub unsafe fn battery_get_capacity() -> f64 {
+    devices::lock().battery.capacity
+}
+
+pub unsafe fn battery_get_current() -> i32 {
+    devices::lock().battery.current
+}
+
+pub unsafe fn battery_get_temperature() -> f64 {
+    devices::lock().battery.temperature
+}
+
+pub unsafe fn battery_get_voltage() -> i32 {
+    devices::lock().battery.voltage
+}
+
+// Competition
+
+pub unsafe fn competition_get_status() -> u8 {
+    devices::lock().competition
+}
+
+// Brain screen
+// Drawing is not simulated; only printed text is recorded.
+
+pub unsafe fn screen_set_pen(color: u32) -> u32 {
+    devices::lock().screen.pen = color;
+    1
+}
+
+pub unsafe fn screen_get_pen() -> u32 {
+    devices::lock().screen.pen
+}
+
+pub unsafe fn screen_set_eraser(color: u32) -> u32 {
+    devices::lock().screen.eraser = color;
+    1
+}
+
+pub unsafe fn screen_get_eraser() -> u32 {
+    devices::lock().screen.eraser
+}
+
+pub unsafe fn screen_erase() -> u32 {
+    1
+}
+
+pub unsafe fn screen_draw_pixel(_x: i16, _y: i16) -> u32 {
+    1
+}
+
+pub unsafe fn screen_draw_line(_x0: i16, _y0: i16, _x1: i16, _y1: i16) -> u32 {
+    1
+}
+
+pub unsafe fn screen_draw_rect(_x0: i16, _y0: i16, _x1: i16, _y1: i16) -> u32 {
+    1
+}
+
+pub unsafe fn screen_fill_rect(_x0: i16, _y0: i16, _x1: i16, _y1: i16) -> u32 {
+    1
+}
+
+pub unsafe fn screen_draw_circle(_x: i16, _y: i16, _radius: i16) -> u32 {
+    1
+}
+
+pub unsafe fn screen_fill_circle(_x: i16, _y: i16, _radius: i16) -> u32 {
+    1
+}
+
+// The real functions are variadic; the crate only ever passes a `"%s"` format
+// with a single string argument.
+
+pub unsafe fn screen_print(
+    _txt_fmt: text_format_e_t,
+    _line: i16,
+    _text: *const c_char,
+    arg: *const c_char,
+) -> u32 {
+    let text = CStr::from_ptr(arg).to_string_lossy().into_owned();
+    devices::lock().screen.text.push(text);
+    1
+}
+
+pub unsafe fn screen_print_at(
+    _txt_fmt: text_format_e_t,
+    _x: i16,
+    _y: i16,
+    _text: *const c_char,
+    arg: *const c_char,
+) -> u32 {
+    let text = CStr::from_ptr(arg).to_string_lossy().into_owned();
+    devices::lock().screen.text.push(text);
+    1
+}
+
+pub unsafe fn screen_touch_status() -> screen_touch_status_s_t {
+    with_devices(Default::default(), |d| Ok(d.screen.touch))
+}
+
+// LLEMU
+
+pub unsafe fn lcd_initialize() -> bool {
+    devices::lock().initialize_lcd();
+    true
+}
+
+pub unsafe fn lcd_set_text(line: i16, text: *const c_char) -> bool {
+    let text = CStr::from_ptr(text).to_string_lossy().into_owned();
+    with_devices(false, |d| {
+        *d.lcd()?.lines.get_mut(line as usize).ok_or(libc::EINVAL)? = text;
+        Ok(true)
+    })
+}
+
+pub unsafe fn lcd_read_buttons() -> u8 {
+    with_devices(PROS_ERR_ as u8, |d| Ok(d.lcd()?.buttons))
+}
+
+// SD card
+
+pub unsafe fn usd_is_installed() -> i32 {
+    devices::lock().sd.root.is_some() as i32
+}
+
+pub unsafe fn fopen(path: *const c_char, mode: *const c_char) -> *mut FILE {
+    let path = CStr::from_ptr(path).to_string_lossy().into_owned();
+    let mode = CStr::from_ptr(mode).to_bytes();
+    with_devices(ptr::null_mut(), |d| Ok(handle(d.sd.open(&path, mode)?)))
+}
+
+pub unsafe fn fclose(stream: *mut FILE) -> c_int {
+    with_devices(-1, |d| d.sd.close(id(stream)).map(|_| 0))
+}
+
+pub unsafe fn fread(ptr: *mut c_void, size: usize, n: usize, stream: *mut FILE) -> usize {
+    let buffer = slice::from_raw_parts_mut(ptr as *mut u8, size * n);
+    with_devices(0, |d| {
+        let file = d.sd.file(id(stream))?;
+        let mut total = 0;
+        while total < buffer.len() {
+            match file.file.read(&mut buffer[total..]) {
+                Ok(0) => break,
+                Ok(count) => total += count,
+                Err(err) => {
+                    file.error = true;
+                    return Err(err.raw_os_error().unwrap_or(libc::EIO));
+                }
+            }
+        }
+        Ok(total.checked_div(size).unwrap_or(0))
+    })
+}
+
+pub unsafe fn fwrite(ptr: *const c_void, size: usize, n: usize, stream: *mut FILE) -> usize {
+    let buffer = slice::from_raw_parts(ptr as *const u8, size * n);
+    with_devices(0, |d| {
+        let file = d.sd.file(id(stream))?;
+        file.file.write_all(buffer).map_err(|err| {
+            file.error = true;
+            err.raw_os_error().unwrap_or(libc::EIO)
+        })?;
+        Ok(n)
+    })
+}
+
+pub unsafe fn ferror(stream: *mut FILE) -> c_int {
+    with_devices(1, |d| Ok(d.sd.file(id(stream))?.error as c_int))
+}
+
+pub unsafe fn fflush(stream: *mut FILE) -> c_int {
+    with_devices(-1, |d| {
+        d.sd.file(id(stream))?
+            .file
+            .flush()
+            .map_err(|err| err.raw_os_error().unwrap_or(libc::EIO))?;
+        Ok(0)
+    })
+}
+
+// `long` is 32 bits on the V5.
+
+pub unsafe fn fseek(stream: *mut FILE, offset: i32, whence: c_int) -> c_int {
+    let pos = match whence as u32 {
+        SEEK_SET => SeekFrom::Start(offset as u64),
+        SEEK_CUR => SeekFrom::Current(offset as i64),
+        SEEK_END => SeekFrom::End(offset as i64),
+        _ => {
+            set_errno(libc::EINVAL);
+            return -1;
+        }
+    };
+    with_devices(-1, |d| {
+        d.sd.file(id(stream))?
+            .file
+            .seek(pos)
+            .map_err(|err| err.raw_os_error().unwrap_or(libc::EIO))?;
+        Ok(0)
+    })
+}
+
+pub unsafe fn ftell(stream: *mut FILE) -> i32 {
+    with_devices(-1, |d| {
+        let position =
+            d.sd.file(id(stream))?
+                .file
+                .stream_position()
+                .map_err(|err| err.raw_os_error().unwrap_or(libc::EIO))?;
+        position.try_into().map_err(|_| libc::EOVERFLOW)
+    })
+}
+
+/// Returns a FatFs result code: 0 for success, 3 if there is no SD card, or 5
+/// if the directory does not exist.
+pub unsafe fn vexFileDirectoryGet(path: *const c_char, buffer: *mut c_char, len: u32) -> u32 {
+    let path = CStr::from_ptr(path).to_string_lossy().into_owned();
+    let root = match &devices::lock().sd.root {
+        Some(root) => root.join(path.trim_start_matches('/')),
+        None => return 3,
+    };
+    let mut names: Vec<String> = match fs::read_dir(root) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect(),
+        Err(_) => return 5,
+    };
+    names.sort();
+    let list = names.join("\n");
+    let buffer = slice::from_raw_parts_mut(buffer as *mut u8, len as usize);
+    let count = list.len().min(buffer.len().saturating_sub(1));
+    buffer[..count].copy_from_slice(&list.as_bytes()[..count]);
+    if let Some(end) = buffer.get_mut(count) {
+        *end = 0;
+    }
+    0
+}
+
+// Controllers
+
+pub unsafe fn controller_is_connected(id: controller_id_e_t) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.controller(id)?.is_connected() as i32))
+}
+
+pub unsafe fn controller_get_analog(id: controller_id_e_t, channel: controller_analog_e_t) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let controller = d.controller(id)?;
+        let value = *controller
+            .analog
+            .get(channel as usize)
+            .ok_or(libc::EINVAL)?;
+        Ok(if controller.is_connected() { value } else { 0 })
+    })
+}
+
+pub unsafe fn controller_get_digital(id: controller_id_e_t, button: controller_digital_e_t) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let controller = d.controller(id)?;
+        let index = (button as usize)
+            .checked_sub(controller_digital_e_t_E_CONTROLLER_DIGITAL_L1 as usize)
+            .ok_or(libc::EINVAL)?;
+        let value = *controller.digital.get(index).ok_or(libc::EINVAL)?;
+        Ok((controller.is_connected() && value) as i32)
+    })
+}
+
+pub unsafe fn controller_get_battery_capacity(id: controller_id_e_t) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let controller = d.controller(id)?;
+        Ok(if controller.is_connected() {
+            controller.battery_capacity
+        } else {
+            0
+        })
+    })
+}
+
+pub unsafe fn controller_get_battery_level(id: controller_id_e_t) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let controller = d.controller(id)?;
+        Ok(if controller.is_connected() {
+            controller.battery_level
+        } else {
+            0
+        })
+    })
+}
+
+pub unsafe fn controller_clear(id: controller_id_e_t) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.controller(id)?.text = [[b' '; 19]; 3];
+        Ok(1)
+    })
+}
+
+pub unsafe fn controller_clear_line(id: controller_id_e_t, line: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let row = d
+            .controller(id)?
+            .text
+            .get_mut(line as usize)
+            .ok_or(libc::EINVAL)?;
+        *row = [b' '; 19];
+        Ok(1)
+    })
+}
+
+pub unsafe fn controller_set_text(
+    id: controller_id_e_t,
+    line: u8,
+    col: u8,
+    str_: *const c_char,
+) -> i32 {
+    let text = CStr::from_ptr(str_).to_bytes();
+    with_devices(PROS_ERR_, |d| {
+        let row = d
+            .controller(id)?
+            .text
+            .get_mut(line as usize)
+            .ok_or(libc::EINVAL)?;
+        let start = (col as usize).min(row.len());
+        for (c, b) in row[start..].iter_mut().zip(text) {
+            *c = *b;
+        }
+        Ok(1)
+    })
+}
+
+pub unsafe fn controller_rumble(id: controller_id_e_t, rumble_pattern: *const c_char) -> i32 {
+    let pattern = CStr::from_ptr(rumble_pattern).to_bytes().to_vec();
+    with_devices(PROS_ERR_, |d| {
+        d.controller(id)?.rumble = pattern;
+        Ok(1)
+    })
+}
+
+// Motors
+
+/// Gets a motor, with its model advanced to time `now`.
+fn motor(d: &mut Devices, port: u8, now: u64) -> Result<&mut MotorState, i32> {
+    let motor = d.motor(port)?;
+    motor.update(now);
+    Ok(motor)
+}
+
+/// Runs `f` on a motor, with its model advanced to the current time.
+fn with_motor<T>(err: T, port: u8, f: impl FnOnce(&mut MotorState) -> T) -> T {
+    let now = kernel::now();
+    with_devices(err, |d| Ok(f(motor(d, port, now)?)))
+}
+
+/// Gives a motor a new command with `f`, and records it.
+fn command_motor(port: u8, command: Command, f: impl FnOnce(&mut MotorState)) -> i32 {
+    let now = kernel::now();
+    with_devices(PROS_ERR_, |d| {
+        f(motor(d, port, now)?);
+        d.record(port, command);
+        Ok(1)
+    })
+}
+
+pub unsafe fn motor_move(port: u8, voltage: i32) -> i32 {
+    motor_move_voltage(port, voltage.clamp(-127, 127) * 12000 / 127)
+}
+
+pub unsafe fn motor_move_voltage(port: u8, voltage: i32) -> i32 {
+    command_motor(port, Command::MotorVoltage(voltage), |motor| {
+        motor.command = MotorCommand::Voltage(motor.sign() as i32 * voltage.clamp(-12000, 12000));
+    })
+}
+
+pub unsafe fn motor_move_velocity(port: u8, velocity: i32) -> i32 {
+    command_motor(port, Command::MotorVelocity(velocity), |motor| {
+        motor.command = MotorCommand::Velocity(motor.sign() as i32 * velocity);
+    })
+}
+
+pub unsafe fn motor_move_absolute(port: u8, position: f64, velocity: i32) -> i32 {
+    command_motor(port, Command::MotorAbsolute(position, velocity), |motor| {
+        motor.command = MotorCommand::Position(motor.to_degrees(position), velocity.abs());
+    })
+}
+
+pub unsafe fn motor_move_relative(port: u8, position: f64, velocity: i32) -> i32 {
+    command_motor(port, Command::MotorRelative(position, velocity), |motor| {
+        let delta = motor.sign() * position / motor.units_per_degree();
+        motor.command = MotorCommand::Position(motor.position + delta, velocity.abs());
+    })
+}
+
+pub unsafe fn motor_modify_profiled_velocity(port: u8, velocity: i32) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        if let MotorCommand::Position(_, v) = &mut motor.command {
+            *v = velocity.abs();
+        }
+        1
+    })
+}
+
+pub unsafe fn motor_get_target_position(port: u8) -> f64 {
+    with_motor(PROS_ERR_F_, port, |motor| match motor.command {
+        MotorCommand::Position(target, _) => motor.from_degrees(target),
+        _ => 0.0,
+    })
+}
+
+pub unsafe fn motor_get_target_velocity(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| match motor.command {
+        MotorCommand::Velocity(v) => motor.sign() as i32 * v,
+        MotorCommand::Position(..) => 0,
+        MotorCommand::Voltage(_) => 0,
+    })
+}
+
+pub unsafe fn motor_get_actual_velocity(port: u8) -> f64 {
+    with_motor(PROS_ERR_F_, port, |motor| motor.sign() * motor.velocity)
+}
+
+pub unsafe fn motor_get_current_draw(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        (motor.current.abs() * 1000.0).round() as i32
+    })
+}
+
+pub unsafe fn motor_get_direction(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        if motor.sign() * motor.velocity < 0.0 {
+            -1
+        } else {
+            1
+        }
+    })
+}
+
+pub unsafe fn motor_get_efficiency(port: u8) -> f64 {
+    with_motor(PROS_ERR_F_, port, |motor| motor.efficiency())
+}
+
+pub unsafe fn motor_get_position(port: u8) -> f64 {
+    with_motor(PROS_ERR_F_, port, |motor| {
+        motor.from_degrees(motor.position)
+    })
+}
+
+pub unsafe fn motor_get_power(port: u8) -> f64 {
+    with_motor(PROS_ERR_F_, port, |motor| motor.power())
+}
+
+pub unsafe fn motor_get_temperature(port: u8) -> f64 {
+    with_motor(PROS_ERR_F_, port, |motor| motor.temperature.round())
+}
+
+pub unsafe fn motor_get_torque(port: u8) -> f64 {
+    with_motor(PROS_ERR_F_, port, |motor| motor.sign() * motor.torque())
+}
+
+pub unsafe fn motor_get_voltage(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        (motor.sign() * motor.voltage.unwrap_or(0.0) * 1000.0).round() as i32
+    })
+}
+
+pub unsafe fn motor_is_over_current(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| motor.over_current as i32)
+}
+
+pub unsafe fn motor_is_over_temp(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| motor.is_over_temp() as i32)
+}
+
+pub unsafe fn motor_get_brake_mode(port: u8) -> motor_brake_mode_e_t {
+    with_motor(motor_brake_mode_e_E_MOTOR_BRAKE_INVALID, port, |motor| {
+        motor.brake_mode
+    })
+}
+
+pub unsafe fn motor_get_current_limit(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| motor.current_limit)
+}
+
+pub unsafe fn motor_get_encoder_units(port: u8) -> motor_encoder_units_e_t {
+    with_motor(
+        motor_encoder_units_e_E_MOTOR_ENCODER_INVALID,
+        port,
+        |motor| motor.encoder_units,
+    )
+}
+
+pub unsafe fn motor_get_gearing(port: u8) -> motor_gearset_e_t {
+    with_motor(motor_gearset_e_E_MOTOR_GEARSET_INVALID, port, |motor| {
+        motor.gearset
+    })
+}
+
+pub unsafe fn motor_get_voltage_limit(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| motor.voltage_limit)
+}
+
+pub unsafe fn motor_is_reversed(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| motor.reversed as i32)
+}
+
+pub unsafe fn motor_set_brake_mode(port: u8, mode: motor_brake_mode_e_t) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        motor.brake_mode = mode;
+        1
+    })
+}
+
+pub unsafe fn motor_set_current_limit(port: u8, limit: i32) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        motor.current_limit = limit.clamp(0, 2500);
+        1
+    })
+}
+
+pub unsafe fn motor_set_encoder_units(port: u8, units: motor_encoder_units_e_t) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        motor.encoder_units = units;
+        1
+    })
+}
+
+pub unsafe fn motor_set_gearing(port: u8, gearset: motor_gearset_e_t) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        motor.gearset = gearset;
+        1
+    })
+}
+
+pub unsafe fn motor_set_reversed(port: u8, reverse: bool) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        motor.reversed = reverse;
+        1
+    })
+}
+
+pub unsafe fn motor_set_voltage_limit(port: u8, limit: i32) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        motor.voltage_limit = limit.clamp(0, 12000);
+        1
+    })
+}
+
+pub unsafe fn motor_set_zero_position(port: u8, position: f64) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        motor.zero += position / motor.units_per_degree();
+        1
+    })
+}
+
+pub unsafe fn motor_tare_position(port: u8) -> i32 {
+    with_motor(PROS_ERR_, port, |motor| {
+        motor.zero = motor.sign() * motor.position;
+        1
+    })
+}
+
+// Rotation sensors
+
+pub unsafe fn rotation_reset(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let sensor = d.rotation(port)?;
+        sensor.set_reported_position(sensor.reported_angle());
+        Ok(1)
+    })
+}
+
+pub unsafe fn rotation_set_position(port: u8, position: u32) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.rotation(port)?.set_reported_position(position as i32);
+        Ok(1)
+    })
+}
+
+pub unsafe fn rotation_reset_position(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.rotation(port)?.set_reported_position(0);
+        Ok(1)
+    })
+}
+
+pub unsafe fn rotation_get_position(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.rotation(port)?.reported_position()))
+}
+
+pub unsafe fn rotation_get_velocity(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.rotation(port)?.reported_velocity()))
+}
+
+pub unsafe fn rotation_get_angle(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.rotation(port)?.reported_angle()))
+}
+
+pub unsafe fn rotation_set_reversed(port: u8, value: bool) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let sensor = d.rotation(port)?;
+        let position = sensor.reported_position();
+        sensor.reversed = value;
+        sensor.set_reported_position(position);
+        Ok(1)
+    })
+}
+
+pub unsafe fn rotation_reverse(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let sensor = d.rotation(port)?;
+        let position = sensor.reported_position();
+        sensor.reversed = !sensor.reversed;
+        sensor.set_reported_position(position);
+        Ok(1)
+    })
+}
+
+pub unsafe fn rotation_get_reversed(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.rotation(port)?.reversed as i32))
+}
+
+// Inertial sensors
+
+/// Gets a calibrated inertial sensor, failing with `EAGAIN` during calibration.
+fn imu(d: &mut Devices, port: u8, now: u64) -> Result<&mut ImuState, i32> {
+    let imu = d.imu(port)?;
+    if imu.is_calibrating(now) {
+        Err(libc::EAGAIN)
+    } else {
+        Ok(imu)
+    }
+}
+
+macro_rules! imu_fns {
+    ($($get:ident / $tare:ident / $set:ident => $reported:ident, $offset:ident;)*) => {
+        $(
+            pub unsafe fn $get(port: u8) -> f64 {
+                let now = kernel::now();
+                with_devices(PROS_ERR_F_, |d| Ok(imu(d, port, now)?.$reported()))
+            }
+
+            pub unsafe fn $tare(port: u8) -> i32 {
+                $set(port, 0.0)
+            }
+
+            pub unsafe fn $set(port: u8, target: f64) -> i32 {
+                let now = kernel::now();
+                with_devices(PROS_ERR_, |d| {
+                    let imu = imu(d, port, now)?;
+                    imu.$offset += imu.$reported() - target;
+                    Ok(1)
+                })
+            }
+        )*
+    };
+}
+
+imu_fns! {
+    imu_get_rotation / imu_tare_rotation / imu_set_rotation => reported_rotation, rotation_offset;
+    imu_get_heading / imu_tare_heading / imu_set_heading => reported_heading, heading_offset;
+    imu_get_pitch / imu_tare_pitch / imu_set_pitch => reported_pitch, pitch_offset;
+    imu_get_roll / imu_tare_roll / imu_set_roll => reported_roll, roll_offset;
+    imu_get_yaw / imu_tare_yaw / imu_set_yaw => reported_yaw, yaw_offset;
+}
+
+pub unsafe fn imu_reset(port: u8) -> i32 {
+    let now = kernel::now();
+    with_devices(PROS_ERR_, |d| {
+        let imu = imu(d, port, now)?;
+        imu.calibrated_at = Some(now + devices::IMU_CALIBRATION_TIME);
+        Ok(1)
+    })
+}
+
+pub unsafe fn imu_get_status(port: u8) -> imu_status_e_t {
+    let now = kernel::now();
+    with_devices(imu_status_e_E_IMU_STATUS_ERROR, |d| {
+        Ok(if d.imu(port)?.is_calibrating(now) {
+            imu_status_e_E_IMU_STATUS_CALIBRATING
+        } else {
+            0
+        })
+    })
+}
+
+pub unsafe fn imu_get_quaternion(port: u8) -> quaternion_s_t {
+    let now = kernel::now();
+    let err = quaternion_s_t {
+        x: PROS_ERR_F_,
+        y: PROS_ERR_F_,
+        z: PROS_ERR_F_,
+        w: PROS_ERR_F_,
+    };
+    with_devices(err, |d| {
+        let [x, y, z, w] = imu(d, port, now)?.quaternion();
+        Ok(quaternion_s_t { x, y, z, w })
+    })
+}
+
+pub unsafe fn imu_get_euler(port: u8) -> euler_s_t {
+    let now = kernel::now();
+    let err = euler_s_t {
+        pitch: PROS_ERR_F_,
+        roll: PROS_ERR_F_,
+        yaw: PROS_ERR_F_,
+    };
+    with_devices(err, |d| {
+        let imu = imu(d, port, now)?;
+        Ok(euler_s_t {
+            pitch: imu.reported_pitch(),
+            roll: imu.reported_roll(),
+            yaw: imu.reported_yaw(),
+        })
+    })
+}
+
+pub unsafe fn imu_get_gyro_rate(port: u8) -> imu_gyro_s_t {
+    let now = kernel::now();
+    let err = imu_gyro_s_t {
+        x: PROS_ERR_F_,
+        y: PROS_ERR_F_,
+        z: PROS_ERR_F_,
+    };
+    with_devices(err, |d| {
+        let [x, y, z] = imu(d, port, now)?.gyro_rate;
+        Ok(imu_gyro_s_t { x, y, z })
+    })
+}
+
+pub unsafe fn imu_get_accel(port: u8) -> imu_accel_s_t {
+    let now = kernel::now();
+    let err = imu_accel_s_t {
+        x: PROS_ERR_F_,
+        y: PROS_ERR_F_,
+        z: PROS_ERR_F_,
+    };
+    with_devices(err, |d| {
+        let [x, y, z] = imu(d, port, now)?.accel;
+        Ok(imu_accel_s_t { x, y, z })
+    })
+}
+
+pub unsafe fn imu_tare_euler(port: u8) -> i32 {
+    imu_set_euler(
+        port,
+        euler_s_t {
+            pitch: 0.0,
+            roll: 0.0,
+            yaw: 0.0,
+        },
+    )
+}
+
+pub unsafe fn imu_tare(port: u8) -> i32 {
+    match imu_tare_euler(port) {
+        PROS_ERR_ => PROS_ERR_,
+        _ => {
+            imu_tare_heading(port);
+            imu_tare_rotation(port)
+        }
+    }
+}
+
+pub unsafe fn imu_set_euler(port: u8, target: euler_s_t) -> i32 {
+    match imu_set_pitch(port, target.pitch) {
+        PROS_ERR_ => PROS_ERR_,
+        _ => {
+            imu_set_roll(port, target.roll);
+            imu_set_yaw(port, target.yaw)
+        }
+    }
+}
+
+// Distance sensors
+
+pub unsafe fn distance_get(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.distance(port)?.distance))
+}
+
+pub unsafe fn distance_get_confidence(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.distance(port)?.confidence))
+}
+
+pub unsafe fn distance_get_object_size(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.distance(port)?.size))
+}
+
+pub unsafe fn distance_get_object_velocity(port: u8) -> f64 {
+    with_devices(PROS_ERR_F_, |d| Ok(d.distance(port)?.velocity))
+}
+
+// GPS sensors
+
+pub unsafe fn gps_initialize_full(
+    port: u8,
+    x_initial: f64,
+    y_initial: f64,
+    heading_initial: f64,
+    x_offset: f64,
+    y_offset: f64,
+) -> i32 {
+    match gps_set_offset(port, x_offset, y_offset) {
+        PROS_ERR_ => PROS_ERR_,
+        _ => gps_set_position(port, x_initial, y_initial, heading_initial),
+    }
+}
+
+pub unsafe fn gps_set_offset(port: u8, x_offset: f64, y_offset: f64) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.gps(port)?.offset = [x_offset, y_offset];
+        Ok(1)
+    })
+}
+
+pub unsafe fn gps_get_offset(port: u8, x_offset: *mut f64, y_offset: *mut f64) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let [x, y] = d.gps(port)?.offset;
+        *x_offset = x;
+        *y_offset = y;
+        Ok(1)
+    })
+}
+
+pub unsafe fn gps_set_position(
+    port: u8,
+    x_initial: f64,
+    y_initial: f64,
+    heading_initial: f64,
+) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let gps = d.gps(port)?;
+        gps.x = x_initial;
+        gps.y = y_initial;
+        gps.rotation = heading_initial;
+        Ok(1)
+    })
+}
+
+pub unsafe fn gps_set_data_rate(port: u8, _rate: u32) -> i32 {
+    with_devices(PROS_ERR_, |d| d.gps(port).map(|_| 1))
+}
+
+pub unsafe fn gps_get_error(port: u8) -> f64 {
+    with_devices(PROS_ERR_F_, |d| Ok(d.gps(port)?.error))
+}
+
+pub unsafe fn gps_get_status(port: u8) -> gps_status_s_t {
+    let err = gps_status_s_t {
+        x: PROS_ERR_F_,
+        y: PROS_ERR_F_,
+        pitch: PROS_ERR_F_,
+        roll: PROS_ERR_F_,
+        yaw: PROS_ERR_F_,
+    };
+    with_devices(err, |d| {
+        let gps = d.gps(port)?;
+        let heading = gps.heading();
+        Ok(gps_status_s_t {
+            x: gps.x,
+            y: gps.y,
+            pitch: 0.0,
+            roll: 0.0,
+            yaw: if heading > 180.0 {
+                heading - 360.0
+            } else {
+                heading
+            },
+        })
+    })
+}
+
+pub unsafe fn gps_get_heading(port: u8) -> f64 {
+    with_devices(PROS_ERR_F_, |d| Ok(d.gps(port)?.heading()))
+}
+
+pub unsafe fn gps_get_heading_raw(port: u8) -> f64 {
+    gps_get_heading(port)
+}
+
+pub unsafe fn gps_get_rotation(port: u8) -> f64 {
+    with_devices(PROS_ERR_F_, |d| {
+        let gps = d.gps(port)?;
+        Ok(gps.rotation - gps.rotation_offset)
+    })
+}
+
+pub unsafe fn gps_set_rotation(port: u8, target: f64) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let gps = d.gps(port)?;
+        gps.rotation_offset = gps.rotation - target;
+        Ok(1)
+    })
+}
+
+pub unsafe fn gps_tare_rotation(port: u8) -> i32 {
+    gps_set_rotation(port, 0.0)
+}
+
+pub unsafe fn gps_get_gyro_rate(port: u8) -> gps_gyro_s_t {
+    let err = gps_gyro_s_t {
+        x: PROS_ERR_F_,
+        y: PROS_ERR_F_,
+        z: PROS_ERR_F_,
+    };
+    with_devices(err, |d| {
+        let [x, y, z] = d.gps(port)?.gyro_rate;
+        Ok(gps_gyro_s_t { x, y, z })
+    })
+}
+
+pub unsafe fn gps_get_accel(port: u8) -> gps_accel_s_t {
+    let err = gps_accel_s_t {
+        x: PROS_ERR_F_,
+        y: PROS_ERR_F_,
+        z: PROS_ERR_F_,
+    };
+    with_devices(err, |d| {
+        let [x, y, z] = d.gps(port)?.accel;
+        Ok(gps_accel_s_t { x, y, z })
+    })
+}
+
+// VEXlink
+
+/// The first byte of every message sent with `link_transmit`.
+const LINK_START_BYTE: u8 = 0x33;
+
+pub unsafe fn link_init(port: u8, link_id: *const c_char, type_: link_type_e_t) -> u32 {
+    let id = CStr::from_ptr(link_id).to_string_lossy().into_owned();
+    with_devices(PROS_ERR_ as u32, |d| {
+        let radio = d.radio(port)?;
+        radio.link = Some((id, type_ == link_type_e_E_LINK_TX));
+        radio.rx.clear();
+        radio.tx.clear();
+        Ok(1)
+    })
+}
+
+pub unsafe fn link_connected(port: u8) -> bool {
+    with_devices(false, |d| d.link(port).map(|_| true))
+}
+
+pub unsafe fn link_raw_receivable_size(port: u8) -> u32 {
+    with_devices(PROS_ERR_ as u32, |d| Ok(d.link(port)?.rx.len() as u32))
+}
+
+pub unsafe fn link_raw_transmittable_size(port: u8) -> u32 {
+    with_devices(PROS_ERR_ as u32, |d| {
+        Ok((LINK_BUFFER_SIZE - d.link_destination(port)?.len()) as u32)
+    })
+}
+
+pub unsafe fn link_transmit_raw(port: u8, data: *mut c_void, data_size: u16) -> u32 {
+    let data = std::slice::from_raw_parts(data as *const u8, data_size as usize);
+    with_devices(PROS_ERR_ as u32, |d| {
+        link_send(d.link_destination(port)?, data)?;
+        Ok(data_size as u32)
+    })
+}
+
+pub unsafe fn link_receive_raw(port: u8, dest: *mut c_void, data_size: u16) -> u32 {
+    with_devices(PROS_ERR_ as u32, |d| {
+        let rx = &mut d.link(port)?.rx;
+        let n = rx.len().min(data_size as usize);
+        for (i, byte) in rx.drain(..n).enumerate() {
+            *(dest as *mut u8).add(i) = byte;
+        }
+        Ok(n as u32)
+    })
+}
+
+pub unsafe fn link_transmit(port: u8, data: *mut c_void, data_size: u16) -> u32 {
+    let data = std::slice::from_raw_parts(data as *const u8, data_size as usize);
+    let mut frame = vec![LINK_START_BYTE, data_size as u8, (data_size >> 8) as u8];
+    frame.extend_from_slice(data);
+    frame.push(frame.iter().fold(0, |c, b| c ^ b));
+    with_devices(PROS_ERR_ as u32, |d| {
+        link_send(d.link_destination(port)?, &frame)?;
+        Ok(data_size as u32)
+    })
+}
+
+pub unsafe fn link_receive(port: u8, dest: *mut c_void, data_size: u16) -> u32 {
+    if data_size as usize > LINK_BUFFER_SIZE {
+        set_errno(libc::EINVAL);
+        return PROS_ERR_ as u32;
+    }
+    with_devices(PROS_ERR_ as u32, |d| {
+        let rx = &mut d.link(port)?.rx;
+        if rx.len() < data_size as usize + 4 {
+            return Ok(0);
+        }
+        let header: Vec<u8> = rx.drain(..3).collect();
+        if header != [LINK_START_BYTE, data_size as u8, (data_size >> 8) as u8] {
+            return Err(libc::EBADMSG);
+        }
+        let mut checksum = header.iter().fold(0, |c, b| c ^ b);
+        for (i, byte) in rx.drain(..data_size as usize).enumerate() {
+            checksum ^= byte;
+            *(dest as *mut u8).add(i) = byte;
+        }
+        match rx.pop_front() {
+            Some(byte) if byte == checksum => Ok(data_size as u32),
+            _ => Err(libc::EBADMSG),
+        }
+    })
+}
+
+pub unsafe fn link_clear_receive_buf(port: u8) -> u32 {
+    with_devices(PROS_ERR_ as u32, |d| {
+        d.link(port)?.rx.clear();
+        Ok(1)
+    })
+}
+
+/// Appends bytes to a VEXlink buffer, failing with `EBUSY` if they do not fit.
+fn link_send(buffer: &mut VecDeque<u8>, data: &[u8]) -> Result<(), i32> {
+    if buffer.len() + data.len() > LINK_BUFFER_SIZE {
+        return Err(libc::EBUSY);
+    }
+    buffer.extend(data);
+    Ok(())
+}
+
+// Optical sensors
+
+pub unsafe fn optical_get_hue(port: u8) -> f64 {
+    with_devices(PROS_ERR_F_, |d| Ok(d.optical(port)?.hue))
+}
+
+pub unsafe fn optical_get_saturation(port: u8) -> f64 {
+    with_devices(PROS_ERR_F_, |d| Ok(d.optical(port)?.saturation))
+}
+
+pub unsafe fn optical_get_brightness(port: u8) -> f64 {
+    with_devices(PROS_ERR_F_, |d| Ok(d.optical(port)?.brightness))
+}
+
+pub unsafe fn optical_get_proximity(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.optical(port)?.proximity))
+}
+
+pub unsafe fn optical_set_led_pwm(port: u8, value: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.optical(port)?.led_pwm = value.min(100) as i32;
+        Ok(1)
+    })
+}
+
+pub unsafe fn optical_get_led_pwm(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.optical(port)?.led_pwm))
+}
+
+pub unsafe fn optical_get_rgb(port: u8) -> optical_rgb_s_t {
+    let err = optical_rgb_s_t {
+        red: PROS_ERR_F_,
+        green: PROS_ERR_F_,
+        blue: PROS_ERR_F_,
+        brightness: PROS_ERR_F_,
+    };
+    with_devices(err, |d| {
+        let sensor = d.optical(port)?;
+        let [red, green, blue] = sensor.rgb();
+        Ok(optical_rgb_s_t {
+            red,
+            green,
+            blue,
+            brightness: sensor.brightness,
+        })
+    })
+}
+
+pub unsafe fn optical_get_raw(port: u8) -> optical_raw_s_t {
+    let err = optical_raw_s_t {
+        clear: PROS_ERR_ as u32,
+        red: PROS_ERR_ as u32,
+        green: PROS_ERR_ as u32,
+        blue: PROS_ERR_ as u32,
+    };
+    with_devices(err, |d| {
+        let sensor = d.optical(port)?;
+        let [red, green, blue] = sensor.rgb().map(|c| (c * 257.0) as u32);
+        Ok(optical_raw_s_t {
+            clear: (sensor.brightness * 65535.0) as u32,
+            red,
+            green,
+            blue,
+        })
+    })
+}
+
+// No gestures are ever detected.
+
+pub unsafe fn optical_get_gesture(port: u8) -> optical_direction_e_t {
+    with_devices(optical_direction_e_ERROR, |d| {
+        d.optical(port)?;
+        Ok(optical_direction_e_NO_GESTURE)
+    })
+}
+
+pub unsafe fn optical_get_gesture_raw(port: u8) -> optical_gesture_s_t {
+    let err = optical_gesture_s_t {
+        time: PROS_ERR_ as u32,
+        ..Default::default()
+    };
+    with_devices(err, |d| {
+        d.optical(port)?;
+        Ok(Default::default())
+    })
+}
+
+pub unsafe fn optical_enable_gesture(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| d.optical(port).map(|_| 1))
+}
+
+pub unsafe fn optical_disable_gesture(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| d.optical(port).map(|_| 1))
+}
+
+// Vision sensors
+
+pub unsafe fn vision_get_signature(port: u8, signature_id: u8) -> vision_signature_s_t {
+    let err = vision_signature_s_t {
+        id: VISION_OBJECT_ERR_SIG as u8,
+        ..Default::default()
+    };
+    with_devices(err, |d| {
+        let sensor = d.vision(port)?;
+        match sensor
+            .signatures
+            .get((signature_id as usize).wrapping_sub(1))
+        {
+            Some(Some(signature)) => Ok(*signature),
+            _ => Err(libc::EINVAL),
+        }
+    })
+}
+
+pub unsafe fn vision_set_signature(
+    port: u8,
+    signature_id: u8,
+    signature_ptr: *mut vision_signature_s_t,
+) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let sensor = d.vision(port)?;
+        let slot = sensor
+            .signatures
+            .get_mut((signature_id as usize).wrapping_sub(1))
+            .ok_or(libc::EINVAL)?;
+        *slot = Some(vision_signature_s_t {
+            id: signature_id,
+            ..*signature_ptr
+        });
+        Ok(1)
+    })
+}
+
+pub unsafe fn vision_create_color_code(
+    port: u8,
+    sig_id1: u32,
+    sig_id2: u32,
+    sig_id3: u32,
+    sig_id4: u32,
+    sig_id5: u32,
+) -> vision_color_code_t {
+    with_devices(PROS_ERR_ as vision_color_code_t, |d| {
+        let sensor = d.vision(port)?;
+        let mut code = 0;
+        for id in [sig_id1, sig_id2, sig_id3, sig_id4, sig_id5] {
+            if id == 0 {
+                break;
+            }
+            let signature = sensor
+                .signatures
+                .get_mut((id as usize).wrapping_sub(1))
+                .and_then(Option::as_mut)
+                .ok_or(libc::EINVAL)?;
+            signature.type_ = vision_object_type_E_VISION_OBJECT_COLOR_CODE;
+            code = (code << 3) | id as vision_color_code_t;
+        }
+        Ok(code)
+    })
+}
+
+pub unsafe fn vision_get_object_count(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.vision(port)?.objects.len() as i32))
+}
+
+pub unsafe fn vision_read_by_size(
+    port: u8,
+    size_id: u32,
+    object_count: u32,
+    object_arr: *mut vision_object_s_t,
+) -> i32 {
+    vision_read(port, size_id, object_count, object_arr, |_| true)
+}
+
+pub unsafe fn vision_read_by_sig(
+    port: u8,
+    size_id: u32,
+    sig_id: u32,
+    object_count: u32,
+    object_arr: *mut vision_object_s_t,
+) -> i32 {
+    vision_read(port, size_id, object_count, object_arr, |o| {
+        o.signature as u32 == sig_id && o.type_ == vision_object_type_E_VISION_OBJECT_NORMAL
+    })
+}
+
+pub unsafe fn vision_read_by_code(
+    port: u8,
+    size_id: u32,
+    color_code: vision_color_code_t,
+    object_count: u32,
+    object_arr: *mut vision_object_s_t,
+) -> i32 {
+    vision_read(port, size_id, object_count, object_arr, |o| {
+        o.signature == color_code && o.type_ == vision_object_type_E_VISION_OBJECT_COLOR_CODE
+    })
+}
+
+/// Copies up to `object_count` matching objects, starting from the
+/// `size_id`th largest, and fills the rest of the array with error entries.
+unsafe fn vision_read(
+    port: u8,
+    size_id: u32,
+    object_count: u32,
+    object_arr: *mut vision_object_s_t,
+    filter: impl Fn(&vision_object_s_t) -> bool,
+) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let objects: Vec<_> = d
+            .vision(port)?
+            .report()
+            .into_iter()
+            .filter(filter)
+            .skip(size_id as usize)
+            .take(object_count as usize)
+            .collect();
+        if objects.is_empty() {
+            return Err(libc::EDOM);
+        }
+        let err = vision_object_s_t {
+            signature: VISION_OBJECT_ERR_SIG as u16,
+            ..Default::default()
+        };
+        for i in 0..object_count as usize {
+            object_arr
+                .add(i)
+                .write(objects.get(i).copied().unwrap_or(err));
+        }
+        Ok(objects.len() as i32)
+    })
+}
+
+pub unsafe fn vision_get_exposure(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.vision(port)?.exposure as i32))
+}
+
+pub unsafe fn vision_set_exposure(port: u8, exposure: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.vision(port)?.exposure = exposure.min(150);
+        Ok(1)
+    })
+}
+
+pub unsafe fn vision_get_white_balance(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.vision(port)?.white_balance))
+}
+
+pub unsafe fn vision_set_white_balance(port: u8, rgb: i32) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.vision(port)?.white_balance = rgb & 0xffffff;
+        Ok(1)
+    })
+}
+
+pub unsafe fn vision_set_zero_point(port: u8, zero_point: vision_zero_e_t) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.vision(port)?.zero_point = zero_point;
+        Ok(1)
+    })
+}
+
+// The LED, automatic white balance and Wi-Fi are not simulated.
+
+pub unsafe fn vision_set_led(port: u8, _rgb: i32) -> i32 {
+    with_devices(PROS_ERR_, |d| d.vision(port).map(|_| 1))
+}
+
+pub unsafe fn vision_clear_led(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| d.vision(port).map(|_| 1))
+}
+
+pub unsafe fn vision_set_auto_white_balance(port: u8, _enable: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| d.vision(port).map(|_| 1))
+}
+
+pub unsafe fn vision_set_wifi_mode(port: u8, _enable: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| d.vision(port).map(|_| 1))
+}
+
+// Generic serial
+
+pub unsafe fn serial_enable(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| d.enable_serial(port).map(|_| 1))
+}
+
+pub unsafe fn serial_set_baudrate(port: u8, baudrate: i32) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.serial(port)?.baudrate = baudrate;
+        Ok(1)
+    })
+}
+
+pub unsafe fn serial_flush(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let serial = d.serial(port)?;
+        serial.input.clear();
+        serial.output.clear();
+        Ok(1)
+    })
+}
+
+pub unsafe fn serial_get_read_avail(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| Ok(d.serial(port)?.input.len() as i32))
+}
+
+pub unsafe fn serial_get_write_free(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        Ok(SERIAL_BUFFER_SIZE.saturating_sub(d.serial(port)?.output.len()) as i32)
+    })
+}
+
+pub unsafe fn serial_peek_byte(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        Ok(d.serial(port)?.input.front().map_or(-1, |b| *b as i32))
+    })
+}
+
+pub unsafe fn serial_read_byte(port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        Ok(d.serial(port)?.input.pop_front().map_or(-1, |b| b as i32))
+    })
+}
+
+pub unsafe fn serial_read(port: u8, buffer: *mut u8, length: i32) -> i32 {
+    let buffer = core::slice::from_raw_parts_mut(buffer, length.max(0) as usize);
+    with_devices(PROS_ERR_, |d| {
+        let serial = d.serial(port)?;
+        let count = buffer.len().min(serial.input.len());
+        for (dst, src) in buffer.iter_mut().zip(serial.input.drain(..count)) {
+            *dst = src;
+        }
+        Ok(count as i32)
+    })
+}
+
+pub unsafe fn serial_write_byte(port: u8, buffer: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let serial = d.serial(port)?;
+        if serial.output.len() >= SERIAL_BUFFER_SIZE {
+            return Err(libc::EIO);
+        }
+        serial.output.push(buffer);
+        Ok(1)
+    })
+}
+
+pub unsafe fn serial_write(port: u8, buffer: *mut u8, length: i32) -> i32 {
+    let buffer = core::slice::from_raw_parts(buffer, length.max(0) as usize);
+    with_devices(PROS_ERR_, |d| {
+        let serial = d.serial(port)?;
+        let count = buffer
+            .len()
+            .min(SERIAL_BUFFER_SIZE.saturating_sub(serial.output.len()));
+        serial.output.extend_from_slice(&buffer[..count]);
+        Ok(count as i32)
+    })
+}
+
+// ADI
+
+fn merge_adi_ports(smart_port: u8, adi_port: u8) -> i32 {
+    (smart_port as i32) << 8 | adi_port as i32
+}
+
+fn split_adi_ports(handle: i32) -> (u8, u8) {
+    ((handle >> 8) as u8, handle as u8)
+}
+
+/// Gets an ADI pin, checking that it has been configured as `config`.
+fn adi_pin(
+    d: &mut Devices,
+    smart_port: u8,
+    adi_port: u8,
+    config: adi_port_config_e_t,
+) -> Result<&mut devices::AdiPin, i32> {
+    let pin = d.adi(smart_port, adi_port)?;
+    if pin.config == config {
+        Ok(pin)
+    } else {
+        Err(libc::EADDRINUSE)
+    }
+}
+
+/// Configures a pair of adjacent ADI pins for a two-wire legacy sensor,
+/// returning the handle.
+fn adi_pair_init(
+    d: &mut Devices,
+    smart_port: u8,
+    first: u8,
+    second: u8,
+    config: adi_port_config_e_t,
+) -> Result<i32, i32> {
+    if first % 2 == 0 || second != first + 1 {
+        return Err(libc::ENXIO);
+    }
+    d.adi(smart_port, second)?.configure(config);
+    d.adi(smart_port, first)?.configure(config);
+    Ok(merge_adi_ports(smart_port, first))
+}
+
+pub unsafe fn ext_adi_port_set_config(
+    smart_port: u8,
+    adi_port: u8,
+    type_: adi_port_config_e_t,
+) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        d.adi(smart_port, adi_port)?.configure(type_);
+        Ok(1)
+    })
+}
+
+pub unsafe fn ext_adi_analog_calibrate(smart_port: u8, adi_port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let pin = adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_ANALOG_IN)?;
+        pin.calibration = pin.value;
+        Ok(pin.calibration)
+    })
+}
+
+pub unsafe fn ext_adi_analog_read(smart_port: u8, adi_port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        Ok(adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_ANALOG_IN)?.value)
+    })
+}
+
+pub unsafe fn ext_adi_analog_read_calibrated(smart_port: u8, adi_port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let pin = adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_ANALOG_IN)?;
+        Ok(pin.value - pin.calibration)
+    })
+}
+
+pub unsafe fn ext_adi_analog_read_calibrated_HR(smart_port: u8, adi_port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let pin = adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_ANALOG_IN)?;
+        Ok((pin.value - pin.calibration) * 16)
+    })
+}
+
+pub unsafe fn ext_adi_digital_read(smart_port: u8, adi_port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        Ok(
+            (adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_DIGITAL_IN)?.value != 0)
+                as i32,
+        )
+    })
+}
+
+pub unsafe fn ext_adi_digital_write(smart_port: u8, adi_port: u8, value: bool) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_DIGITAL_OUT)?.value = value as i32;
+        d.record(smart_port, Command::AdiDigital(adi_port, value));
+        Ok(1)
+    })
+}
+
+/// Gets a pin configured for a legacy motor, which may be either a servo or a
+/// PWM motor.
+fn adi_motor_pin(
+    d: &mut Devices,
+    smart_port: u8,
+    adi_port: u8,
+) -> Result<&mut devices::AdiPin, i32> {
+    let pin = d.adi(smart_port, adi_port)?;
+    match pin.config {
+        adi_port_config_e_E_ADI_LEGACY_PWM | adi_port_config_e_E_ADI_LEGACY_SERVO => Ok(pin),
+        _ => Err(libc::EADDRINUSE),
+    }
+}
+
+pub unsafe fn ext_adi_motor_set(smart_port: u8, adi_port: u8, speed: i8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        let speed = speed.max(-127);
+        adi_motor_pin(d, smart_port, adi_port)?.value = speed as i32;
+        d.record(smart_port, Command::AdiMotor(adi_port, speed));
+        Ok(1)
+    })
+}
+
+pub unsafe fn ext_adi_motor_get(smart_port: u8, adi_port: u8) -> i32 {
+    with_devices(PROS_ERR_, |d| {
+        Ok(adi_motor_pin(d, smart_port, adi_port)?.value)
+    })
+}
+
+pub unsafe fn ext_adi_motor_stop(smart_port: u8, adi_port: u8) -> i32 {
+    ext_adi_motor_set(smart_port, adi_port, 0)
+}
+
+pub unsafe fn ext_adi_encoder_init(
+    smart_port: u8,
+    adi_port_top: u8,
+    adi_port_bottom: u8,
+    reverse: bool,
+) -> ext_adi_encoder_t {
+    with_devices(PROS_ERR_, |d| {
+        let handle = adi_pair_init(
+            d,
+            smart_port,
+            adi_port_top,
+            adi_port_bottom,
+            adi_port_config_e_E_ADI_LEGACY_ENCODER,
+        )?;
+        let (smart_port, adi_port) = split_adi_ports(handle);
+        d.adi(smart_port, adi_port)?.reversed = reverse;
+        Ok(handle)
+    })
+}
+
+pub unsafe fn ext_adi_encoder_get(enc: ext_adi_encoder_t) -> i32 {
+    let (smart_port, adi_port) = split_adi_ports(enc);
+    with_devices(PROS_ERR_, |d| {
+        let pin = adi_pin(
+            d,
+            smart_port,
+            adi_port,
+            adi_port_config_e_E_ADI_LEGACY_ENCODER,
+        )?;
+        Ok(if pin.reversed { -pin.value } else { pin.value })
+    })
+}
+
+pub unsafe fn ext_adi_encoder_reset(enc: ext_adi_encoder_t) -> i32 {
+    let (smart_port, adi_port) = split_adi_ports(enc);
+    with_devices(PROS_ERR_, |d| {
+        adi_pin(
+            d,
+            smart_port,
+            adi_port,
+            adi_port_config_e_E_ADI_LEGACY_ENCODER,
+        )?
+        .value = 0;
+        Ok(1)
+    })
+}
+
+pub unsafe fn ext_adi_encoder_shutdown(enc: ext_adi_encoder_t) -> i32 {
+    let (smart_port, adi_port) = split_adi_ports(enc);
+    with_devices(PROS_ERR_, |d| {
+        adi_pin(
+            d,
+            smart_port,
+            adi_port,
+            adi_port_config_e_E_ADI_LEGACY_ENCODER,
+        )?;
+        d.adi(smart_port, adi_port)?
+            .configure(adi_port_config_e_E_ADI_TYPE_UNDEFINED);
+        d.adi(smart_port, adi_port + 1)?
+            .configure(adi_port_config_e_E_ADI_TYPE_UNDEFINED);
+        Ok(1)
+    })
+}
+
+pub unsafe fn ext_adi_gyro_init(smart_port: u8, adi_port: u8, multiplier: f64) -> ext_adi_gyro_t {
+    with_devices(PROS_ERR_, |d| {
+        let pin = d.adi(smart_port, adi_port)?;
+        pin.configure(adi_port_config_e_E_ADI_LEGACY_GYRO);
+        pin.multiplier = multiplier;
+        Ok(merge_adi_ports(smart_port, adi_port))
+    })
+}
+
+pub unsafe fn ext_adi_gyro_get(gyro: ext_adi_gyro_t) -> f64 {
+    let (smart_port, adi_port) = split_adi_ports(gyro);
+    with_devices(PROS_ERR_F_, |d| {
+        let pin = adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_LEGACY_GYRO)?;
+        Ok((pin.value - pin.calibration) as f64 * pin.multiplier)
+    })
+}
+
+pub unsafe fn ext_adi_gyro_reset(gyro: ext_adi_gyro_t) -> i32 {
+    let (smart_port, adi_port) = split_adi_ports(gyro);
+    with_devices(PROS_ERR_, |d| {
+        let pin = adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_LEGACY_GYRO)?;
+        pin.calibration = pin.value;
+        Ok(1)
+    })
+}
+
+pub unsafe fn ext_adi_gyro_shutdown(gyro: ext_adi_gyro_t) -> i32 {
+    let (smart_port, adi_port) = split_adi_ports(gyro);
+    with_devices(PROS_ERR_, |d| {
+        adi_pin(d, smart_port, adi_port, adi_port_config_e_E_ADI_LEGACY_GYRO)?
+            .configure(adi_port_config_e_E_ADI_TYPE_UNDEFINED);
+        Ok(1)
+    })
+}
+
+pub unsafe fn ext_adi_ultrasonic_init(
+    smart_port: u8,
+    adi_port_ping: u8,
+    adi_port_echo: u8,
+) -> ext_adi_ultrasonic_t {
+    with_devices(PROS_ERR_, |d| {
+        adi_pair_init(
+            d,
+            smart_port,
+            adi_port_ping,
+            adi_port_echo,
+            adi_port_config_e_E_ADI_LEGACY_ULTRASONIC,
+        )
+    })
+}
+
+pub unsafe fn ext_adi_ultrasonic_get(ult: ext_adi_ultrasonic_t) -> i32 {
+    let (smart_port, adi_port) = split_adi_ports(ult);
+    with_devices(PROS_ERR_, |d| {
+        Ok(adi_pin(
+            d,
+            smart_port,
+            adi_port,
+            adi_port_config_e_E_ADI_LEGACY_ULTRASONIC,
+        )?
+        .value)
+    })
+}
+
+pub unsafe fn ext_adi_ultrasonic_shutdown(ult: ext_adi_ultrasonic_t) -> i32 {
+    let (smart_port, adi_port) = split_adi_ports(ult);
+    with_devices(PROS_ERR_, |d| {
+        adi_pin(
+            d,
+            smart_port,
+            adi_port,
+            adi_port_config_e_E_ADI_LEGACY_ULTRASONIC,
+        )?;
+        d.adi(smart_port, adi_port)?
+            .configure(adi_port_config_e_E_ADI_TYPE_UNDEFINED);
+        d.adi(smart_port, adi_port + 1)?
+            .configure(adi_port_config_e_E_ADI_TYPE_UNDEFINED);
+        Ok(1)
+    })
+}
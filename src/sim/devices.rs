@@ -0,0 +1,904 @@
+//! Simulated device state.
+//!
+//! Smart ports start out empty. Unless a port has been explicitly configured
+//! through [`plug()`](super::plug) or [`unplug()`](super::unplug), the first
+//! device API used on an empty port plugs in a device of the matching type, so
+//! that existing robot code runs unmodified.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, VecDeque},
+    fs::{self, OpenOptions},
+    mem,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use super::{motor::MotorState, Command};
+use crate::{
+    bindings,
+    smart_port::DeviceType,
+    vision::{VISION_FOV_HEIGHT, VISION_FOV_WIDTH},
+};
+
+/// The number of V5 smart ports.
+pub(crate) const NUM_PORTS: usize = 21;
+
+/// The capacity of the simulated serial buffers, in bytes.
+pub(crate) const SERIAL_BUFFER_SIZE: usize = 1024;
+
+/// The capacity of the simulated VEXlink buffers, in bytes.
+pub(crate) const LINK_BUFFER_SIZE: usize = 512;
+
+/// The time the inertial sensor takes to calibrate, in microseconds.
+pub(crate) const IMU_CALIBRATION_TIME: u64 = 2_000_000;
+
+static DEVICES: Mutex<Devices> = Mutex::new(Devices::new());
+
+/// Locks the global device state.
+pub(crate) fn lock() -> MutexGuard<'static, Devices> {
+    DEVICES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A function which updates simulated devices, given the time elapsed since
+/// the simulation was reset.
+pub(crate) struct Hook(pub(crate) Box<dyn FnMut(Duration, &mut Devices) + Send>);
+
+/// The state of all simulated devices.
+pub(crate) struct Devices {
+    ports: [Port; NUM_PORTS],
+    adi: BTreeMap<(u8, u8), AdiPin>,
+    controllers: [ControllerState; 2],
+    pub(crate) battery: BatteryState,
+    /// The competition status bits, as reported by field control.
+    pub(crate) competition: u8,
+    pub(crate) screen: ScreenState,
+    lcd: LcdState,
+    pub(crate) sd: SdState,
+    hooks: Vec<Hook>,
+    commands: BTreeMap<u8, Vec<Command>>,
+    /// The time at which the simulation was last reset, in microseconds.
+    epoch: u64,
+}
+
+impl Devices {
+    const fn new() -> Self {
+        const EMPTY: Port = Port {
+            device: SmartDevice::None,
+            fixed: false,
+        };
+        const CONTROLLER: ControllerState = ControllerState::new();
+        Self {
+            ports: [EMPTY; NUM_PORTS],
+            adi: BTreeMap::new(),
+            controllers: [CONTROLLER; 2],
+            battery: BatteryState::new(),
+            competition: 0,
+            screen: ScreenState::new(),
+            lcd: LcdState::new(),
+            sd: SdState::new(),
+            hooks: Vec::new(),
+            commands: BTreeMap::new(),
+            epoch: 0,
+        }
+    }
+
+    /// Returns every device to its initial state, and restarts the time seen
+    /// by hooks from `now`.
+    pub(crate) fn reset(&mut self, now: u64) {
+        *self = Self::new();
+        self.epoch = now;
+    }
+
+    /// Adds a hook to be run before every device access.
+    pub(crate) fn add_hook(&mut self, hook: Hook) {
+        self.hooks.push(hook);
+    }
+
+    /// Runs all hooks for time `now`.
+    pub(crate) fn run_hooks(&mut self, now: u64) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let elapsed = Duration::from_micros(now.saturating_sub(self.epoch));
+        let mut hooks = mem::take(&mut self.hooks);
+        for hook in hooks.iter_mut() {
+            (hook.0)(elapsed, self);
+        }
+        self.hooks = hooks;
+    }
+
+    /// Records a command sent to the device on a port.
+    pub(crate) fn record(&mut self, port: u8, command: Command) {
+        self.commands.entry(port).or_default().push(command);
+    }
+
+    /// Takes the commands recorded for a port.
+    pub(crate) fn take_commands(&mut self, port: u8) -> Vec<Command> {
+        self.commands.remove(&port).unwrap_or_default()
+    }
+
+    fn port(&mut self, port: u8) -> Result<&mut Port, i32> {
+        match port {
+            1..=21 => Ok(&mut self.ports[port as usize - 1]),
+            _ => Err(libc::ENXIO),
+        }
+    }
+
+    /// Gets the type of device plugged into the smart port with the given
+    /// zero-based index.
+    pub(crate) fn plugged_type(&self, index: u8) -> DeviceType {
+        self.ports
+            .get(index as usize)
+            .map_or(DeviceType::None, |p| p.device.device_type())
+    }
+
+    /// Plugs a device of the given type into a smart port, replacing any
+    /// existing device.
+    pub(crate) fn plug(&mut self, port: u8, device_type: DeviceType) -> Result<(), i32> {
+        let slot = self.port(port)?;
+        slot.device = SmartDevice::new(device_type);
+        slot.fixed = true;
+        self.adi.retain(|(p, _), _| *p != port);
+        Ok(())
+    }
+
+    /// Gets the device on a smart port, plugging in a device of the requested
+    /// type if the port is empty and has not been explicitly configured.
+    fn claim(&mut self, port: u8, device_type: DeviceType) -> Result<&mut SmartDevice, i32> {
+        let slot = self.port(port)?;
+        let current = slot.device.device_type();
+        if current != device_type {
+            if slot.fixed || current != DeviceType::None {
+                return Err(libc::ENODEV);
+            }
+            slot.device = SmartDevice::new(device_type);
+        }
+        Ok(&mut slot.device)
+    }
+
+    /// Switches a smart port into generic serial mode.
+    pub(crate) fn enable_serial(&mut self, port: u8) -> Result<(), i32> {
+        let slot = self.port(port)?;
+        match slot.device {
+            SmartDevice::Serial(_) => {}
+            _ if slot.fixed => return Err(libc::ENODEV),
+            _ => slot.device = SmartDevice::new(DeviceType::Serial),
+        }
+        Ok(())
+    }
+}
+
+macro_rules! accessor {
+    ($(#[$attr:meta])* $name:ident, $variant:ident, $state:ty) => {
+        impl Devices {
+            $(#[$attr])*
+            pub(crate) fn $name(&mut self, port: u8) -> Result<&mut $state, i32> {
+                match self.claim(port, DeviceType::$variant)? {
+                    SmartDevice::$variant(state) => Ok(state),
+                    _ => Err(libc::ENODEV),
+                }
+            }
+        }
+    };
+}
+
+accessor!(
+    /// Gets the motor on a smart port.
+    motor,
+    Motor,
+    MotorState
+);
+accessor!(
+    /// Gets the rotation sensor on a smart port.
+    rotation,
+    Rotation,
+    RotationState
+);
+accessor!(
+    /// Gets the inertial sensor on a smart port.
+    imu,
+    Imu,
+    ImuState
+);
+accessor!(
+    /// Gets the distance sensor on a smart port.
+    distance,
+    Distance,
+    DistanceState
+);
+accessor!(
+    /// Gets the GPS sensor on a smart port.
+    gps,
+    Gps,
+    GpsState
+);
+accessor!(
+    /// Gets the radio on a smart port.
+    radio,
+    Radio,
+    RadioState
+);
+accessor!(
+    /// Gets the optical sensor on a smart port.
+    optical,
+    Optical,
+    OpticalState
+);
+accessor!(
+    /// Gets the vision sensor on a smart port.
+    vision,
+    Vision,
+    VisionState
+);
+
+impl Devices {
+    /// Gets the serial interface of a smart port, which must have been enabled
+    /// with [`enable_serial()`](Self::enable_serial).
+    pub(crate) fn serial(&mut self, port: u8) -> Result<&mut SerialState, i32> {
+        match &mut self.port(port)?.device {
+            SmartDevice::Serial(state) => Ok(state),
+            _ => Err(libc::ENODEV),
+        }
+    }
+
+    /// Gets a pin of an ADI expander, or of the brain's built-in ADI ports.
+    pub(crate) fn adi(&mut self, smart_port: u8, adi_port: u8) -> Result<&mut AdiPin, i32> {
+        if !(1..=8).contains(&adi_port) {
+            return Err(libc::ENXIO);
+        }
+        if smart_port != bindings::INTERNAL_ADI_PORT as u8 {
+            self.claim(smart_port, DeviceType::Adi)?;
+        }
+        Ok(self
+            .adi
+            .entry((smart_port, adi_port))
+            .or_insert_with(AdiPin::new))
+    }
+
+    /// Initializes the LLEMU.
+    pub(crate) fn initialize_lcd(&mut self) {
+        self.lcd.initialized = true;
+    }
+
+    /// Gets the LLEMU, which must have been initialized.
+    pub(crate) fn lcd(&mut self) -> Result<&mut LcdState, i32> {
+        match self.lcd.initialized {
+            true => Ok(&mut self.lcd),
+            false => Err(libc::ENXIO),
+        }
+    }
+
+    /// Gets the radio on a smart port, which must have been initialized as a
+    /// VEXlink.
+    pub(crate) fn link(&mut self, port: u8) -> Result<&mut RadioState, i32> {
+        let radio = self.radio(port)?;
+        match radio.link {
+            Some(_) => Ok(radio),
+            None => Err(libc::ENXIO),
+        }
+    }
+
+    /// Gets the buffer which receives bytes sent over the VEXlink on a smart
+    /// port: the receive buffer of another port with a link of the same name
+    /// and the opposite role, or otherwise the port's own outgoing buffer.
+    pub(crate) fn link_destination(&mut self, port: u8) -> Result<&mut VecDeque<u8>, i32> {
+        let link = self.link(port)?.link.clone();
+        let peer = self.ports.iter().position(|p| match &p.device {
+            SmartDevice::Radio(radio) => match (&radio.link, &link) {
+                (Some((id, transmitter)), Some((link_id, link_transmitter))) => {
+                    id == link_id && transmitter != link_transmitter
+                }
+                _ => false,
+            },
+            _ => false,
+        });
+        let peer = match peer {
+            Some(i) => &mut self.ports[i].device,
+            None => return Ok(&mut self.link(port)?.tx),
+        };
+        match peer {
+            SmartDevice::Radio(radio) => Ok(&mut radio.rx),
+            _ => Err(libc::ENODEV),
+        }
+    }
+
+    /// Links or unlinks both controllers from the brain.
+    pub(crate) fn set_linked(&mut self, linked: bool) {
+        for controller in self.controllers.iter_mut() {
+            controller.linked = linked;
+        }
+    }
+
+    /// Gets the state of a controller.
+    pub(crate) fn controller(
+        &mut self,
+        id: bindings::controller_id_e_t,
+    ) -> Result<&mut ControllerState, i32> {
+        self.controllers.get_mut(id as usize).ok_or(libc::EINVAL)
+    }
+}
+
+struct Port {
+    device: SmartDevice,
+    fixed: bool,
+}
+
+enum SmartDevice {
+    None,
+    Motor(MotorState),
+    Rotation(RotationState),
+    Imu(ImuState),
+    Distance(DistanceState),
+    Optical(OpticalState),
+    Vision(VisionState),
+    Gps(GpsState),
+    Radio(RadioState),
+    Serial(SerialState),
+    Other(DeviceType),
+}
+
+impl SmartDevice {
+    fn new(device_type: DeviceType) -> Self {
+        match device_type {
+            DeviceType::None => Self::None,
+            DeviceType::Motor => Self::Motor(MotorState::new()),
+            DeviceType::Rotation => Self::Rotation(RotationState::new()),
+            DeviceType::Imu => Self::Imu(ImuState::new()),
+            DeviceType::Distance => Self::Distance(DistanceState::new()),
+            DeviceType::Optical => Self::Optical(OpticalState::new()),
+            DeviceType::Vision => Self::Vision(VisionState::new()),
+            DeviceType::Gps => Self::Gps(GpsState::new()),
+            DeviceType::Radio => Self::Radio(RadioState::new()),
+            DeviceType::Serial => Self::Serial(SerialState::new()),
+            t => Self::Other(t),
+        }
+    }
+
+    fn device_type(&self) -> DeviceType {
+        match self {
+            Self::None => DeviceType::None,
+            Self::Motor(_) => DeviceType::Motor,
+            Self::Rotation(_) => DeviceType::Rotation,
+            Self::Imu(_) => DeviceType::Imu,
+            Self::Distance(_) => DeviceType::Distance,
+            Self::Optical(_) => DeviceType::Optical,
+            Self::Vision(_) => DeviceType::Vision,
+            Self::Gps(_) => DeviceType::Gps,
+            Self::Radio(_) => DeviceType::Radio,
+            Self::Serial(_) => DeviceType::Serial,
+            Self::Other(t) => *t,
+        }
+    }
+}
+
+/// The state of a simulated rotation sensor.
+pub(crate) struct RotationState {
+    /// Absolute position, in centidegrees, in the sensor's physical direction.
+    pub(crate) position: i32,
+    pub(crate) offset: i32,
+    pub(crate) reversed: bool,
+    /// Velocity, in centidegrees per second.
+    pub(crate) velocity: i32,
+}
+
+impl RotationState {
+    fn new() -> Self {
+        Self {
+            position: 0,
+            offset: 0,
+            reversed: false,
+            velocity: 0,
+        }
+    }
+
+    fn sign(&self) -> i32 {
+        if self.reversed {
+            -1
+        } else {
+            1
+        }
+    }
+
+    /// The position reported to the user, in centidegrees.
+    pub(crate) fn reported_position(&self) -> i32 {
+        self.sign() * self.position - self.offset
+    }
+
+    /// The angle reported to the user, in centidegrees.
+    pub(crate) fn reported_angle(&self) -> i32 {
+        (self.sign() * self.position).rem_euclid(36000)
+    }
+
+    /// The velocity reported to the user, in centidegrees per second.
+    pub(crate) fn reported_velocity(&self) -> i32 {
+        self.sign() * self.velocity
+    }
+
+    /// Makes the reported position equal to `position`.
+    pub(crate) fn set_reported_position(&mut self, position: i32) {
+        self.offset = self.sign() * self.position - position;
+    }
+}
+
+/// The state of a simulated inertial sensor.
+pub(crate) struct ImuState {
+    /// The time at which the current calibration ends, in microseconds.
+    pub(crate) calibrated_at: Option<u64>,
+    /// The true orientation of the sensor, in degrees.
+    pub(crate) rotation: f64,
+    pub(crate) pitch: f64,
+    pub(crate) roll: f64,
+    /// Offsets subtracted from the true orientation to give reported values.
+    pub(crate) rotation_offset: f64,
+    pub(crate) heading_offset: f64,
+    pub(crate) pitch_offset: f64,
+    pub(crate) roll_offset: f64,
+    pub(crate) yaw_offset: f64,
+    /// Rotation rates, in degrees per second.
+    pub(crate) gyro_rate: [f64; 3],
+    /// Accelerations, in g.
+    pub(crate) accel: [f64; 3],
+}
+
+impl ImuState {
+    fn new() -> Self {
+        Self {
+            calibrated_at: None,
+            rotation: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            rotation_offset: 0.0,
+            heading_offset: 0.0,
+            pitch_offset: 0.0,
+            roll_offset: 0.0,
+            yaw_offset: 0.0,
+            gyro_rate: [0.0; 3],
+            accel: [0.0, 0.0, 1.0],
+        }
+    }
+
+    /// Checks whether the sensor is calibrating at time `now`.
+    pub(crate) fn is_calibrating(&self, now: u64) -> bool {
+        self.calibrated_at.map_or(false, |t| now < t)
+    }
+
+    /// Wraps an angle into the range [-180, 180).
+    pub(crate) fn wrap(angle: f64) -> f64 {
+        (angle + 180.0).rem_euclid(360.0) - 180.0
+    }
+
+    pub(crate) fn reported_rotation(&self) -> f64 {
+        self.rotation - self.rotation_offset
+    }
+
+    pub(crate) fn reported_heading(&self) -> f64 {
+        (self.rotation - self.heading_offset).rem_euclid(360.0)
+    }
+
+    pub(crate) fn reported_pitch(&self) -> f64 {
+        Self::wrap(self.pitch - self.pitch_offset)
+    }
+
+    pub(crate) fn reported_roll(&self) -> f64 {
+        Self::wrap(self.roll - self.roll_offset)
+    }
+
+    pub(crate) fn reported_yaw(&self) -> f64 {
+        Self::wrap(self.rotation - self.yaw_offset)
+    }
+
+    /// Gets the orientation as a quaternion, from the reported Euler angles.
+    pub(crate) fn quaternion(&self) -> [f64; 4] {
+        let (sr, cr) = (self.reported_roll().to_radians() / 2.0).sin_cos();
+        let (sp, cp) = (self.reported_pitch().to_radians() / 2.0).sin_cos();
+        let (sy, cy) = (self.reported_yaw().to_radians() / 2.0).sin_cos();
+        [
+            sr * cp * cy - cr * sp * sy,
+            cr * sp * cy + sr * cp * sy,
+            cr * cp * sy - sr * sp * cy,
+            cr * cp * cy + sr * sp * sy,
+        ]
+    }
+}
+
+/// The state of a simulated distance sensor.
+pub(crate) struct DistanceState {
+    /// Distance to the detected object, in millimetres.
+    pub(crate) distance: i32,
+    /// Confidence in the reading, from 0 to 63.
+    pub(crate) confidence: i32,
+    /// Relative size of the detected object, from 0 to 400.
+    pub(crate) size: i32,
+    /// Velocity of the detected object, in metres per second.
+    pub(crate) velocity: f64,
+}
+
+impl DistanceState {
+    fn new() -> Self {
+        // The sensor reports 9999 when no object is detected.
+        Self {
+            distance: 9999,
+            confidence: 0,
+            size: 0,
+            velocity: 0.0,
+        }
+    }
+}
+
+/// The state of a simulated optical sensor.
+pub(crate) struct OpticalState {
+    /// Hue of the detected color, in degrees.
+    pub(crate) hue: f64,
+    /// Saturation and brightness of the detected color, from 0 to 1.
+    pub(crate) saturation: f64,
+    pub(crate) brightness: f64,
+    /// Proximity of the detected object, from 0 to 255.
+    pub(crate) proximity: i32,
+    /// LED brightness, in percent.
+    pub(crate) led_pwm: i32,
+}
+
+impl OpticalState {
+    fn new() -> Self {
+        Self {
+            hue: 0.0,
+            saturation: 0.0,
+            brightness: 0.0,
+            proximity: 0,
+            led_pwm: 0,
+        }
+    }
+
+    /// Converts the detected color to RGB, with components from 0 to 255.
+    pub(crate) fn rgb(&self) -> [f64; 3] {
+        let chroma = self.brightness * self.saturation;
+        let h = self.hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = self.brightness - chroma;
+        [r, g, b].map(|c| (c + m) * 255.0)
+    }
+}
+
+/// The state of a simulated vision sensor.
+pub(crate) struct VisionState {
+    /// Signatures stored in slots 1 to 7.
+    pub(crate) signatures: [Option<bindings::vision_signature_s_t>; 7],
+    pub(crate) exposure: u8,
+    /// White balance, as a 24-bit RGB value.
+    pub(crate) white_balance: i32,
+    pub(crate) zero_point: bindings::vision_zero_e_t,
+    /// Detected objects, with coordinates measured from the top-left corner.
+    pub(crate) objects: Vec<bindings::vision_object_s_t>,
+}
+
+impl VisionState {
+    fn new() -> Self {
+        Self {
+            signatures: [None; 7],
+            exposure: 50,
+            white_balance: 0,
+            zero_point: bindings::vision_zero_E_VISION_ZERO_TOPLEFT,
+            objects: Vec::new(),
+        }
+    }
+
+    /// Gets the detected objects as reported by the sensor: largest first, with
+    /// coordinates measured from the zero point.
+    pub(crate) fn report(&self) -> Vec<bindings::vision_object_s_t> {
+        let (dx, dy) = match self.zero_point {
+            bindings::vision_zero_E_VISION_ZERO_CENTER => {
+                (VISION_FOV_WIDTH / 2, VISION_FOV_HEIGHT / 2)
+            }
+            _ => (0, 0),
+        };
+        let mut objects = self.objects.clone();
+        objects.sort_by_key(|o| Reverse(o.width as i32 * o.height as i32));
+        for o in objects.iter_mut() {
+            o.left_coord -= dx;
+            o.top_coord -= dy;
+            o.x_middle_coord -= dx;
+            o.y_middle_coord -= dy;
+        }
+        objects
+    }
+}
+
+/// The state of a simulated GPS sensor.
+pub(crate) struct GpsState {
+    /// The true position of the robot, in metres.
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    /// The true rotation of the robot, in degrees clockwise.
+    pub(crate) rotation: f64,
+    /// Offset subtracted from the true rotation to give the reported rotation.
+    pub(crate) rotation_offset: f64,
+    /// Offset of the sensor from the robot's centre of rotation, in metres.
+    pub(crate) offset: [f64; 2],
+    /// Estimated position error, in metres.
+    pub(crate) error: f64,
+    /// Rotation rates, in degrees per second.
+    pub(crate) gyro_rate: [f64; 3],
+    /// Accelerations, in g.
+    pub(crate) accel: [f64; 3],
+}
+
+impl GpsState {
+    fn new() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            rotation: 0.0,
+            rotation_offset: 0.0,
+            offset: [0.0; 2],
+            error: 0.0,
+            gyro_rate: [0.0; 3],
+            accel: [0.0, 0.0, 1.0],
+        }
+    }
+
+    /// The heading of the robot, in degrees from 0 to 360.
+    pub(crate) fn heading(&self) -> f64 {
+        self.rotation.rem_euclid(360.0)
+    }
+}
+
+/// The state of a simulated radio.
+pub(crate) struct RadioState {
+    /// The name of the VEXlink and whether this end is the transmitter, once
+    /// a link has been initialized.
+    pub(crate) link: Option<(String, bool)>,
+    /// Bytes waiting to be received by the robot.
+    pub(crate) rx: VecDeque<u8>,
+    /// Bytes sent by the robot which no simulated port has received.
+    pub(crate) tx: VecDeque<u8>,
+}
+
+impl RadioState {
+    fn new() -> Self {
+        Self {
+            link: None,
+            rx: VecDeque::new(),
+            tx: VecDeque::new(),
+        }
+    }
+}
+
+/// The state of a smart port in generic serial mode.
+pub(crate) struct SerialState {
+    pub(crate) baudrate: i32,
+    /// Bytes waiting to be read by the program.
+    pub(crate) input: VecDeque<u8>,
+    /// Bytes written by the program.
+    pub(crate) output: Vec<u8>,
+}
+
+impl SerialState {
+    fn new() -> Self {
+        Self {
+            baudrate: 115200,
+            input: VecDeque::new(),
+            output: Vec::new(),
+        }
+    }
+}
+
+/// The state of a single ADI pin.
+pub(crate) struct AdiPin {
+    pub(crate) config: bindings::adi_port_config_e_t,
+    /// The raw value of the pin: an analog or digital reading, encoder ticks,
+    /// gyro angle in tenths of a degree, ultrasonic distance or motor speed.
+    pub(crate) value: i32,
+    pub(crate) calibration: i32,
+    pub(crate) reversed: bool,
+    pub(crate) multiplier: f64,
+}
+
+impl AdiPin {
+    fn new() -> Self {
+        Self {
+            config: bindings::adi_port_config_e_E_ADI_TYPE_UNDEFINED,
+            value: 0,
+            calibration: 0,
+            reversed: false,
+            multiplier: 1.0,
+        }
+    }
+
+    /// Configures the pin, resetting its value.
+    pub(crate) fn configure(&mut self, config: bindings::adi_port_config_e_t) {
+        *self = Self::new();
+        self.config = config;
+    }
+}
+
+/// The state of a simulated controller.
+pub(crate) struct ControllerState {
+    pub(crate) connected: bool,
+    /// Whether the radio link to the brain is up.
+    pub(crate) linked: bool,
+    pub(crate) analog: [i32; 4],
+    pub(crate) digital: [bool; 12],
+    pub(crate) battery_level: i32,
+    pub(crate) battery_capacity: i32,
+    /// The text shown on the controller screen.
+    pub(crate) text: [[u8; 19]; 3],
+    /// The last rumble pattern sent to the controller.
+    pub(crate) rumble: Vec<u8>,
+}
+
+impl ControllerState {
+    const fn new() -> Self {
+        Self {
+            connected: false,
+            linked: true,
+            analog: [0; 4],
+            digital: [false; 12],
+            battery_level: 100,
+            battery_capacity: 100,
+            text: [[b' '; 19]; 3],
+            rumble: Vec::new(),
+        }
+    }
+
+    /// Checks whether the controller is connected to the brain.
+    pub(crate) fn is_connected(&self) -> bool {
+        self.connected && self.linked
+    }
+}
+
+/// The state of the simulated robot battery.
+pub(crate) struct BatteryState {
+    pub(crate) capacity: f64,
+    pub(crate) current: i32,
+    pub(crate) temperature: f64,
+    pub(crate) voltage: i32,
+}
+
+impl BatteryState {
+    const fn new() -> Self {
+        Self {
+            capacity: 100.0,
+            current: 0,
+            temperature: 25.0,
+            voltage: 12800,
+        }
+    }
+}
+
+/// The state of the simulated brain screen.
+pub(crate) struct ScreenState {
+    pub(crate) pen: u32,
+    pub(crate) eraser: u32,
+    /// The text printed on the screen, oldest first.
+    pub(crate) text: Vec<String>,
+    pub(crate) touch: bindings::screen_touch_status_s_t,
+}
+
+impl ScreenState {
+    const fn new() -> Self {
+        Self {
+            pen: 0xFFFFFF,
+            eraser: 0x000000,
+            text: Vec::new(),
+            touch: bindings::screen_touch_status_s_t {
+                touch_status: bindings::last_touch_e_t_E_TOUCH_RELEASED,
+                x: 0,
+                y: 0,
+                press_count: 0,
+                release_count: 0,
+            },
+        }
+    }
+
+    /// Moves the simulated finger: `Some` point to touch the screen there, or
+    /// `None` to lift it.
+    pub(crate) fn touch(&mut self, point: Option<(i16, i16)>) {
+        let touch = &mut self.touch;
+        let down = touch.touch_status != bindings::last_touch_e_t_E_TOUCH_RELEASED;
+        match point {
+            Some((x, y)) => {
+                if down {
+                    touch.touch_status = bindings::last_touch_e_t_E_TOUCH_HELD;
+                } else {
+                    touch.touch_status = bindings::last_touch_e_t_E_TOUCH_PRESSED;
+                    touch.press_count += 1;
+                }
+                touch.x = x;
+                touch.y = y;
+            }
+            None if down => {
+                touch.touch_status = bindings::last_touch_e_t_E_TOUCH_RELEASED;
+                touch.release_count += 1;
+            }
+            None => {}
+        }
+    }
+}
+
+/// The state of the simulated LLEMU.
+pub(crate) struct LcdState {
+    pub(crate) initialized: bool,
+    pub(crate) lines: [String; 8],
+    /// The buttons pressed, as a bit mask.
+    pub(crate) buttons: u8,
+}
+
+impl LcdState {
+    const fn new() -> Self {
+        const EMPTY: String = String::new();
+        Self {
+            initialized: false,
+            lines: [EMPTY; 8],
+            buttons: 0,
+        }
+    }
+}
+
+/// The state of the simulated SD card.
+pub(crate) struct SdState {
+    /// The host directory holding the contents of the card, if one is
+    /// installed.
+    pub(crate) root: Option<PathBuf>,
+    files: BTreeMap<usize, SdFile>,
+    next_id: usize,
+}
+
+/// A file opened on the simulated SD card.
+pub(crate) struct SdFile {
+    pub(crate) file: fs::File,
+    /// Whether an operation on the file has failed.
+    pub(crate) error: bool,
+}
+
+impl SdState {
+    const fn new() -> Self {
+        Self {
+            root: None,
+            files: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Opens a file under `/usd/` with a C `fopen` mode, returning its ID.
+    pub(crate) fn open(&mut self, path: &str, mode: &[u8]) -> Result<usize, i32> {
+        let root = self.root.as_ref().ok_or(libc::ENXIO)?;
+        let path = root.join(path.strip_prefix("/usd/").ok_or(libc::ENOENT)?);
+        let mut options = OpenOptions::new();
+        match mode.first() {
+            Some(b'r') => options.read(true),
+            Some(b'w') => options.write(true).create(true).truncate(true),
+            Some(b'a') => options.append(true).create(true),
+            _ => return Err(libc::EINVAL),
+        };
+        if mode.contains(&b'+') {
+            options.read(true).write(true);
+        }
+        let file = options
+            .open(path)
+            .map_err(|err| err.raw_os_error().unwrap_or(libc::EIO))?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.files.insert(id, SdFile { file, error: false });
+        Ok(id)
+    }
+
+    /// Gets an open file.
+    pub(crate) fn file(&mut self, id: usize) -> Result<&mut SdFile, i32> {
+        self.files.get_mut(&id).ok_or(libc::EBADF)
+    }
+
+    /// Closes an open file.
+    pub(crate) fn close(&mut self, id: usize) -> Result<(), i32> {
+        self.files.remove(&id).map(drop).ok_or(libc::EBADF)
+    }
+}
@@ -0,0 +1,454 @@
+//! Simulated field control.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use super::{bindings, devices, kernel};
+use crate::{
+    machine::StateMachine,
+    peripherals::Peripherals,
+    robot::{Competition, CompetitionState, Robot},
+    rtos::{time_since_start, Context, Instant, Selectable, Task},
+};
+
+/// The longest the robot is given to initialize before the match starts
+/// regardless.
+const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A period of a match, as signalled by field control.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchPhase {
+    /// The robot is disabled.
+    Disabled,
+    /// The autonomous period.
+    Autonomous,
+    /// The driver control period.
+    Opcontrol,
+}
+
+impl MatchPhase {
+    fn state(self) -> CompetitionState {
+        match self {
+            Self::Disabled => CompetitionState::Disabled,
+            Self::Autonomous => CompetitionState::Autonomous,
+            Self::Opcontrol => CompetitionState::Opcontrol,
+        }
+    }
+
+    /// The competition status bits reported while connected to field control.
+    fn status(self) -> u8 {
+        let phase = match self {
+            Self::Disabled => bindings::COMPETITION_DISABLED,
+            Self::Autonomous => bindings::COMPETITION_AUTONOMOUS,
+            Self::Opcontrol => 0,
+        };
+        (phase | bindings::COMPETITION_CONNECTED | bindings::COMPETITION_SYSTEM) as u8
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Step {
+    phase: MatchPhase,
+    duration: Duration,
+    linked: bool,
+}
+
+/// A timeline of field control events, which can be replayed against a
+/// [`Robot`] implementation to check that it behaves well across phase
+/// changes.
+///
+/// ```no_run
+/// # use vex_rt::{prelude::*, sim::*};
+/// # struct Bot;
+/// # impl Robot for Bot {
+/// #     fn new(_: Peripherals) -> Self { Bot }
+/// # }
+/// let (peripherals, _sim) = PeripheralsBuilder::new().build();
+/// let report = Match::standard().run::<Bot>(peripherals);
+/// assert!(report.is_clean(), "{}", report);
+/// ```
+pub struct Match {
+    steps: Vec<Step>,
+    grace_period: Duration,
+}
+
+impl Match {
+    /// Creates an empty timeline.
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            grace_period: Duration::from_millis(100),
+        }
+    }
+
+    /// Creates the timeline of a standard match: a 15 second autonomous
+    /// period and a 1:45 driver control period, each preceded and followed by
+    /// the robot being disabled.
+    pub fn standard() -> Self {
+        Self::new()
+            .phase(MatchPhase::Disabled, Duration::from_secs(1))
+            .phase(MatchPhase::Autonomous, Duration::from_secs(15))
+            .phase(MatchPhase::Disabled, Duration::from_secs(3))
+            .phase(MatchPhase::Opcontrol, Duration::from_secs(105))
+    }
+
+    /// Adds a phase to the timeline.
+    pub fn phase(mut self, phase: MatchPhase, duration: Duration) -> Self {
+        self.steps.push(Step {
+            phase,
+            duration,
+            linked: true,
+        });
+        self
+    }
+
+    /// Adds a period during which the controllers lose their link to the
+    /// brain. The robot is disabled until the link is restored.
+    pub fn disconnect(mut self, duration: Duration) -> Self {
+        self.steps.push(Step {
+            phase: MatchPhase::Disabled,
+            duration,
+            linked: false,
+        });
+        self
+    }
+
+    /// Sets how long the robot is given to wind down after each phase ends,
+    /// before its tasks are checked. Defaults to 100 milliseconds.
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Constructs a robot and plays the timeline against it, followed by a
+    /// field disable.
+    ///
+    /// The robot is initialized first; the match starts once
+    /// [`Robot::initialize()`] returns, or after 10 seconds. From then on, the
+    /// [competition status](crate::competition) reports a connected field
+    /// controller.
+    pub fn run<R: Robot>(self, peripherals: Peripherals) -> MatchReport {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let competition = Competition::new(Probe {
+            robot: R::new(peripherals),
+            calls: calls.clone(),
+        });
+
+        let deadline = time_since_start() + INITIALIZE_TIMEOUT;
+        let initialized = loop {
+            if lock(&calls)
+                .first()
+                .map_or(false, |c: &Call| c.returned.is_some())
+            {
+                break true;
+            }
+            if time_since_start() >= deadline {
+                break false;
+            }
+            Task::delay(Duration::from_millis(1));
+        };
+        // Robot methods are called one at a time, so each phase's tasks are
+        // those spawned from this thread between the previous call returning
+        // and its own returning.
+        let mut first_task = lock(&calls)
+            .first()
+            .and_then(|c| c.returned)
+            .unwrap_or_else(kernel::next_id);
+
+        let mut phases = Vec::new();
+        let mut current: Option<Active> = None;
+        let final_disable = Step {
+            phase: MatchPhase::Disabled,
+            duration: self.grace_period,
+            linked: true,
+        };
+        for step in self.steps.into_iter().chain([final_disable]) {
+            {
+                let mut devices = devices::lock();
+                devices.set_linked(step.linked);
+                devices.competition = step.phase.status();
+            }
+            let ctx = competition.transition(step.phase.state());
+            let start = time_since_start();
+            let mut remaining = step.duration;
+            if let Some(previous) = current.take() {
+                let grace_period = self.grace_period.min(step.duration);
+                Task::delay(grace_period);
+                remaining -= grace_period;
+                let (report, next_task) = previous.finish(&calls, start);
+                phases.push(report);
+                first_task = next_task;
+            }
+            if !remaining.is_zero() {
+                Task::delay(remaining);
+            }
+            current = Some(Active {
+                step,
+                ctx,
+                start,
+                first_task,
+            });
+        }
+
+        MatchReport {
+            initialized,
+            phases,
+        }
+    }
+}
+
+impl Default for Match {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A phase which has been entered but not yet reported on.
+struct Active {
+    step: Step,
+    ctx: Context,
+    start: Instant,
+    first_task: usize,
+}
+
+impl Active {
+    /// Reports on the phase, also returning the ID of the first task which
+    /// belongs to the next phase.
+    fn finish(self, calls: &Mutex<Vec<Call>>, end: Instant) -> (PhaseReport, usize) {
+        let call = lock(calls)
+            .iter()
+            .find(|c| c.ctx.ptr_eq(&self.ctx))
+            .map(|c| c.returned);
+        // A call which has not returned may still spawn tasks, so claims every
+        // task spawned so far.
+        let next_task = call.flatten().unwrap_or_else(kernel::next_id);
+        let tasks = Range {
+            start: self.first_task,
+            end: next_task,
+        };
+        let report = PhaseReport {
+            phase: self.step.phase,
+            disconnected: !self.step.linked,
+            start: self.start,
+            end,
+            started: call.is_some(),
+            returned: call.flatten().is_some(),
+            context_cancelled: self.ctx.done().poll().is_ok(),
+            lingering_tasks: kernel::live_tasks(tasks),
+        };
+        (report, next_task)
+    }
+}
+
+/// The outcome of a [`Match`].
+#[derive(Clone, Debug)]
+pub struct MatchReport {
+    /// Whether [`Robot::initialize()`] returned before the match started.
+    pub initialized: bool,
+    /// The reports for each phase, in order.
+    pub phases: Vec<PhaseReport>,
+}
+
+impl MatchReport {
+    /// Checks whether the robot initialized in time and every phase was
+    /// clean.
+    pub fn is_clean(&self) -> bool {
+        self.initialized && self.phases.iter().all(PhaseReport::is_clean)
+    }
+}
+
+impl Display for MatchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if !self.initialized {
+            writeln!(f, "initialize did not return")?;
+        }
+        for phase in self.phases.iter() {
+            writeln!(f, "{}", phase)?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of a single phase of a [`Match`].
+#[derive(Clone, Debug)]
+pub struct PhaseReport {
+    /// The phase.
+    pub phase: MatchPhase,
+    /// Whether the controllers were disconnected during the phase.
+    pub disconnected: bool,
+    /// The time at which the phase started.
+    pub start: Instant,
+    /// The time at which the phase ended.
+    pub end: Instant,
+    /// Whether the [`Robot`] method for the phase was called.
+    pub started: bool,
+    /// Whether the [`Robot`] method for the phase returned within the grace
+    /// period after the phase ended.
+    pub returned: bool,
+    /// Whether the [`Context`] passed to the [`Robot`] method was cancelled
+    /// when the phase ended.
+    pub context_cancelled: bool,
+    /// The names of the tasks spawned during the phase which were still
+    /// running at the end of the grace period.
+    pub lingering_tasks: Vec<String>,
+}
+
+impl PhaseReport {
+    /// Checks whether the phase started, and ended without leaving anything
+    /// running.
+    pub fn is_clean(&self) -> bool {
+        self.started && self.returned && self.context_cancelled && self.lingering_tasks.is_empty()
+    }
+}
+
+impl Display for PhaseReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}{} [{:?}, {:?}]:",
+            self.phase,
+            if self.disconnected {
+                " (disconnected)"
+            } else {
+                ""
+            },
+            self.start,
+            self.end
+        )?;
+        if self.is_clean() {
+            return write!(f, " ok");
+        }
+        if !self.started {
+            write!(f, " not started;")?;
+        } else if !self.returned {
+            write!(f, " did not return;")?;
+        }
+        if !self.context_cancelled {
+            write!(f, " context not cancelled;")?;
+        }
+        for task in self.lingering_tasks.iter() {
+            write!(f, " task {:?} still running;", task)?;
+        }
+        Ok(())
+    }
+}
+
+/// A call made by the state machine to the robot.
+struct Call {
+    ctx: Context,
+    /// The ID of the next task to be spawned as of when the call returned, or
+    /// `None` if it has not returned.
+    returned: Option<usize>,
+}
+
+/// Wraps a robot, recording the calls made to it.
+struct Probe<R> {
+    robot: R,
+    calls: Arc<Mutex<Vec<Call>>>,
+}
+
+impl<R: Robot> Probe<R> {
+    fn call(&mut self, ctx: Context, f: impl FnOnce(&mut R, Context)) {
+        let index = {
+            let mut calls = lock(&self.calls);
+            calls.push(Call {
+                ctx: ctx.clone(),
+                returned: None,
+            });
+            calls.len() - 1
+        };
+        f(&mut self.robot, ctx);
+        lock(&self.calls)[index].returned = Some(kernel::next_id());
+    }
+}
+
+impl<R: Robot> Robot for Probe<R> {
+    fn new(_peripherals: Peripherals) -> Self {
+        unreachable!("probes are constructed by the match simulator")
+    }
+
+    fn initialize(&mut self, ctx: Context) {
+        self.call(ctx, R::initialize);
+    }
+
+    fn autonomous(&mut self, ctx: Context) {
+        self.call(ctx, R::autonomous);
+    }
+
+    fn opcontrol(&mut self, ctx: Context) {
+        self.call(ctx, R::opcontrol);
+    }
+
+    fn disabled(&mut self, ctx: Context) {
+        self.call(ctx, R::disabled);
+    }
+}
+
+fn lock(calls: &Mutex<Vec<Call>>) -> MutexGuard<'_, Vec<Call>> {
+    calls.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lcd::Lcd, select, sim::PeripheralsBuilder};
+
+    /// Leaves a task running after autonomous, which it spawns only once the
+    /// period has ended.
+    struct Leaky {
+        lcd: Lcd,
+    }
+
+    impl Robot for Leaky {
+        fn new(peripherals: Peripherals) -> Self {
+            Self {
+                lcd: peripherals.brain_screen.into_lcd().unwrap(),
+            }
+        }
+
+        fn autonomous(&mut self, ctx: Context) {
+            self.lcd.print(0, "autonomous");
+            select! {
+                _ = ctx.done() => {},
+            }
+            Task::spawn_ext(
+                "leak",
+                Task::DEFAULT_PRIORITY,
+                Task::DEFAULT_STACK_DEPTH,
+                || loop {
+                    Task::delay(Duration::from_millis(10));
+                },
+            )
+            .unwrap();
+        }
+
+        fn disabled(&mut self, ctx: Context) {
+            select! {
+                _ = ctx.done() => {},
+            }
+        }
+    }
+
+    #[test]
+    fn lingering_task_reported_for_its_phase() {
+        let (peripherals, _sim) = PeripheralsBuilder::new().build();
+        let report = Match::new()
+            .phase(MatchPhase::Autonomous, Duration::from_secs(1))
+            .phase(MatchPhase::Disabled, Duration::from_secs(1))
+            .run::<Leaky>(peripherals);
+
+        assert!(report.initialized);
+        assert_eq!(report.phases.len(), 2, "{}", report);
+        let autonomous = &report.phases[0];
+        assert_eq!(autonomous.phase, MatchPhase::Autonomous);
+        assert!(autonomous.started && autonomous.returned && autonomous.context_cancelled);
+        assert_eq!(autonomous.lingering_tasks, ["leak"]);
+        assert!(!report.is_clean());
+        let disabled = &report.phases[1];
+        assert!(disabled.is_clean(), "{}", report);
+    }
+}
@@ -0,0 +1,875 @@
+//! Simulated kernel: tasks, notifications, mutexes, semaphores and the virtual
+//! clock.
+//!
+//! Every task is backed by a host thread. The kernel tracks how many tasks are
+//! currently runnable; when that count drops to zero, the virtual clock jumps
+//! straight to the earliest pending wake-up and the corresponding tasks are
+//! resumed.
+//!
+//! A panic in a spawned task is recorded against the thread which ultimately
+//! spawned it (its root), and raised again on that thread the next time it
+//! uses the kernel. Under [`run()`](super::run) the process exits instead, as
+//! it would on the brain.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    ffi::CString,
+    ops::Range,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread,
+};
+
+/// Timeout value which denotes an unbounded wait.
+pub(crate) const TIMEOUT_MAX: u32 = u32::MAX;
+
+static KERNEL: Mutex<Kernel> = Mutex::new(Kernel::new());
+
+static WAKE: Condvar = Condvar::new();
+
+static EXIT_WHEN_IDLE: AtomicBool = AtomicBool::new(false);
+
+static EXITING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static CURRENT: RefCell<Option<Registration>> = RefCell::new(None);
+    static SPAWNED: Cell<bool> = Cell::new(false);
+}
+
+/// The scheduling state of a simulated task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TaskStatus {
+    Running,
+    Ready,
+    Blocked,
+    Suspended,
+    Deleted,
+    Invalid,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WaitReason {
+    Delay,
+    Notify,
+    Mutex(usize),
+    Semaphore(usize),
+    External,
+    Suspended,
+}
+
+struct Wait {
+    reason: WaitReason,
+    deadline: Option<u64>,
+}
+
+struct TaskControl {
+    name: CString,
+    priority: u32,
+    root: usize,
+    notifications: u32,
+    wait: Option<Wait>,
+    deleted: bool,
+    service: bool,
+}
+
+struct MutexControl {
+    owner: Option<usize>,
+    depth: u32,
+}
+
+struct SemaphoreControl {
+    count: u32,
+    max: u32,
+}
+
+struct Kernel {
+    now: u64,
+    next_id: usize,
+    running: usize,
+    tasks: BTreeMap<usize, TaskControl>,
+    mutexes: BTreeMap<usize, MutexControl>,
+    semaphores: BTreeMap<usize, SemaphoreControl>,
+    panics: BTreeMap<usize, String>,
+}
+
+impl Kernel {
+    const fn new() -> Self {
+        Self {
+            now: 0,
+            next_id: 1,
+            running: 0,
+            tasks: BTreeMap::new(),
+            mutexes: BTreeMap::new(),
+            semaphores: BTreeMap::new(),
+            panics: BTreeMap::new(),
+        }
+    }
+
+    fn allocate_id(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Adds a runnable task. `root` is the ID of the thread which ultimately
+    /// spawned it, or `None` if the task is a thread registering itself.
+    fn insert_task(&mut self, name: &str, priority: u32, root: Option<usize>) -> usize {
+        let id = self.allocate_id();
+        self.tasks.insert(
+            id,
+            TaskControl {
+                name: CString::new(name.replace('\0', "")).unwrap_or_default(),
+                priority,
+                root: root.unwrap_or(id),
+                notifications: 0,
+                wait: None,
+                deleted: false,
+                service: false,
+            },
+        );
+        self.running += 1;
+        id
+    }
+
+    fn wake(&mut self, id: usize) {
+        if let Some(task) = self.tasks.get_mut(&id) {
+            if matches!(&task.wait, Some(wait) if wait.reason != WaitReason::Suspended) {
+                task.wait = None;
+                self.running += 1;
+                WAKE.notify_all();
+            }
+        }
+    }
+
+    fn wake_all(&mut self, reason: WaitReason) {
+        let ids: Vec<usize> = self
+            .tasks
+            .iter()
+            .filter(|(_, t)| matches!(&t.wait, Some(wait) if wait.reason == reason))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.wake(id);
+        }
+    }
+
+    /// Advances the virtual clock if no task is able to run. Returns `true` if
+    /// no task will ever be able to run again.
+    fn schedule(&mut self) -> bool {
+        if self.running != 0 {
+            return false;
+        }
+        let next = self
+            .tasks
+            .values()
+            .filter_map(|t| t.wait.as_ref()?.deadline)
+            .min();
+        if let Some(deadline) = next {
+            self.now = self.now.max(deadline);
+            let now = self.now;
+            let ids: Vec<usize> = self
+                .tasks
+                .iter()
+                .filter(
+                    |(_, t)| matches!(&t.wait, Some(Wait { deadline: Some(d), .. }) if *d <= now),
+                )
+                .map(|(id, _)| *id)
+                .collect();
+            for id in ids {
+                self.wake(id);
+            }
+            false
+        } else {
+            true
+        }
+    }
+
+    fn deadline(&self, timeout: u32) -> Option<u64> {
+        (timeout != TIMEOUT_MAX).then(|| self.now + timeout as u64 * 1000)
+    }
+
+    fn expired(&self, deadline: Option<u64>) -> bool {
+        deadline.map_or(false, |d| self.now >= d)
+    }
+}
+
+/// Represents the association of a host thread with a simulated task.
+struct Registration(usize);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if EXITING.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut k = lock();
+        if let Some(task) = k.tasks.get_mut(&self.0) {
+            task.deleted = true;
+            let wait = Wait {
+                reason: WaitReason::Suspended,
+                deadline: None,
+            };
+            if task.wait.replace(wait).is_none() {
+                k.running -= 1;
+            }
+        }
+        // Nobody is left to raise a panic recorded against this thread.
+        k.panics.remove(&self.0);
+        WAKE.notify_all();
+        drop(schedule(k));
+    }
+}
+
+fn lock() -> MutexGuard<'static, Kernel> {
+    KERNEL.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Runs the scheduler, exiting the process if requested once every task is
+/// waiting for an event which can no longer occur.
+fn schedule(mut k: MutexGuard<'static, Kernel>) -> MutexGuard<'static, Kernel> {
+    if k.schedule() && EXIT_WHEN_IDLE.load(Ordering::Relaxed) {
+        EXITING.store(true, Ordering::Relaxed);
+        drop(k);
+        std::process::exit(0);
+    }
+    k
+}
+
+/// Blocks the task `id` until it is woken, either explicitly or by reaching
+/// `deadline` on the virtual clock.
+fn block(
+    mut k: MutexGuard<'static, Kernel>,
+    id: usize,
+    mut reason: WaitReason,
+    mut deadline: Option<u64>,
+) -> MutexGuard<'static, Kernel> {
+    if let Some(task) = k.tasks.get_mut(&id) {
+        if task.deleted {
+            // Deleted tasks never run again.
+            reason = WaitReason::Suspended;
+            deadline = None;
+            WAKE.notify_all();
+        }
+        task.wait = Some(Wait { reason, deadline });
+        k.running -= 1;
+    }
+    k = schedule(k);
+    while k.tasks.get(&id).map_or(false, |t| t.wait.is_some()) {
+        if k.panics.contains_key(&id) && !thread::panicking() {
+            // A task spawned from this thread panicked, so stop waiting for
+            // something which may never happen.
+            if let Some(task) = k.tasks.get_mut(&id) {
+                task.wait = None;
+            }
+            k.running += 1;
+            break;
+        }
+        k = WAKE.wait(k).unwrap_or_else(PoisonError::into_inner);
+    }
+    if k.tasks.get(&id).map_or(false, |t| t.deleted) {
+        return block(k, id, WaitReason::Suspended, None);
+    }
+    raise(k, id)
+}
+
+/// Raises on the current thread any panic recorded against the task `id` by
+/// the tasks it spawned, unless the thread is already panicking.
+fn raise(mut k: MutexGuard<'static, Kernel>, id: usize) -> MutexGuard<'static, Kernel> {
+    if thread::panicking() {
+        return k;
+    }
+    if let Some(message) = k.panics.remove(&id) {
+        drop(k);
+        panic!("{}", message);
+    }
+    k
+}
+
+/// Gets the ID of the current task, registering the current thread as a task if
+/// it is not one already.
+///
+/// Raises any panic recorded against the current task.
+pub(crate) fn current() -> usize {
+    let id = registered().unwrap_or_else(|| {
+        let name = thread::current().name().unwrap_or("").to_string();
+        let id = lock().insert_task(&name, crate::rtos::Task::DEFAULT_PRIORITY, None);
+        CURRENT.with(|c| *c.borrow_mut() = Some(Registration(id)));
+        id
+    });
+    if !SPAWNED.with(Cell::get) {
+        drop(raise(lock(), id));
+    }
+    id
+}
+
+/// Gets the ID of the current task, if the current thread is registered as
+/// one.
+fn registered() -> Option<usize> {
+    CURRENT.with(|c| c.borrow().as_ref().map(|Registration(id)| *id))
+}
+
+/// Gets the current time on the virtual clock, in microseconds.
+pub(crate) fn now() -> u64 {
+    lock().now
+}
+
+/// Spawns a new task backed by a host thread. Returns `None` if the thread
+/// could not be created.
+pub(crate) fn spawn(name: &str, priority: u32, f: Box<dyn FnOnce() + Send>) -> Option<usize> {
+    // The spawning thread must be registered, so that the clock cannot advance
+    // while it is still running.
+    let parent = current();
+
+    let mut k = lock();
+    let root = k.tasks.get(&parent).map_or(parent, |t| t.root);
+    let id = k.insert_task(name, priority, Some(root));
+    drop(k);
+    let result = thread::Builder::new().name(name.into()).spawn(move || {
+        SPAWNED.with(|s| s.set(true));
+        CURRENT.with(|c| *c.borrow_mut() = Some(Registration(id)));
+        let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) else {
+            return;
+        };
+        // As on the brain, a panic in any task brings down the program.
+        if EXIT_WHEN_IDLE.load(Ordering::Relaxed) {
+            std::process::exit(1);
+        }
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        let mut k = lock();
+        let name = k.tasks[&id].name.to_string_lossy().into_owned();
+        k.panics
+            .entry(root)
+            .or_insert(format!("task {:?} panicked: {}", name, message));
+        WAKE.notify_all();
+    });
+    match result {
+        Ok(_) => Some(id),
+        Err(_) => {
+            let mut k = lock();
+            k.tasks.remove(&id);
+            k.running -= 1;
+            drop(schedule(k));
+            None
+        }
+    }
+}
+
+/// Delays the current task by the given number of milliseconds of virtual time.
+///
+/// A delay of zero still waits for the next millisecond tick, so that polling
+/// loops cannot stall the virtual clock.
+pub(crate) fn delay(millis: u32) {
+    let id = current();
+    let k = lock();
+    let deadline = k.now + millis.max(1) as u64 * 1000;
+    drop(block(k, id, WaitReason::Delay, Some(deadline)));
+}
+
+/// Runs `f`, which waits on something outside of the kernel, with the current
+/// task (if the current thread is registered as one) marked as blocked so that
+/// the virtual clock can advance in the meantime.
+pub(crate) fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    let Some(id) = registered() else {
+        return f();
+    };
+    let mut k = lock();
+    if let Some(task) = k.tasks.get_mut(&id) {
+        task.wait = Some(Wait {
+            reason: WaitReason::External,
+            deadline: None,
+        });
+        k.running -= 1;
+    }
+    drop(schedule(k));
+    let result = f();
+    let mut k = lock();
+    if let Some(task) = k.tasks.get_mut(&id) {
+        if task.wait.take().is_some() {
+            k.running += 1;
+        }
+    }
+    if k.tasks.get(&id).map_or(false, |t| t.deleted) {
+        drop(block(k, id, WaitReason::Suspended, None));
+    }
+    result
+}
+
+/// Makes the process exit once no task can make further progress.
+pub(crate) fn exit_when_idle() {
+    EXIT_WHEN_IDLE.store(true, Ordering::Relaxed);
+}
+
+/// Suspends the current task forever.
+pub(crate) fn suspend() -> ! {
+    let id = current();
+    let _k = block(lock(), id, WaitReason::Suspended, None);
+    unreachable!("suspended task resumed")
+}
+
+/// Gets the name of a task. The pointer remains valid for the lifetime of the
+/// program.
+pub(crate) fn name(id: usize) -> Option<*const libc::c_char> {
+    lock().tasks.get(&id).map(|t| t.name.as_ptr())
+}
+
+/// Finds a live task by name.
+pub(crate) fn find(name: &str) -> Option<usize> {
+    lock()
+        .tasks
+        .iter()
+        .find(|(_, t)| !t.deleted && t.name.as_bytes() == name.as_bytes())
+        .map(|(id, _)| *id)
+}
+
+/// Gets the ID which will be given to the next task, mutex or semaphore to be
+/// created.
+pub(crate) fn next_id() -> usize {
+    lock().next_id
+}
+
+/// Gets the names of the live tasks whose IDs lie in the given range, other
+/// than services. Only tasks which share a root with the current task are
+/// included, so that tasks spawned by other threads, such as other tests, are
+/// not.
+pub(crate) fn live_tasks(ids: Range<usize>) -> Vec<String> {
+    let current = current();
+    let k = lock();
+    let root = k.tasks.get(&current).map_or(current, |t| t.root);
+    k.tasks
+        .range(ids)
+        .filter(|(id, t)| **id != root && t.root == root && !t.deleted && !t.service)
+        .map(|(_, t)| t.name.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Marks a task as a service which lives for the rest of the program.
+pub(crate) fn mark_service(id: usize) {
+    if let Some(task) = lock().tasks.get_mut(&id) {
+        task.service = true;
+    }
+}
+
+/// Deletes every live task spawned, directly or otherwise, by the current
+/// thread, other than services, and waits for them to stop running. Then
+/// raises any panic recorded against the current thread.
+pub(crate) fn delete_spawned() {
+    let Some(root) = registered() else {
+        return;
+    };
+    let mut k = lock();
+    let ids: Vec<usize> = k
+        .tasks
+        .iter()
+        .filter(|(id, t)| **id != root && t.root == root && !t.deleted && !t.service)
+        .map(|(id, _)| *id)
+        .collect();
+    for id in ids.iter() {
+        if let Some(task) = k.tasks.get_mut(id) {
+            task.deleted = true;
+            if task.wait.is_some() {
+                k.wake(*id);
+            }
+        }
+    }
+    // A deleted task parks itself the next time it enters the kernel.
+    while ids.iter().any(|id| {
+        !matches!(
+            k.tasks[id].wait,
+            Some(Wait {
+                reason: WaitReason::Suspended,
+                ..
+            })
+        )
+    }) {
+        k = WAKE.wait(k).unwrap_or_else(PoisonError::into_inner);
+    }
+    drop(raise(k, root));
+}
+
+/// Gets the priority of a task.
+pub(crate) fn priority(id: usize) -> Option<u32> {
+    lock().tasks.get(&id).map(|t| t.priority)
+}
+
+/// Gets the scheduling state of a task.
+pub(crate) fn status(id: usize) -> TaskStatus {
+    let current = current();
+    let k = lock();
+    match k.tasks.get(&id) {
+        None => TaskStatus::Invalid,
+        Some(t) if t.deleted => TaskStatus::Deleted,
+        Some(_) if id == current => TaskStatus::Running,
+        Some(TaskControl { wait: None, .. }) => TaskStatus::Ready,
+        Some(TaskControl {
+            wait:
+                Some(Wait {
+                    reason: WaitReason::Suspended,
+                    ..
+                }),
+            ..
+        }) => TaskStatus::Suspended,
+        Some(_) => TaskStatus::Blocked,
+    }
+}
+
+/// Deletes a task. The host thread is parked the next time it enters the
+/// kernel, or immediately if it is the current task.
+pub(crate) fn delete(id: usize) {
+    if id == current() {
+        let mut k = lock();
+        if let Some(task) = k.tasks.get_mut(&id) {
+            task.deleted = true;
+        }
+        drop(block(k, id, WaitReason::Suspended, None));
+        unreachable!("deleted task resumed");
+    }
+    let mut k = lock();
+    if let Some(task) = k.tasks.get_mut(&id) {
+        task.deleted = true;
+        if task.wait.is_some() {
+            k.wake(id);
+        }
+    }
+}
+
+/// Sends a notification to a task.
+pub(crate) fn notify(id: usize) -> u32 {
+    let mut k = lock();
+    let Some(task) = k.tasks.get_mut(&id) else {
+        return 0;
+    };
+    task.notifications = task.notifications.saturating_add(1);
+    let waiting = matches!(&task.wait, Some(w) if w.reason == WaitReason::Notify);
+    if waiting {
+        k.wake(id);
+    }
+    1
+}
+
+/// Waits for a notification to the current task, returning the notification
+/// count prior to it being cleared or decremented.
+pub(crate) fn notify_take(clear: bool, timeout: u32) -> u32 {
+    let id = current();
+    let mut k = lock();
+    let deadline = k.deadline(timeout.max(1));
+    loop {
+        let task = k.tasks.get_mut(&id).expect("current task not registered");
+        let count = task.notifications;
+        if count > 0 {
+            task.notifications = if clear { 0 } else { count - 1 };
+            return count;
+        }
+        if k.expired(deadline) {
+            return 0;
+        }
+        k = block(k, id, WaitReason::Notify, deadline);
+    }
+}
+
+/// Creates a recursive mutex.
+pub(crate) fn mutex_create() -> usize {
+    let mut k = lock();
+    let id = k.allocate_id();
+    k.mutexes.insert(
+        id,
+        MutexControl {
+            owner: None,
+            depth: 0,
+        },
+    );
+    id
+}
+
+/// Deletes a mutex, waking any tasks waiting on it.
+pub(crate) fn mutex_delete(id: usize) {
+    let mut k = lock();
+    k.mutexes.remove(&id);
+    k.wake_all(WaitReason::Mutex(id));
+}
+
+/// Takes a recursive mutex, waiting up to `timeout` milliseconds.
+pub(crate) fn mutex_take(id: usize, timeout: u32) -> bool {
+    let task = current();
+    let mut k = lock();
+    let deadline = k.deadline(timeout);
+    loop {
+        let Some(mutex) = k.mutexes.get_mut(&id) else {
+            return false;
+        };
+        match mutex.owner {
+            None => {
+                mutex.owner = Some(task);
+                mutex.depth = 1;
+                return true;
+            }
+            Some(owner) if owner == task => {
+                mutex.depth += 1;
+                return true;
+            }
+            Some(_) => {}
+        }
+        if timeout == 0 || k.expired(deadline) {
+            return false;
+        }
+        k = block(k, task, WaitReason::Mutex(id), deadline);
+    }
+}
+
+/// Gives back a recursive mutex held by the current task.
+pub(crate) fn mutex_give(id: usize) -> bool {
+    let task = current();
+    let mut k = lock();
+    let Some(mutex) = k.mutexes.get_mut(&id) else {
+        return false;
+    };
+    if mutex.owner != Some(task) {
+        return false;
+    }
+    mutex.depth -= 1;
+    if mutex.depth == 0 {
+        mutex.owner = None;
+        k.wake_all(WaitReason::Mutex(id));
+    }
+    true
+}
+
+/// Creates a counting semaphore.
+pub(crate) fn sem_create(max: u32, init: u32) -> usize {
+    let mut k = lock();
+    let id = k.allocate_id();
+    k.semaphores.insert(
+        id,
+        SemaphoreControl {
+            count: init.min(max),
+            max,
+        },
+    );
+    id
+}
+
+/// Deletes a semaphore, waking any tasks waiting on it.
+pub(crate) fn sem_delete(id: usize) {
+    let mut k = lock();
+    k.semaphores.remove(&id);
+    k.wake_all(WaitReason::Semaphore(id));
+}
+
+/// Takes an instance of a semaphore, waiting up to `timeout` milliseconds.
+pub(crate) fn sem_wait(id: usize, timeout: u32) -> bool {
+    let task = current();
+    let mut k = lock();
+    let deadline = k.deadline(timeout);
+    loop {
+        let Some(sem) = k.semaphores.get_mut(&id) else {
+            return false;
+        };
+        if sem.count > 0 {
+            sem.count -= 1;
+            return true;
+        }
+        if timeout == 0 || k.expired(deadline) {
+            return false;
+        }
+        k = block(k, task, WaitReason::Semaphore(id), deadline);
+    }
+}
+
+/// Gives an instance of a semaphore.
+pub(crate) fn sem_post(id: usize) -> bool {
+    let mut k = lock();
+    let Some(sem) = k.semaphores.get_mut(&id) else {
+        return false;
+    };
+    if sem.count >= sem.max {
+        return false;
+    }
+    sem.count += 1;
+    k.wake_all(WaitReason::Semaphore(id));
+    true
+}
+
+/// Gets the current count of a semaphore.
+pub(crate) fn sem_count(id: usize) -> u32 {
+    lock().semaphores.get(&id).map_or(0, |s| s.count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{mpsc, Arc},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{
+        rtos::{time_since_start, Context, Loop, Mutex, ParentContext, Semaphore, Task},
+        select,
+    };
+
+    #[test]
+    fn delay_advances_virtual_clock() {
+        current();
+        let start = time_since_start();
+        Task::delay(Duration::from_millis(250));
+        assert_eq!(time_since_start(), start + Duration::from_millis(250));
+        Task::delay(Duration::ZERO);
+        assert_eq!(time_since_start(), start + Duration::from_millis(251));
+    }
+
+    #[test]
+    fn select_loop_until_deadline() {
+        current();
+        let start = time_since_start();
+        let global = Context::new_global();
+        let ctx = global.fork_with_timeout(Duration::from_millis(55));
+        let mut l = Loop::new(Duration::from_millis(10));
+        let mut cycles = 0;
+        loop {
+            select! {
+                _ = l.select() => cycles += 1,
+                _ = ctx.done() => break,
+            }
+        }
+        assert_eq!(cycles, 5);
+        assert_eq!(time_since_start(), start + Duration::from_millis(55));
+    }
+
+    #[test]
+    fn select_loop_until_cancelled() {
+        current();
+        let start = time_since_start();
+        let ctx = Context::new_global();
+        let cancel = ctx.clone();
+        Task::spawn(move || {
+            Task::delay(Duration::from_millis(25));
+            cancel.cancel();
+        })
+        .unwrap();
+        let mut l = Loop::new(Duration::from_millis(10));
+        let mut cycles = 0;
+        loop {
+            select! {
+                _ = l.select() => cycles += 1,
+                _ = ctx.done() => break,
+            }
+        }
+        assert_eq!(cycles, 2);
+        assert_eq!(time_since_start(), start + Duration::from_millis(25));
+    }
+
+    #[test]
+    fn mutex_handoff() {
+        current();
+        let start = time_since_start();
+        let mutex = Arc::new(Mutex::new(None));
+        let guard = mutex.lock();
+        let m = mutex.clone();
+        Task::spawn(move || *m.lock() = Some(time_since_start())).unwrap();
+        Task::delay(Duration::from_millis(20));
+        assert_eq!(*guard, None);
+        drop(guard);
+        Task::delay(Duration::from_millis(1));
+        assert_eq!(*mutex.lock(), Some(start + Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn semaphore_handoff() {
+        current();
+        let start = time_since_start();
+        let sem = Arc::new(Semaphore::new(1, 0));
+        let s = sem.clone();
+        Task::spawn(move || {
+            Task::delay(Duration::from_millis(30));
+            s.post().unwrap();
+        })
+        .unwrap();
+        sem.wait(Duration::from_secs(1)).unwrap();
+        assert_eq!(time_since_start(), start + Duration::from_millis(30));
+        assert!(sem.wait(Duration::from_millis(10)).is_err());
+        assert_eq!(time_since_start(), start + Duration::from_millis(40));
+        sem.post().unwrap();
+        assert!(sem.post().is_err());
+        assert_eq!(sem.count(), 1);
+    }
+
+    #[test]
+    fn notifications() {
+        let id = current();
+        let start = time_since_start();
+        Task::spawn(move || {
+            Task::delay(Duration::from_millis(15));
+            super::notify(id);
+        })
+        .unwrap();
+        assert_eq!(notify_take(true, TIMEOUT_MAX), 1);
+        assert_eq!(time_since_start(), start + Duration::from_millis(15));
+        assert_eq!(notify_take(true, 10), 0);
+        assert_eq!(time_since_start(), start + Duration::from_millis(25));
+
+        super::notify(id);
+        super::notify(id);
+        assert_eq!(notify_take(false, TIMEOUT_MAX), 2);
+        assert_eq!(notify_take(true, TIMEOUT_MAX), 1);
+        assert_eq!(time_since_start(), start + Duration::from_millis(25));
+    }
+
+    #[test]
+    fn live_tasks_share_root() {
+        current();
+        let start = next_id();
+        let wait = || {
+            notify_take(true, TIMEOUT_MAX);
+        };
+        Task::spawn_ext(
+            "mine",
+            Task::DEFAULT_PRIORITY,
+            Task::DEFAULT_STACK_DEPTH,
+            wait,
+        )
+        .unwrap();
+
+        // Tasks spawned by another thread, such as a test running in parallel,
+        // are left out.
+        let (spawned, check) = mpsc::channel();
+        let (checked, finish) = mpsc::channel::<()>();
+        let other = thread::spawn(move || {
+            Task::spawn_ext(
+                "theirs",
+                Task::DEFAULT_PRIORITY,
+                Task::DEFAULT_STACK_DEPTH,
+                wait,
+            )
+            .unwrap();
+            spawned.send(()).unwrap();
+            blocking(|| finish.recv()).unwrap();
+            delete_spawned();
+        });
+        blocking(|| check.recv()).unwrap();
+        assert_eq!(live_tasks(start..next_id()), ["mine"]);
+        checked.send(()).unwrap();
+        blocking(|| other.join()).unwrap();
+
+        delete_spawned();
+        assert!(live_tasks(start..next_id()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "task \"doomed\" panicked: oops")]
+    fn task_panic_is_raised_on_spawning_thread() {
+        Task::spawn_ext(
+            "doomed",
+            Task::DEFAULT_PRIORITY,
+            Task::DEFAULT_STACK_DEPTH,
+            || panic!("oops"),
+        )
+        .unwrap();
+        Task::delay(Duration::from_secs(1));
+    }
+}
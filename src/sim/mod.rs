@@ -0,0 +1,106 @@
+//! Host simulation backend.
+//!
+//! With the `sim` feature enabled, the crate is built on top of `std` against
+//! an in-process implementation of the PROS API instead of the real kernel, so
+//! that robot code can run as an ordinary host binary or from `cargo test`:
+//!
+//! - [`Task`](crate::rtos::Task)s run on host threads.
+//! - [`Mutex`](crate::rtos::Mutex), [`Semaphore`](crate::rtos::Semaphore) and
+//!   task notifications are provided by a small simulated kernel.
+//! - [`time_since_start()`](crate::rtos::time_since_start) follows a virtual
+//!   clock. The clock only advances when every task is blocked (e.g., in
+//!   [`Task::delay()`](crate::rtos::Task::delay) or
+//!   [`select!`](crate::select!)), at which point it jumps straight to the
+//!   earliest pending wake-up, so simulated time passes as fast as the host
+//!   allows.
+//!
+//! Any thread which uses the kernel (e.g., a `#[test]` function) is treated as
+//! a task, and holds the virtual clock while it is running.
+//!
+//! A panic in a spawned task does not bring down the process, except under
+//! [`run()`]. Instead, it is raised again on the thread which spawned the task,
+//! directly or through other tasks, the next time that thread uses the kernel.
+//!
+//! Smart ports start out empty. Unless a port has been configured with
+//! [`plug()`] or [`unplug()`], the first device API used on an empty port plugs
+//! in a device of the matching type. Controllers start out disconnected.
+//!
+//! Motors follow a DC motor model of the V5 smart motor, so their position,
+//! velocity, current draw and temperature respond to commands over time. Each
+//! motor turns freely unless given a load with [`set_motor_load()`].
+//!
+//! For unit tests, [`PeripheralsBuilder`] sets up devices with scripted
+//! readings and records the commands sent to them, and [`Match`] replays a
+//! match timeline against a [`Robot`](crate::robot::Robot) implementation.
+//!
+//! Binaries using [`entry!`](crate::entry!) get a `main` function which calls
+//! [`run()`].
+
+use crate::smart_port::DeviceType;
+
+pub(crate) mod bindings;
+mod devices;
+mod field;
+mod kernel;
+mod motor;
+mod peripherals;
+
+pub use field::*;
+pub use peripherals::*;
+
+/// Runs a robot program the same way the PROS kernel would with no field
+/// control connected: `initialize` is called, followed by `opcontrol`.
+///
+/// The process exits once no task is able to make further progress.
+///
+/// This is called by the `main` function generated by
+/// [`entry!`](crate::entry!).
+pub fn run(initialize: unsafe extern "C" fn(), opcontrol: extern "C" fn()) -> ! {
+    kernel::exit_when_idle();
+    unsafe { initialize() };
+    opcontrol();
+    kernel::suspend()
+}
+
+/// Marks a task as a service; see
+/// [`Task::mark_service()`](crate::rtos::Task::mark_service).
+pub(crate) fn mark_service(task: bindings::task_t) {
+    kernel::mark_service(task as usize)
+}
+
+/// Plugs a simulated device of the given type into a smart port, replacing
+/// any existing device.
+///
+/// Once configured this way, using a port as a device of a different type
+/// fails as it would on a real robot.
+pub fn plug(port: u8, device_type: DeviceType) {
+    assert!(
+        (1..22).contains(&port),
+        "Cannot plug a device into smart port {}",
+        port
+    );
+    devices::lock()
+        .plug(port, device_type)
+        .expect("port checked above");
+}
+
+/// Unplugs any simulated device from a smart port.
+///
+/// Once configured this way, using the port as any device fails as it would on
+/// a real robot.
+pub fn unplug(port: u8) {
+    plug(port, DeviceType::None)
+}
+
+/// Applies a constant torque, in Nm, resisting the rotation of the simulated
+/// motor on a smart port. A load greater than the stall torque of the motor's
+/// gearset stalls it. Panics if there is no motor on the port.
+pub fn set_motor_load(port: u8, torque: f64) {
+    let now = kernel::now();
+    let mut devices = devices::lock();
+    let motor = devices
+        .motor(port)
+        .unwrap_or_else(|_| panic!("No motor on smart port {}", port));
+    motor.update(now);
+    motor.load = torque.abs();
+}
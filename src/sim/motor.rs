@@ -0,0 +1,465 @@
+//! Simulated V5 smart motor.
+//!
+//! Each motor is modelled as a permanent-magnet DC motor behind its gear
+//! cartridge, driving the inertia of its own rotor against friction and an
+//! optional external load. The motor's built-in velocity and position
+//! controllers, its current and voltage limits and its thermal protection are
+//! approximated so that readings evolve plausibly over time.
+//!
+//! The model is advanced lazily, in fixed steps, whenever the motor is
+//! accessed.
+
+use core::f64::consts::PI;
+
+use crate::bindings;
+
+/// The integration step, in microseconds.
+const STEP: u64 = 1000;
+
+/// The nominal supply voltage, in volts.
+const NOMINAL_VOLTAGE: f64 = 12.0;
+
+/// The winding resistance, in ohms.
+const RESISTANCE: f64 = 3.0;
+
+/// The current drawn at stall with the default current limit, in amps.
+const STALL_CURRENT: f64 = 2.5;
+
+/// The time the unloaded motor takes to reach 63% of its free speed, in
+/// seconds.
+const TIME_CONSTANT: f64 = 0.1;
+
+/// Friction torque, as a fraction of the stall torque.
+const FRICTION: f64 = 0.02;
+
+/// Gain of the built-in velocity controller, relative to the back-EMF
+/// constant.
+const VELOCITY_GAIN: f64 = 2.0;
+
+/// Gain of the built-in position controller, in (rad/s)/rad.
+const POSITION_GAIN: f64 = 10.0;
+
+/// The ambient temperature, in degrees Celsius.
+const AMBIENT_TEMPERATURE: f64 = 25.0;
+
+/// Thermal resistance from the windings to ambient, in K/W.
+const THERMAL_RESISTANCE: f64 = 3.0;
+
+/// Heat capacity of the motor, in J/K.
+const THERMAL_CAPACITY: f64 = 30.0;
+
+/// The temperature at which the motor starts limiting its current, in degrees
+/// Celsius.
+const OVER_TEMPERATURE: f64 = 55.0;
+
+/// The temperature at which the motor stops drawing current altogether, in
+/// degrees Celsius.
+const SHUTDOWN_TEMPERATURE: f64 = 70.0;
+
+/// Converts RPM to rad/s.
+const RPM: f64 = 2.0 * PI / 60.0;
+
+/// The command most recently given to a motor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum MotorCommand {
+    /// Drive at the given voltage, in millivolts.
+    Voltage(i32),
+    /// Hold the given velocity, in RPM.
+    Velocity(i32),
+    /// Move to the given position, in degrees, at up to the given velocity.
+    Position(f64, i32),
+}
+
+/// The state of a simulated V5 smart motor.
+///
+/// Positions are stored in degrees of the output shaft, relative to the
+/// absolute encoder origin and in the motor's physical direction of rotation.
+/// The same goes for velocities, in RPM, and for voltages, currents and
+/// torques.
+pub(crate) struct MotorState {
+    pub(crate) gearset: bindings::motor_gearset_e_t,
+    pub(crate) encoder_units: bindings::motor_encoder_units_e_t,
+    pub(crate) brake_mode: bindings::motor_brake_mode_e_t,
+    pub(crate) reversed: bool,
+    pub(crate) current_limit: i32,
+    pub(crate) voltage_limit: i32,
+    pub(crate) command: MotorCommand,
+    pub(crate) position: f64,
+    pub(crate) zero: f64,
+    pub(crate) velocity: f64,
+    /// The voltage applied to the windings, in volts, or `None` if they are
+    /// left open.
+    pub(crate) voltage: Option<f64>,
+    /// The current through the windings, in amps.
+    pub(crate) current: f64,
+    /// The temperature of the windings, in degrees Celsius.
+    pub(crate) temperature: f64,
+    /// Whether the current is being limited.
+    pub(crate) over_current: bool,
+    /// An external torque resisting rotation, in Nm.
+    pub(crate) load: f64,
+    /// The position maintained by hold braking once the motor has stopped.
+    hold: Option<f64>,
+    /// The time up to which the model has been advanced, in microseconds.
+    updated: u64,
+}
+
+impl MotorState {
+    pub(crate) fn new() -> Self {
+        Self {
+            gearset: bindings::motor_gearset_e_E_MOTOR_GEARSET_18,
+            encoder_units: bindings::motor_encoder_units_e_E_MOTOR_ENCODER_DEGREES,
+            brake_mode: bindings::motor_brake_mode_e_E_MOTOR_BRAKE_COAST,
+            reversed: false,
+            current_limit: 2500,
+            voltage_limit: 0,
+            command: MotorCommand::Voltage(0),
+            position: 0.0,
+            zero: 0.0,
+            velocity: 0.0,
+            voltage: None,
+            current: 0.0,
+            temperature: AMBIENT_TEMPERATURE,
+            over_current: false,
+            load: 0.0,
+            hold: None,
+            updated: 0,
+        }
+    }
+
+    /// The direction multiplier applied to user-facing values.
+    pub(crate) fn sign(&self) -> f64 {
+        if self.reversed {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    /// The number of encoder units in one degree of output shaft rotation.
+    pub(crate) fn units_per_degree(&self) -> f64 {
+        match self.encoder_units {
+            bindings::motor_encoder_units_e_E_MOTOR_ENCODER_ROTATIONS => 1.0 / 360.0,
+            bindings::motor_encoder_units_e_E_MOTOR_ENCODER_COUNTS => match self.gearset {
+                bindings::motor_gearset_e_E_MOTOR_GEARSET_36 => 1800.0 / 360.0,
+                bindings::motor_gearset_e_E_MOTOR_GEARSET_06 => 300.0 / 360.0,
+                _ => 900.0 / 360.0,
+            },
+            _ => 1.0,
+        }
+    }
+
+    /// Converts a user-facing position to an absolute physical position in
+    /// degrees.
+    pub(crate) fn to_degrees(&self, position: f64) -> f64 {
+        self.sign() * (position / self.units_per_degree() + self.zero)
+    }
+
+    /// Converts an absolute physical position in degrees to a user-facing
+    /// position.
+    pub(crate) fn from_degrees(&self, degrees: f64) -> f64 {
+        (self.sign() * degrees - self.zero) * self.units_per_degree()
+    }
+
+    /// The free speed and stall torque of the output shaft, in RPM and Nm.
+    fn cartridge(&self) -> (f64, f64) {
+        match self.gearset {
+            bindings::motor_gearset_e_E_MOTOR_GEARSET_36 => (100.0, 2.1),
+            bindings::motor_gearset_e_E_MOTOR_GEARSET_06 => (600.0, 0.35),
+            _ => (200.0, 1.05),
+        }
+    }
+
+    /// The back-EMF constant, in V/(rad/s), and torque constant, in Nm/A.
+    fn constants(&self) -> (f64, f64) {
+        let (free_speed, stall_torque) = self.cartridge();
+        (
+            NOMINAL_VOLTAGE / (free_speed * RPM),
+            stall_torque / STALL_CURRENT,
+        )
+    }
+
+    /// The torque produced at the output shaft, in Nm.
+    pub(crate) fn torque(&self) -> f64 {
+        self.constants().1 * self.current
+    }
+
+    /// The electrical power drawn by the motor, in watts.
+    pub(crate) fn power(&self) -> f64 {
+        self.voltage.map_or(0.0, |v| (v * self.current).abs())
+    }
+
+    /// The mechanical output power as a percentage of the electrical input
+    /// power.
+    pub(crate) fn efficiency(&self) -> f64 {
+        let input = self.voltage.map_or(0.0, |v| v * self.current);
+        if input > 0.0 {
+            (100.0 * self.torque() * self.velocity * RPM / input).clamp(0.0, 100.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether the motor is hot enough to be limiting its current.
+    pub(crate) fn is_over_temp(&self) -> bool {
+        self.temperature >= OVER_TEMPERATURE
+    }
+
+    /// The current limit in effect, in amps, taking thermal protection into
+    /// account.
+    fn effective_current_limit(&self) -> f64 {
+        let derating =
+            (SHUTDOWN_TEMPERATURE - self.temperature) / (SHUTDOWN_TEMPERATURE - OVER_TEMPERATURE);
+        self.current_limit as f64 / 1000.0 * derating.clamp(0.0, 1.0)
+    }
+
+    /// Advances the model to time `now`, in microseconds.
+    pub(crate) fn update(&mut self, now: u64) {
+        while self.updated + STEP <= now {
+            if self.is_settled() {
+                self.updated = now;
+                break;
+            }
+            self.step(STEP as f64 / 1e6);
+            self.updated += STEP;
+        }
+    }
+
+    /// Whether the motor is at rest, cooled down and not being driven, so
+    /// that time can be skipped.
+    fn is_settled(&self) -> bool {
+        self.velocity == 0.0
+            && self.current == 0.0
+            && self.temperature - AMBIENT_TEMPERATURE < 1e-3
+            && matches!(
+                self.command,
+                MotorCommand::Voltage(0) | MotorCommand::Velocity(0)
+            )
+            && self.brake_mode != bindings::motor_brake_mode_e_E_MOTOR_BRAKE_HOLD
+    }
+
+    /// Computes the voltage needed to track a target velocity in rad/s.
+    fn track(&self, target: f64, ke: f64, kt: f64) -> f64 {
+        let (_, stall_torque) = self.cartridge();
+        let friction = if target.abs() > 1e-3 {
+            target.signum() * FRICTION * stall_torque / kt * RESISTANCE
+        } else {
+            0.0
+        };
+        ke * target + friction + VELOCITY_GAIN * ke * (target - self.velocity * RPM)
+    }
+
+    /// Advances the model by `dt` seconds.
+    fn step(&mut self, dt: f64) {
+        let (free_speed, stall_torque) = self.cartridge();
+        let (ke, kt) = self.constants();
+        let w = self.velocity * RPM;
+
+        let voltage = match self.command {
+            MotorCommand::Voltage(mv) if mv != 0 => {
+                self.hold = None;
+                Some(mv as f64 / 1000.0)
+            }
+            MotorCommand::Velocity(rpm) if rpm != 0 => {
+                self.hold = None;
+                let target = (rpm as f64).clamp(-free_speed, free_speed) * RPM;
+                Some(self.track(target, ke, kt))
+            }
+            MotorCommand::Position(target, rpm) => {
+                self.hold = None;
+                let max = (rpm as f64).min(free_speed) * RPM;
+                let target =
+                    (POSITION_GAIN * (target - self.position).to_radians()).clamp(-max, max);
+                Some(self.track(target, ke, kt))
+            }
+            _ => match self.brake_mode {
+                bindings::motor_brake_mode_e_E_MOTOR_BRAKE_BRAKE => Some(0.0),
+                bindings::motor_brake_mode_e_E_MOTOR_BRAKE_HOLD => {
+                    let hold = *self.hold.get_or_insert(self.position);
+                    let target = POSITION_GAIN * (hold - self.position).to_radians();
+                    Some(self.track(target, ke, kt))
+                }
+                _ => None,
+            },
+        };
+
+        let max_voltage = match self.voltage_limit {
+            0 => NOMINAL_VOLTAGE,
+            limit => (limit as f64 / 1000.0).min(NOMINAL_VOLTAGE),
+        };
+        self.voltage = voltage.map(|v| v.clamp(-max_voltage, max_voltage));
+
+        let limit = self.effective_current_limit();
+        let current = self.voltage.map_or(0.0, |v| (v - ke * w) / RESISTANCE);
+        self.over_current = current.abs() > limit;
+        self.current = current.clamp(-limit, limit);
+
+        let inertia = TIME_CONSTANT * kt * ke / RESISTANCE;
+        let friction = FRICTION * stall_torque + self.load;
+        let drive = kt * self.current;
+        let next = if w != 0.0 {
+            let next = w + (drive - friction * w.signum()) / inertia * dt;
+            // Friction and load can stop the motor, but not reverse it.
+            if next.signum() != w.signum() && drive.abs() <= friction {
+                0.0
+            } else {
+                next
+            }
+        } else if drive.abs() > friction {
+            (drive - friction * drive.signum()) / inertia * dt
+        } else {
+            0.0
+        };
+
+        self.position += ((w + next) / 2.0 * dt).to_degrees();
+        self.velocity = next / RPM;
+
+        let heat = self.current * self.current * RESISTANCE;
+        let cooling = (self.temperature - AMBIENT_TEMPERATURE) / THERMAL_RESISTANCE;
+        self.temperature += (heat - cooling) / THERMAL_CAPACITY * dt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        motor::{BrakeMode, EncoderUnits, Gearset, Motor},
+        rtos::Task,
+        sim::{set_motor_load, PeripheralsBuilder, Simulation},
+        smart_port::DeviceType,
+    };
+
+    /// Builds a simulation with a motor on port 1.
+    fn build(gearset: Gearset) -> (Motor, Simulation) {
+        let (peripherals, sim) = PeripheralsBuilder::new().port(1, DeviceType::Motor).build();
+        let motor = peripherals
+            .port01
+            .into_motor(gearset, EncoderUnits::Degrees, false)
+            .unwrap();
+        (motor, sim)
+    }
+
+    #[test]
+    fn free_speed_per_gearset() {
+        for (gearset, free_speed) in [
+            (Gearset::SixToOne, 600.0),
+            (Gearset::EighteenToOne, 200.0),
+            (Gearset::ThirtySixToOne, 100.0),
+        ] {
+            let (mut motor, _sim) = build(gearset);
+            motor.move_voltage(12000).unwrap();
+            Task::delay(Duration::from_secs(1));
+            // Friction holds the motor slightly below its free speed.
+            let velocity = motor.get_actual_velocity().unwrap();
+            assert!(
+                velocity > 0.97 * free_speed && velocity < free_speed,
+                "{}",
+                velocity
+            );
+            let current = motor.get_current_draw().unwrap();
+            assert!(current > 0 && current < 100, "{}", current);
+            assert_eq!(motor.get_temperature().unwrap(), 25.0);
+            assert!(!motor.is_over_current().unwrap());
+            assert!(!motor.is_over_temp().unwrap());
+
+            motor.move_voltage(-12000).unwrap();
+            Task::delay(Duration::from_secs(1));
+            assert!(motor.get_actual_velocity().unwrap() < -0.97 * free_speed);
+        }
+    }
+
+    #[test]
+    fn velocity_and_position_control() {
+        let (mut motor, _sim) = build(Gearset::EighteenToOne);
+        motor.move_velocity(100).unwrap();
+        Task::delay(Duration::from_secs(1));
+        let velocity = motor.get_actual_velocity().unwrap();
+        assert!((velocity - 100.0).abs() < 0.5, "{}", velocity);
+
+        motor.tare_position().unwrap();
+        motor.move_absolute(360.0, 100).unwrap();
+        Task::delay(Duration::from_secs(2));
+        let position = motor.get_position().unwrap();
+        assert!((position - 360.0).abs() < 0.1, "{}", position);
+        assert!(motor.get_actual_velocity().unwrap().abs() < 0.1);
+    }
+
+    #[test]
+    fn brake_stops_faster_than_coast() {
+        for mode in [BrakeMode::Coast, BrakeMode::Brake] {
+            let (mut motor, _sim) = build(Gearset::EighteenToOne);
+            motor.set_brake_mode(mode).unwrap();
+            motor.move_voltage(12000).unwrap();
+            Task::delay(Duration::from_secs(1));
+            let start = motor.get_position().unwrap();
+            motor.move_voltage(0).unwrap();
+            Task::delay(Duration::from_millis(500));
+            let travel = motor.get_position().unwrap() - start;
+            let velocity = motor.get_actual_velocity().unwrap();
+            match mode {
+                BrakeMode::Coast => {
+                    // Only friction slows the motor.
+                    assert!(velocity > 150.0, "{}", velocity);
+                    assert!(travel > 500.0, "{}", travel);
+                }
+                _ => {
+                    assert_eq!(velocity, 0.0);
+                    assert!(travel < 150.0, "{}", travel);
+                    let stopped = motor.get_position().unwrap();
+                    Task::delay(Duration::from_millis(500));
+                    assert_eq!(motor.get_position().unwrap(), stopped);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn current_under_load() {
+        let (mut motor, _sim) = build(Gearset::EighteenToOne);
+        motor.move_voltage(12000).unwrap();
+        set_motor_load(1, 0.5);
+        Task::delay(Duration::from_secs(1));
+        // The current balances friction and the load through the torque
+        // constant of 0.42 Nm/A.
+        let current = motor.get_current_draw().unwrap();
+        assert!((1200..1280).contains(&current), "{}", current);
+        let velocity = motor.get_actual_velocity().unwrap();
+        assert!(velocity > 130.0 && velocity < 145.0, "{}", velocity);
+        assert!(!motor.is_over_current().unwrap());
+
+        // A load beyond the stall torque stalls the motor at its current
+        // limit.
+        set_motor_load(1, 5.0);
+        Task::delay(Duration::from_secs(1));
+        assert_eq!(motor.get_actual_velocity().unwrap(), 0.0);
+        assert_eq!(motor.get_current_draw().unwrap(), 2500);
+        assert!(motor.is_over_current().unwrap());
+    }
+
+    #[test]
+    fn stall_heats_until_over_temp() {
+        let (mut motor, _sim) = build(Gearset::EighteenToOne);
+        motor.move_voltage(12000).unwrap();
+        set_motor_load(1, 5.0);
+        Task::delay(Duration::from_secs(60));
+        let temperature = motor.get_temperature().unwrap();
+        assert!(temperature > 45.0 && temperature < 55.0, "{}", temperature);
+        assert!(!motor.is_over_temp().unwrap());
+        assert_eq!(motor.get_current_draw().unwrap(), 2500);
+
+        // Once over temperature, the motor limits its current further.
+        Task::delay(Duration::from_secs(30));
+        let temperature = motor.get_temperature().unwrap();
+        assert!(temperature >= 55.0 && temperature < 60.0, "{}", temperature);
+        assert!(motor.is_over_temp().unwrap());
+        assert!(motor.get_current_draw().unwrap() < 2500);
+
+        set_motor_load(1, 0.0);
+        motor.move_voltage(0).unwrap();
+        Task::delay(Duration::from_secs(60));
+        assert!(!motor.is_over_temp().unwrap());
+        assert!(motor.get_temperature().unwrap() < 45.0);
+    }
+}
@@ -0,0 +1,500 @@
+//! Programmable peripherals for unit tests.
+
+use std::{
+    path::PathBuf,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use super::{
+    devices::{self, Devices, Hook},
+    kernel,
+};
+use crate::{
+    bindings,
+    controller::{ControllerData, ControllerId},
+    gps::GpsPose,
+    peripherals::Peripherals,
+    smart_port::DeviceType,
+    vision::{VisionObject, VisionObjectType},
+};
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// A command sent to a simulated device, as returned by
+/// [`Simulation::take_commands()`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Command {
+    /// A motor was given a target voltage, in millivolts.
+    MotorVoltage(i32),
+    /// A motor was given a target velocity, in RPM.
+    MotorVelocity(i32),
+    /// A motor was given a target absolute position and maximum velocity.
+    MotorAbsolute(f64, i32),
+    /// A motor was given a target relative position and maximum velocity.
+    MotorRelative(f64, i32),
+    /// A digital output on the given ADI port was set.
+    AdiDigital(u8, bool),
+    /// A legacy motor on the given ADI port was given a speed.
+    AdiMotor(u8, i8),
+}
+
+/// A builder for a [`Peripherals`] object backed by programmable simulated
+/// devices.
+///
+/// Readings are produced by closures, which are passed the time since
+/// [`build()`](Self::build) was called; a canned reading is simply a closure
+/// which ignores its argument. The closures are called with the simulated
+/// devices locked, so they must not use any device APIs themselves.
+///
+/// ```no_run
+/// # use vex_rt::{prelude::*, sim::*};
+/// let (peripherals, mut sim) = PeripheralsBuilder::new()
+///     .port(1, DeviceType::Motor)
+///     .controller(ControllerId::Master, |_| {
+///         Some(ControllerData {
+///             left_y: 127,
+///             ..Default::default()
+///         })
+///     })
+///     .build();
+///
+/// let mut motor = peripherals
+///     .port01
+///     .into_motor(Gearset::EighteenToOne, EncoderUnits::Degrees, false)
+///     .unwrap();
+/// let y = peripherals.master_controller.left_stick.get_y().unwrap();
+/// motor.move_i8(y).unwrap();
+///
+/// assert_eq!(sim.take_commands(1), [Command::MotorVoltage(12000)]);
+/// ```
+#[derive(Default)]
+pub struct PeripheralsBuilder {
+    ports: Vec<(u8, DeviceType)>,
+    hooks: Vec<Hook>,
+    sd_card: Option<PathBuf>,
+}
+
+impl PeripheralsBuilder {
+    /// Creates a new builder, with every smart port empty and both controllers
+    /// disconnected.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Plugs a device of the given type into a smart port; see
+    /// [`plug()`](super::plug).
+    pub fn port(mut self, port: u8, device_type: DeviceType) -> Self {
+        self.ports.push((port, device_type));
+        self
+    }
+
+    /// Scripts the inputs of a controller. The controller is disconnected
+    /// whenever `f` returns `None`.
+    pub fn controller(
+        self,
+        id: ControllerId,
+        mut f: impl FnMut(Duration) -> Option<ControllerData> + Send + 'static,
+    ) -> Self {
+        let id = bindings::controller_id_e_t::from(id);
+        self.hook(move |t, d| {
+            if let Ok(controller) = d.controller(id) {
+                let data = f(t);
+                controller.connected = data.is_some();
+                let data = data.unwrap_or_default();
+                controller.analog = [
+                    data.left_x as i32,
+                    data.left_y as i32,
+                    data.right_x as i32,
+                    data.right_y as i32,
+                ];
+                controller.digital = [
+                    data.l1, data.l2, data.r1, data.r2, data.up, data.down, data.left, data.right,
+                    data.x, data.b, data.y, data.a,
+                ];
+                controller.battery_level = data.battery_level;
+                controller.battery_capacity = data.battery_capacity;
+            }
+        })
+    }
+
+    /// Scripts the position of the rotation sensor on a smart port, in
+    /// centidegrees. The velocity is derived from the change in position.
+    pub fn rotation(self, port: u8, mut f: impl FnMut(Duration) -> i32 + Send + 'static) -> Self {
+        let mut last = None;
+        self.hook(move |t, d| {
+            if let Ok(sensor) = d.rotation(port) {
+                let position = f(t);
+                if let Some((last_t, last_position)) = last.replace((t, position)) {
+                    let dt = (t - last_t).as_secs_f64();
+                    if dt > 0.0 {
+                        sensor.velocity = ((position - last_position) as f64 / dt) as i32;
+                    }
+                }
+                sensor.position = position;
+            }
+        })
+    }
+
+    /// Scripts the rotation of the inertial sensor on a smart port about its
+    /// vertical axis, in degrees.
+    pub fn imu(self, port: u8, mut f: impl FnMut(Duration) -> f64 + Send + 'static) -> Self {
+        self.hook(move |t, d| {
+            if let Ok(sensor) = d.imu(port) {
+                sensor.rotation = f(t);
+            }
+        })
+    }
+
+    /// Scripts the true pose of the robot carrying the GPS sensor on a smart
+    /// port. The heading may be unbounded, in which case it also gives the
+    /// sensor's rotation.
+    pub fn gps(self, port: u8, mut f: impl FnMut(Duration) -> GpsPose + Send + 'static) -> Self {
+        self.hook(move |t, d| {
+            if let Ok(sensor) = d.gps(port) {
+                let pose = f(t);
+                sensor.x = pose.x;
+                sensor.y = pose.y;
+                sensor.rotation = pose.heading;
+            }
+        })
+    }
+
+    /// Scripts the reading of the distance sensor on a smart port, in
+    /// millimetres.
+    pub fn distance(self, port: u8, mut f: impl FnMut(Duration) -> i32 + Send + 'static) -> Self {
+        self.hook(move |t, d| {
+            if let Ok(sensor) = d.distance(port) {
+                sensor.distance = f(t);
+                sensor.confidence = 63;
+            }
+        })
+    }
+
+    /// Scripts the object in front of the optical sensor on a smart port:
+    /// `Some(hue)` for a close, brightly lit object with the given hue in
+    /// degrees, or `None` for no object.
+    pub fn optical(
+        self,
+        port: u8,
+        mut f: impl FnMut(Duration) -> Option<f64> + Send + 'static,
+    ) -> Self {
+        self.hook(move |t, d| {
+            if let Ok(sensor) = d.optical(port) {
+                let hue = f(t);
+                sensor.hue = hue.unwrap_or_default();
+                sensor.saturation = hue.map_or(0.0, |_| 1.0);
+                sensor.brightness = hue.map_or(0.0, |_| 1.0);
+                sensor.proximity = hue.map_or(0, |_| 255);
+            }
+        })
+    }
+
+    /// Scripts the objects seen by the vision sensor on a smart port, with
+    /// coordinates measured from the top-left corner of the field of view.
+    /// The sensor sorts them by size and applies its zero point.
+    pub fn vision(
+        self,
+        port: u8,
+        mut f: impl FnMut(Duration) -> Vec<VisionObject> + Send + 'static,
+    ) -> Self {
+        self.hook(move |t, d| {
+            if let Ok(sensor) = d.vision(port) {
+                sensor.objects = f(t)
+                    .into_iter()
+                    .map(|o| bindings::vision_object_s_t {
+                        signature: o.signature,
+                        type_: match o.object_type {
+                            VisionObjectType::Normal => {
+                                bindings::vision_object_type_E_VISION_OBJECT_NORMAL
+                            }
+                            VisionObjectType::ColorCode => {
+                                bindings::vision_object_type_E_VISION_OBJECT_COLOR_CODE
+                            }
+                            VisionObjectType::Line => {
+                                bindings::vision_object_type_E_VISION_OBJECT_LINE
+                            }
+                        },
+                        left_coord: o.left,
+                        top_coord: o.top,
+                        width: o.width,
+                        height: o.height,
+                        angle: o.angle,
+                        x_middle_coord: o.x_middle,
+                        y_middle_coord: o.y_middle,
+                    })
+                    .collect();
+            }
+        })
+    }
+
+    /// Scripts touches on the brain screen: `Some` point while the screen is
+    /// touched there, or `None` while it is not touched.
+    pub fn touch(self, mut f: impl FnMut(Duration) -> Option<(i16, i16)> + Send + 'static) -> Self {
+        self.hook(move |t, d| d.screen.touch(f(t)))
+    }
+
+    /// Scripts the left, center and right LLEMU buttons; each is pressed while
+    /// its value is `true`.
+    pub fn lcd_buttons(self, mut f: impl FnMut(Duration) -> [bool; 3] + Send + 'static) -> Self {
+        self.hook(move |t, d| {
+            if let Ok(lcd) = d.lcd() {
+                let [left, center, right] = f(t);
+                lcd.buttons = (left as u8) * bindings::LCD_BTN_LEFT as u8
+                    | (center as u8) * bindings::LCD_BTN_CENTER as u8
+                    | (right as u8) * bindings::LCD_BTN_RIGHT as u8;
+            }
+        })
+    }
+
+    /// Scripts the raw value of an ADI port: an analog reading, a digital
+    /// input (non-zero for high), encoder ticks, gyro angle in tenths of a
+    /// degree or ultrasonic distance. `smart_port` is the port of the ADI
+    /// expander, or 22 for the brain's built-in ADI ports.
+    pub fn adi(
+        self,
+        smart_port: u8,
+        adi_port: u8,
+        mut f: impl FnMut(Duration) -> i32 + Send + 'static,
+    ) -> Self {
+        self.hook(move |t, d| {
+            if let Ok(pin) = d.adi(smart_port, adi_port) {
+                pin.value = f(t);
+            }
+        })
+    }
+
+    /// Installs an SD card holding the contents of a directory on the host.
+    /// Files written to the card are written to the directory.
+    pub fn sd_card(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.sd_card = Some(dir.into());
+        self
+    }
+
+    fn hook(mut self, hook: impl FnMut(Duration, &mut Devices) + Send + 'static) -> Self {
+        self.hooks.push(Hook(Box::new(hook)));
+        self
+    }
+
+    /// Resets the simulated devices and applies the configuration, returning
+    /// the peripherals along with a handle for inspecting the simulation.
+    ///
+    /// Only one [`Simulation`] may exist at a time, so this blocks until any
+    /// other is dropped; this allows tests to run in parallel.
+    pub fn build(self) -> (Peripherals, Simulation) {
+        // The thread holding the simulation also holds the virtual clock while
+        // it is running, but must not hold it while waiting for its turn.
+        kernel::current();
+        let guard = kernel::blocking(|| LOCK.lock().unwrap_or_else(PoisonError::into_inner));
+        let now = kernel::now();
+        devices::lock().reset(now);
+        for (port, device_type) in self.ports {
+            super::plug(port, device_type);
+        }
+        let mut devices = devices::lock();
+        for hook in self.hooks {
+            devices.add_hook(hook);
+        }
+        devices.sd.root = self.sd_card;
+        drop(devices);
+        (unsafe { Peripherals::new() }, Simulation { _guard: guard })
+    }
+}
+
+/// A handle to the simulation configured by a [`PeripheralsBuilder`].
+///
+/// Dropping the handle deletes the tasks spawned, directly or otherwise, by the
+/// thread which built it, so that they cannot interfere with the next
+/// simulation. It then raises any panic which occurred in those tasks.
+pub struct Simulation {
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl Simulation {
+    /// Takes the commands sent to devices on a smart port since the last call,
+    /// oldest first. Port 22 gives commands sent to the brain's built-in ADI
+    /// ports.
+    pub fn take_commands(&mut self, port: u8) -> Vec<Command> {
+        devices::lock().take_commands(port)
+    }
+
+    /// Takes the text printed on the brain screen since the last call, oldest
+    /// first.
+    pub fn take_screen_text(&mut self) -> Vec<String> {
+        std::mem::take(&mut devices::lock().screen.text)
+    }
+
+    /// Gets the text on each line of the LLEMU, or `None` if it has not been
+    /// initialized.
+    pub fn lcd_text(&self) -> Option<Vec<String>> {
+        devices::lock().lcd().ok().map(|lcd| lcd.lines.to_vec())
+    }
+
+    /// Gets the text on each line of a controller screen, as last sent to the
+    /// controller.
+    pub fn controller_text(&self, id: ControllerId) -> Vec<String> {
+        devices::lock()
+            .controller(id.into())
+            .map(|controller| {
+                controller
+                    .text
+                    .iter()
+                    .map(|row| String::from_utf8_lossy(row).into_owned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Takes the bytes sent over the VEXlink on a smart port since the last
+    /// call, oldest first. Bytes received by another simulated port with a
+    /// matching link are not included.
+    pub fn take_link_data(&mut self, port: u8) -> Vec<u8> {
+        devices::lock()
+            .radio(port)
+            .map_or_else(|_| Vec::new(), |radio| radio.tx.drain(..).collect())
+    }
+
+    /// Delivers bytes to the VEXlink on a smart port, as if sent by the other
+    /// robot.
+    pub fn send_link_data(&mut self, port: u8, data: &[u8]) {
+        if let Ok(radio) = devices::lock().radio(port) {
+            radio.rx.extend(data);
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        kernel::delete_spawned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::{
+        motor::{EncoderUnits, Gearset},
+        rtos::{time_since_start, DataSource, Task},
+    };
+
+    #[test]
+    fn scripted_sensors() {
+        let (peripherals, _sim) = PeripheralsBuilder::new()
+            .port(1, DeviceType::Rotation)
+            .port(2, DeviceType::Distance)
+            .rotation(1, |t| t.as_millis() as i32 * 10)
+            .distance(2, |t| {
+                if t < Duration::from_millis(100) {
+                    500
+                } else {
+                    200
+                }
+            })
+            .adi(22, 1, |t| (t >= Duration::from_millis(100)) as i32)
+            .build();
+        let rotation = peripherals.port01.into_rotation(false).unwrap();
+        let distance = peripherals.port02.into_distance();
+        let button = peripherals.port_a.into_adi_digital_input().unwrap();
+
+        assert_eq!(rotation.get_position().unwrap(), 0);
+        assert_eq!(distance.get_distance().unwrap(), 500);
+        assert!(!button.read().unwrap());
+
+        Task::delay(Duration::from_millis(100));
+        assert_eq!(rotation.get_position().unwrap(), 1000);
+        assert_eq!(distance.get_distance().unwrap(), 200);
+        assert!(button.read().unwrap());
+    }
+
+    #[test]
+    fn scripted_controller() {
+        let data = ControllerData {
+            left_y: -64,
+            right_x: 127,
+            l2: true,
+            a: true,
+            battery_level: 80,
+            battery_capacity: 100,
+            ..Default::default()
+        };
+        let (peripherals, _sim) = PeripheralsBuilder::new()
+            .controller(ControllerId::Master, move |t| {
+                (t < Duration::from_millis(50)).then_some(data)
+            })
+            .build();
+        let master = &peripherals.master_controller;
+
+        assert!(master.is_connected().unwrap());
+        assert_eq!(master.read().unwrap(), data);
+        assert!(!peripherals.partner_controller.is_connected().unwrap());
+
+        Task::delay(Duration::from_millis(50));
+        assert!(!master.is_connected().unwrap());
+        assert_eq!(master.left_stick.get_y().unwrap(), 0);
+        assert!(!master.a.is_pressed().unwrap());
+    }
+
+    #[test]
+    fn take_commands() {
+        let (peripherals, mut sim) = PeripheralsBuilder::new().port(1, DeviceType::Motor).build();
+        let mut motor = peripherals
+            .port01
+            .into_motor(Gearset::EighteenToOne, EncoderUnits::Degrees, false)
+            .unwrap();
+        let mut piston = peripherals.port_b.into_adi_digital_output().unwrap();
+
+        motor.move_i8(127).unwrap();
+        motor.move_voltage(-6000).unwrap();
+        motor.move_velocity(100).unwrap();
+        piston.write(true).unwrap();
+
+        assert_eq!(
+            sim.take_commands(1),
+            [
+                Command::MotorVoltage(12000),
+                Command::MotorVoltage(-6000),
+                Command::MotorVelocity(100),
+            ]
+        );
+        assert!(sim.take_commands(1).is_empty());
+        assert_eq!(sim.take_commands(22), [Command::AdiDigital(2, true)]);
+    }
+
+    #[test]
+    fn drop_deletes_spawned_tasks() {
+        let (peripherals, sim) = PeripheralsBuilder::new().port(1, DeviceType::Motor).build();
+        let mut motor = peripherals
+            .port01
+            .into_motor(Gearset::EighteenToOne, EncoderUnits::Degrees, false)
+            .unwrap();
+        Task::spawn(move || loop {
+            motor.move_voltage(6000).unwrap();
+            Task::delay(Duration::from_millis(10));
+        })
+        .unwrap();
+        Task::delay(Duration::from_millis(25));
+        drop(sim);
+
+        let (_peripherals, mut sim) = PeripheralsBuilder::new().port(1, DeviceType::Motor).build();
+        Task::delay(Duration::from_millis(25));
+        assert!(sim.take_commands(1).is_empty());
+    }
+
+    #[test]
+    fn waiting_for_simulation_releases_clock() {
+        let (_peripherals, sim) = PeripheralsBuilder::new().build();
+        let start = time_since_start();
+        let waiter = thread::spawn(|| {
+            let (_peripherals, _sim) = PeripheralsBuilder::new().build();
+            time_since_start()
+        });
+
+        Task::delay(Duration::from_millis(100));
+        assert_eq!(time_since_start(), start + Duration::from_millis(100));
+        drop(sim);
+        assert!(waiter.join().unwrap() >= start + Duration::from_millis(100));
+    }
+}
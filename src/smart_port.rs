@@ -5,10 +5,14 @@ use crate::{
     bindings,
     distance::DistanceSensor,
     error::Error,
+    gps::GpsSensor,
     imu::InertialSensor,
+    link::{Link, LinkError, LinkRole},
     motor::{EncoderUnits, Gearset, Motor, MotorError},
+    optical::OpticalSensor,
     rotation::{RotationSensor, RotationSensorError},
     serial::Serial,
+    vision::VisionSensor,
 };
 use core::convert::{TryFrom, TryInto};
 
@@ -66,18 +70,42 @@ impl SmartPort {
         self.into()
     }
 
+    /// Converts a `SmartPort` into a [`GpsSensor`](crate::gps::GpsSensor).
+    pub fn into_gps(self) -> GpsSensor {
+        self.into()
+    }
+
     /// Converts a `SmartPort` into a
     /// [`InertialSensor`](crate::imu::InertialSensor).
     pub fn into_imu(self) -> InertialSensor {
         self.into()
     }
 
+    /// Converts a `SmartPort` into a [`Link`](crate::link::Link) with the
+    /// given ID and role.
+    #[inline]
+    pub fn into_link(self, id: &str, role: LinkRole) -> Result<Link, LinkError> {
+        (self, id, role).try_into()
+    }
+
+    /// Converts a `SmartPort` into an
+    /// [`OpticalSensor`](crate::optical::OpticalSensor).
+    pub fn into_optical(self) -> OpticalSensor {
+        self.into()
+    }
+
     /// Converts a `SmartPort` into a
     /// [`RotationSensor`](crate::rotation::RotationSensor).
     #[inline]
     pub fn into_rotation(self, reversed: bool) -> Result<RotationSensor, RotationSensorError> {
         (self, reversed).try_into()
     }
+
+    /// Converts a `SmartPort` into a
+    /// [`VisionSensor`](crate::vision::VisionSensor).
+    pub fn into_vision(self) -> VisionSensor {
+        self.into()
+    }
 }
 
 impl TryFrom<(SmartPort, Gearset, EncoderUnits, bool)> for Motor {
@@ -109,12 +137,34 @@ impl From<SmartPort> for DistanceSensor {
         unsafe { DistanceSensor::new(port.port) }
     }
 }
+
+impl From<SmartPort> for GpsSensor {
+    fn from(port: SmartPort) -> Self {
+        unsafe { GpsSensor::new(port.port) }
+    }
+}
+
 impl From<SmartPort> for InertialSensor {
     fn from(port: SmartPort) -> Self {
         unsafe { InertialSensor::new(port.port) }
     }
 }
 
+impl TryFrom<(SmartPort, &str, LinkRole)> for Link {
+    type Error = LinkError;
+
+    #[inline]
+    fn try_from((port, id, role): (SmartPort, &str, LinkRole)) -> Result<Self, Self::Error> {
+        unsafe { Link::new(port.port, id, role) }
+    }
+}
+
+impl From<SmartPort> for OpticalSensor {
+    fn from(port: SmartPort) -> Self {
+        unsafe { OpticalSensor::new(port.port) }
+    }
+}
+
 impl TryFrom<(SmartPort, bool)> for RotationSensor {
     type Error = RotationSensorError;
 
@@ -124,6 +174,12 @@ impl TryFrom<(SmartPort, bool)> for RotationSensor {
     }
 }
 
+impl From<SmartPort> for VisionSensor {
+    fn from(port: SmartPort) -> Self {
+        unsafe { VisionSensor::new(port.port) }
+    }
+}
+
 /// Represents the type of device plugged into a smart port.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DeviceType {
@@ -154,6 +210,9 @@ pub enum DeviceType {
     /// V5 Optical Sensor.
     Optical,
 
+    /// V5 GPS Sensor.
+    Gps,
+
     /// Generic serial mode.
     Serial,
 
@@ -176,6 +235,7 @@ impl From<bindings::v5_device_e_t> for DeviceType {
             bindings::v5_device_e_E_DEVICE_VISION => Self::Vision,
             bindings::v5_device_e_E_DEVICE_ADI => Self::Adi,
             bindings::v5_device_e_E_DEVICE_OPTICAL => Self::Optical,
+            bindings::v5_device_e_E_DEVICE_GPS => Self::Gps,
             bindings::v5_device_e_E_DEVICE_GENERIC => Self::Serial,
             bindings::v5_device_e_E_DEVICE_UNDEFINED => Self::Undefined,
             _ => Self::Unknown(t),
@@ -0,0 +1,423 @@
+//! # Vision Sensor API.
+
+use alloc::vec::Vec;
+
+use crate::{
+    bindings,
+    error::{get_errno, Error},
+    rtos::DataSource,
+};
+
+/// The width of the vision sensor's field of view, in pixels.
+pub const VISION_FOV_WIDTH: i16 = 316;
+
+/// The height of the vision sensor's field of view, in pixels.
+pub const VISION_FOV_HEIGHT: i16 = 212;
+
+/// A struct which represents a V5 smart port configured as a vision sensor.
+pub struct VisionSensor {
+    port: u8,
+}
+
+impl VisionSensor {
+    /// Constructs a new vision sensor.
+    ///
+    /// # Safety
+    ///
+    /// This function is unsafe because it allows the user to create multiple
+    /// mutable references to the same vision sensor. You likely want to
+    /// implement [`Robot::new()`](crate::robot::Robot::new()) instead.
+    pub unsafe fn new(port: u8) -> VisionSensor {
+        VisionSensor { port }
+    }
+
+    /// Stores a signature in the sensor, in one of the slots 1 to 7.
+    pub fn set_signature(
+        &mut self,
+        id: u8,
+        signature: &VisionSignature,
+    ) -> Result<(), VisionSensorError> {
+        let mut signature = signature.into_raw(id);
+        match unsafe { bindings::vision_set_signature(self.port, id, &mut signature) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the signature stored in one of the slots 1 to 7.
+    pub fn get_signature(&self, id: u8) -> Result<VisionSignature, VisionSensorError> {
+        match unsafe { bindings::vision_get_signature(self.port, id) } {
+            x if x.id == bindings::VISION_OBJECT_ERR_SIG as u8 => {
+                Err(VisionSensorError::from_errno())
+            }
+            x => Ok(VisionSignature::from_raw(x)),
+        }
+    }
+
+    /// Creates a color code from the signatures in the given slots, which
+    /// must already be set. Objects made up of adjacent blobs of these
+    /// signatures are then reported as color code objects. Panics unless
+    /// between 2 and 5 signatures are given.
+    pub fn create_color_code(&mut self, ids: &[u8]) -> Result<ColorCode, VisionSensorError> {
+        assert!(
+            (2..=5).contains(&ids.len()),
+            "a color code needs between 2 and 5 signatures"
+        );
+        let mut sigs = [0u32; 5];
+        for (sig, id) in sigs.iter_mut().zip(ids) {
+            *sig = *id as u32;
+        }
+        let [a, b, c, d, e] = sigs;
+        match unsafe { bindings::vision_create_color_code(self.port, a, b, c, d, e) } {
+            x if x == bindings::PROS_ERR_ as bindings::vision_color_code_t => {
+                Err(VisionSensorError::from_errno())
+            }
+            x => Ok(ColorCode(x)),
+        }
+    }
+
+    /// Gets the number of objects currently detected.
+    pub fn get_object_count(&self) -> Result<i32, VisionSensorError> {
+        match unsafe { bindings::vision_get_object_count(self.port) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Gets up to `max` detected objects of any signature, largest first.
+    pub fn get_objects_by_size(&self, max: usize) -> Result<Vec<VisionObject>, VisionSensorError> {
+        self.read_objects(max, |objects| unsafe {
+            bindings::vision_read_by_size(self.port, 0, max as u32, objects)
+        })
+    }
+
+    /// Gets up to `max` detected objects matching the signature in the given
+    /// slot, largest first.
+    pub fn get_objects_by_signature(
+        &self,
+        id: u8,
+        max: usize,
+    ) -> Result<Vec<VisionObject>, VisionSensorError> {
+        self.read_objects(max, |objects| unsafe {
+            bindings::vision_read_by_sig(self.port, 0, id as u32, max as u32, objects)
+        })
+    }
+
+    /// Gets up to `max` detected objects matching a color code, largest first.
+    pub fn get_objects_by_code(
+        &self,
+        code: ColorCode,
+        max: usize,
+    ) -> Result<Vec<VisionObject>, VisionSensorError> {
+        self.read_objects(max, |objects| unsafe {
+            bindings::vision_read_by_code(self.port, 0, code.0, max as u32, objects)
+        })
+    }
+
+    /// Sets the exposure setting of the sensor, from 0 to 150.
+    pub fn set_exposure(&mut self, exposure: u8) -> Result<(), VisionSensorError> {
+        match unsafe { bindings::vision_set_exposure(self.port, exposure) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the exposure setting of the sensor, from 0 to 150.
+    pub fn get_exposure(&self) -> Result<i32, VisionSensorError> {
+        match unsafe { bindings::vision_get_exposure(self.port) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            x => Ok(x),
+        }
+    }
+
+    /// Sets the white balance of the sensor, as a 24-bit RGB value, and
+    /// disables automatic white balance.
+    pub fn set_white_balance(&mut self, rgb: u32) -> Result<(), VisionSensorError> {
+        match unsafe { bindings::vision_set_white_balance(self.port, rgb as i32) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the white balance of the sensor, as a 24-bit RGB value.
+    pub fn get_white_balance(&self) -> Result<u32, VisionSensorError> {
+        match unsafe { bindings::vision_get_white_balance(self.port) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            x => Ok(x as u32),
+        }
+    }
+
+    /// Enables or disables automatic white balance.
+    pub fn set_auto_white_balance(&mut self, enable: bool) -> Result<(), VisionSensorError> {
+        match unsafe { bindings::vision_set_auto_white_balance(self.port, enable as u8) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the color of the sensor's LED, as a 24-bit RGB value. This
+    /// overrides the default behaviour of the LED, which indicates the color
+    /// of the largest detected object.
+    pub fn set_led(&mut self, rgb: u32) -> Result<(), VisionSensorError> {
+        match unsafe { bindings::vision_set_led(self.port, rgb as i32) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Restores the default behaviour of the sensor's LED.
+    pub fn clear_led(&mut self) -> Result<(), VisionSensorError> {
+        match unsafe { bindings::vision_clear_led(self.port) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the point which object coordinates are measured from.
+    pub fn set_zero_point(&mut self, zero_point: VisionZeroPoint) -> Result<(), VisionSensorError> {
+        match unsafe { bindings::vision_set_zero_point(self.port, zero_point.into()) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Enables or disables the sensor's Wi-Fi access point.
+    pub fn set_wifi_mode(&mut self, enable: bool) -> Result<(), VisionSensorError> {
+        match unsafe { bindings::vision_set_wifi_mode(self.port, enable as u8) } {
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            _ => Ok(()),
+        }
+    }
+
+    fn read_objects(
+        &self,
+        max: usize,
+        read: impl FnOnce(*mut bindings::vision_object_s_t) -> i32,
+    ) -> Result<Vec<VisionObject>, VisionSensorError> {
+        let mut objects = Vec::with_capacity(max);
+        match read(objects.as_mut_ptr()) {
+            // No objects were detected.
+            bindings::PROS_ERR_ if get_errno() == libc::EDOM => Ok(Vec::new()),
+            bindings::PROS_ERR_ => Err(VisionSensorError::from_errno()),
+            n => {
+                unsafe { objects.set_len((n as usize).min(max)) };
+                Ok(objects
+                    .into_iter()
+                    .filter(|o: &bindings::vision_object_s_t| {
+                        o.signature != bindings::VISION_OBJECT_ERR_SIG as u16
+                    })
+                    .map(VisionObject::from_raw)
+                    .collect())
+            }
+        }
+    }
+}
+
+impl DataSource for VisionSensor {
+    type Data = VisionSensorData;
+
+    type Error = VisionSensorError;
+
+    fn read(&self) -> Result<Self::Data, Self::Error> {
+        let count = self.get_object_count()?;
+        Ok(VisionSensorData {
+            objects: self.get_objects_by_size(count.max(0) as usize)?,
+        })
+    }
+}
+
+/// Represents the data that can be read from a vision sensor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisionSensorData {
+    /// All objects detected in the frame, largest first.
+    pub objects: Vec<VisionObject>,
+}
+
+/// Represents the color signature of objects to be detected by a vision
+/// sensor. The values can be copied from the vision utility.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VisionSignature {
+    /// The minimum value on the U axis.
+    pub u_min: i32,
+    /// The maximum value on the U axis.
+    pub u_max: i32,
+    /// The mean value on the U axis.
+    pub u_mean: i32,
+    /// The minimum value on the V axis.
+    pub v_min: i32,
+    /// The maximum value on the V axis.
+    pub v_max: i32,
+    /// The mean value on the V axis.
+    pub v_mean: i32,
+    /// The scale factor applied to the ranges.
+    pub range: f32,
+    /// The color of the signature, as a 24-bit RGB value. This is computed by
+    /// the sensor.
+    pub rgb: u32,
+    /// The type of the signature; 1 if it is part of a color code, and 0
+    /// otherwise.
+    pub signature_type: u32,
+}
+
+impl VisionSignature {
+    fn from_raw(raw: bindings::vision_signature_s_t) -> Self {
+        Self {
+            u_min: raw.u_min,
+            u_max: raw.u_max,
+            u_mean: raw.u_mean,
+            v_min: raw.v_min,
+            v_max: raw.v_max,
+            v_mean: raw.v_mean,
+            range: raw.range,
+            rgb: raw.rgb,
+            signature_type: raw.type_,
+        }
+    }
+
+    fn into_raw(self, id: u8) -> bindings::vision_signature_s_t {
+        bindings::vision_signature_s_t {
+            id,
+            _pad: [0; 3],
+            range: self.range,
+            u_min: self.u_min,
+            u_max: self.u_max,
+            u_mean: self.u_mean,
+            v_min: self.v_min,
+            v_max: self.v_max,
+            v_mean: self.v_mean,
+            rgb: self.rgb,
+            type_: self.signature_type,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// Identifies a color code created with [`VisionSensor::create_color_code()`].
+pub struct ColorCode(bindings::vision_color_code_t);
+
+impl ColorCode {
+    #[inline]
+    /// Gets the raw color code value, which is also the signature reported
+    /// for objects matching the color code.
+    pub fn into_raw(self) -> bindings::vision_color_code_t {
+        self.0
+    }
+}
+
+/// Represents an object detected by a vision sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VisionObject {
+    /// The signature slot of the object, or the color code for color code
+    /// objects.
+    pub signature: u16,
+    /// The type of the object.
+    pub object_type: VisionObjectType,
+    /// The x-coordinate of the left edge of the object.
+    pub left: i16,
+    /// The y-coordinate of the top edge of the object.
+    pub top: i16,
+    /// The width of the object.
+    pub width: i16,
+    /// The height of the object.
+    pub height: i16,
+    /// The angle of a color code object, in tenths of a degree.
+    pub angle: u16,
+    /// The x-coordinate of the middle of the object.
+    pub x_middle: i16,
+    /// The y-coordinate of the middle of the object.
+    pub y_middle: i16,
+}
+
+impl VisionObject {
+    fn from_raw(raw: bindings::vision_object_s_t) -> Self {
+        Self {
+            signature: raw.signature,
+            object_type: match raw.type_ {
+                bindings::vision_object_type_E_VISION_OBJECT_COLOR_CODE => {
+                    VisionObjectType::ColorCode
+                }
+                bindings::vision_object_type_E_VISION_OBJECT_LINE => VisionObjectType::Line,
+                _ => VisionObjectType::Normal,
+            },
+            left: raw.left_coord,
+            top: raw.top_coord,
+            width: raw.width,
+            height: raw.height,
+            angle: raw.angle,
+            x_middle: raw.x_middle_coord,
+            y_middle: raw.y_middle_coord,
+        }
+    }
+}
+
+/// Represents the possible types of objects detected by a vision sensor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisionObjectType {
+    /// An object matching a single signature.
+    Normal,
+    /// An object matching a color code.
+    ColorCode,
+    /// A line.
+    Line,
+}
+
+/// Represents the possible points from which object coordinates are
+/// measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VisionZeroPoint {
+    /// The top-left corner of the field of view.
+    TopLeft,
+    /// The centre of the field of view.
+    Center,
+}
+
+impl From<VisionZeroPoint> for bindings::vision_zero_e_t {
+    fn from(zero_point: VisionZeroPoint) -> Self {
+        match zero_point {
+            VisionZeroPoint::TopLeft => bindings::vision_zero_E_VISION_ZERO_TOPLEFT,
+            VisionZeroPoint::Center => bindings::vision_zero_E_VISION_ZERO_CENTER,
+        }
+    }
+}
+
+/// Represents possible errors for vision sensor operations.
+#[derive(Debug)]
+pub enum VisionSensorError {
+    /// Port is out of range (1-21).
+    PortOutOfRange,
+    /// Port cannot be configured as a vision sensor.
+    PortNotVisionSensor,
+    /// The signature slot is out of range (1-7), or the signature has not
+    /// been set.
+    InvalidSignature,
+    /// The sensor could not be read.
+    ReadFailed,
+    /// Unknown error.
+    Unknown(i32),
+}
+
+impl VisionSensorError {
+    fn from_errno() -> Self {
+        match get_errno() {
+            libc::ENXIO => Self::PortOutOfRange,
+            libc::ENODEV => Self::PortNotVisionSensor,
+            libc::EINVAL => Self::InvalidSignature,
+            libc::EHOSTDOWN => Self::ReadFailed,
+            x => Self::Unknown(x),
+        }
+    }
+}
+
+impl From<VisionSensorError> for Error {
+    fn from(err: VisionSensorError) -> Self {
+        match err {
+            VisionSensorError::PortOutOfRange => Error::Custom("port out of range".into()),
+            VisionSensorError::PortNotVisionSensor => {
+                Error::Custom("port not a vision sensor".into())
+            }
+            VisionSensorError::InvalidSignature => Error::Custom("invalid signature".into()),
+            VisionSensorError::ReadFailed => Error::Custom("failed to read vision sensor".into()),
+            VisionSensorError::Unknown(n) => Error::System(n),
+        }
+    }
+}